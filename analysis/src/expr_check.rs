@@ -1,25 +1,28 @@
-use std::collections::HashMap;
-use parse::ast:: {
-    ProgramNode,
-    TypeDef,
-};
-use lex::token::Token;
-use parse::parser::parse;
-use lex::lexer::Lexer;
+use parse::ast::ProgramNode;
 use parse::symbol_table::TopLevelScope;
-use crate::local_resolver::local_resolver;
+use parse::error::SemanticError;
 
-pub fn check_expr(ast: &ProgramNode, mut symboltable: &mut TopLevelScope) {
+pub fn check_expr(ast: &ProgramNode, symboltable: &mut TopLevelScope) -> Result<(), SemanticError> {
     let funcs = &ast.defs.func_defs;
 
+    for var in &ast.defs.var_defs {
+        var.check_expr_validity(symboltable)?;
+    }
     for func in funcs {
-        func.check_expr_validity(&mut symboltable);
+        func.check_expr_validity(symboltable)?;
     }
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use parse::parser::parse;
+    use lex::lexer::Lexer;
+    use crate::local_resolver::local_resolver;
+    use parse::error::SemanticErrorType;
+    use lex::location::Location;
 
     #[test]
     fn test_check_expr() {
@@ -43,10 +46,11 @@ mod tests {
             int main(int argc,char **argv) {
                 struct student a;
                 int ba1 = 1;
+                int i;
                 int[] aa;
                 int[10] bb = aa;
                 bb[ba1] = 1 * 2 << 3 && 4 + 5 / 6 + calc(2);
-                a.age = 2 + calc(a);
+                a.age = 2 + calc(a.age);
                 &a;
                 for(i = 0; i < 10; i++) {
                     int ca = 1;
@@ -70,11 +74,490 @@ mod tests {
                 
             }
         "));
-        let ast = parse(&mut lxr);
+        let ast = parse(&mut lxr).unwrap();
         println!(" === {:?} ", ast);
-        let mut symboltable = local_resolver(&ast);
+        let mut symboltable = local_resolver(&ast).unwrap();
         println!("symboltable : {:?}", symboltable);
-        check_expr(&ast, &mut symboltable);
+        check_expr(&ast, &mut symboltable).unwrap();
     }
-}
 
+    #[test]
+    fn test_check_expr_error() {
+        let mut lxr = Lexer::new(String::from("
+            struct student {
+                int age;
+            };
+
+            int main() {
+                struct student a;
+                char c;
+                a.name = 1;
+                c = 1;
+                1 = c;
+                return 0;
+            }
+        "));
+        let ast = parse(&mut lxr).unwrap();
+        let mut symboltable = local_resolver(&ast).unwrap();
+        let err = check_expr(&ast, &mut symboltable).unwrap_err();
+        assert_eq!(SemanticErrorType::NoMember(String::from("a"), String::from("name")), err.error);
+        assert_eq!(Some(Location::new(9, 17)), err.span.map(|span| span.start));
+    }
+
+    #[test]
+    fn test_check_compound_and_conditional() {
+        let mut lxr = Lexer::new(String::from("
+            int main() {
+                int a;
+                int b;
+                a += b <<= 2;
+                a = b > 1 ? a : b;
+                return 0;
+            }
+        "));
+        let ast = parse(&mut lxr).unwrap();
+        let mut symboltable = local_resolver(&ast).unwrap();
+        check_expr(&ast, &mut symboltable).unwrap();
+
+        let mut lxr = Lexer::new(String::from("
+            int main() {
+                int a;
+                char *c;
+                a = a ? a : c;
+                (a ? a : a) = 1;
+                return 0;
+            }
+        "));
+        let ast = parse(&mut lxr).unwrap();
+        let mut symboltable = local_resolver(&ast).unwrap();
+        let err = check_expr(&ast, &mut symboltable).unwrap_err();
+        assert_eq!(SemanticErrorType::IncompatibleTypes(String::from("int"), String::from("char *")), err.error);
+        assert_eq!(Some(Location::new(5, 21)), err.span.map(|span| span.start));
+
+        let mut lxr = Lexer::new(String::from("
+            int main() {
+                int a;
+                a ? a : a = 1;
+                return 0;
+            }
+        "));
+        let ast = parse(&mut lxr).unwrap();
+        let mut symboltable = local_resolver(&ast).unwrap();
+        let err = check_expr(&ast, &mut symboltable).unwrap_err();
+        assert!(matches!(err.error, SemanticErrorType::NotLeftValue(_)));
+    }
+
+    #[test]
+    fn test_check_switch() {
+        let check = |source: &str| {
+            let ast = parse(&mut Lexer::new(String::from(source))).unwrap();
+            let mut symboltable = local_resolver(&ast).unwrap();
+            check_expr(&ast, &mut symboltable)
+        };
+
+        check("
+            int main() {
+                int a;
+                switch (a) {
+                    case 1: case -1: a = 1;
+                    case 'a': { int b; b = a; break; }
+                    case (1 << 3) + 2 * 3: default: break;
+                }
+                return 0;
+            }
+        ").unwrap();
+
+        let err = check("
+            int main() {
+                int a;
+                switch (a) {
+                    case 3: break;
+                    case 1 + 2: break;
+                }
+                return 0;
+            }
+        ").unwrap_err();
+        assert!(matches!(err.error, SemanticErrorType::DuplicateCase(3, _)));
+        assert_eq!(Some(Location::new(6, 21)), err.span.map(|span| span.start));
+
+        let err = check("
+            int main() {
+                int a;
+                switch (a) { default: break; default: break; }
+                return 0;
+            }
+        ").unwrap_err();
+        assert!(matches!(err.error, SemanticErrorType::DuplicateDefault(_)));
+
+        let err = check("
+            int main() {
+                int a;
+                switch (a) { case a: break; }
+                return 0;
+            }
+        ").unwrap_err();
+        assert_eq!(SemanticErrorType::NotConstant, err.error);
+
+        // 控制表达式可以是char、long和枚举，不能是浮点数和指针
+        check("
+            enum color { RED };
+            int main() {
+                char c;
+                long l;
+                enum color e;
+                switch (c) { case 'a': break; }
+                switch (l) { case 1: break; }
+                switch (e) { case RED: break; }
+                return 0;
+            }
+        ").unwrap();
+        for (decl, found) in [("double d", "double"), ("char *d", "char *")] {
+            let err = check(&format!("
+                int main() {{
+                    {};
+                    switch (d) {{ case 1: break; }}
+                    return 0;
+                }}
+            ", decl)).unwrap_err();
+            assert_eq!(SemanticErrorType::IncompatibleTypes(String::from("int"), String::from(found)), err.error);
+            assert_eq!(Some(Location::new(4, 29)), err.span.map(|span| span.start));
+        }
+    }
+
+    #[test]
+    fn test_check_goto() {
+        let mut lxr = Lexer::new(String::from("
+            int main() {
+                int a;
+                goto end;
+            loop:
+                a = a + 1;
+                if (a < 10) { goto loop; }
+            end:
+                return a;
+            }
+
+            int calc() {
+                goto loop;
+            }
+        "));
+        let ast = parse(&mut lxr).unwrap();
+        let mut symboltable = local_resolver(&ast).unwrap();
+        let err = check_expr(&ast, &mut symboltable).unwrap_err();
+        assert_eq!(SemanticErrorType::UndefinedLabel(String::from("loop")), err.error);
+        assert_eq!(Some(Location::new(13, 17)), err.span.map(|span| span.start));
+    }
+
+    #[test]
+    fn test_check_func_pointer() {
+        let check = |source: &str| {
+            let ast = parse(&mut Lexer::new(String::from(source))).unwrap();
+            let mut symboltable = local_resolver(&ast).unwrap();
+            check_expr(&ast, &mut symboltable)
+        };
+
+        check("
+            int apply(int (*f)(int, char), int v) {
+                return f(v, 'a');
+            }
+
+            int main() {
+                int (*cb)(int, char) = calc;
+                int r;
+                cb = calc;
+                r = cb(1, 'b') + apply(calc, 2);
+                return r;
+            }
+
+            int calc(int a, char b) {
+                return a;
+            }
+        ").unwrap();
+
+        let err = check("
+            int main() {
+                int (*cb)(int);
+                cb = calc;
+                return 0;
+            }
+
+            int calc(int a, char *b) {
+                return a;
+            }
+        ").unwrap_err();
+        // 函数名当成函数指针，类型按C的写法显示
+        let error = SemanticErrorType::IncompatibleTypes(String::from("int (*)(int)"), String::from("int (*)(int, char *)"));
+        assert_eq!(error, err.error);
+
+        let err = check("
+            int main() {
+                int (*cb)(int, char);
+                cb(1);
+                return 0;
+            }
+        ").unwrap_err();
+        assert_eq!(SemanticErrorType::ArgumentCount(String::from("cb"), 2, 1), err.error);
+
+        let err = check("
+            int main() {
+                int (*cb)(int, char);
+                cb(1, \"b\");
+                return 0;
+            }
+        ").unwrap_err();
+        assert!(matches!(err.error, SemanticErrorType::IncompatibleTypes(_, _)));
+        assert_eq!(Some(Location::new(4, 23)), err.span.map(|span| span.start));
+
+        let err = check("
+            int main() {
+                int cb;
+                cb(1);
+                return 0;
+            }
+        ").unwrap_err();
+        assert_eq!(SemanticErrorType::NotFunction(String::from("cb")), err.error);
+    }
+
+    #[test]
+    fn test_check_const() {
+        let check = |source: &str| {
+            let ast = parse(&mut Lexer::new(String::from(source))).unwrap();
+            let mut symboltable = local_resolver(&ast).unwrap();
+            check_expr(&ast, &mut symboltable)
+        };
+
+        check("
+            const int limit = 10;
+            int main() {
+                const char *s;
+                char *t;
+                volatile unsigned long long n;
+                unsigned int m;
+                s = t;
+                m = limit;
+                return 0;
+            }
+        ").unwrap();
+
+        let err = check("
+            const int limit = 10;
+            int main() {
+                limit = 1;
+                return 0;
+            }
+        ").unwrap_err();
+        assert_eq!(SemanticErrorType::AssignToConst(String::from("limit")), err.error);
+
+        let assign = ["a[0] = 1;", "a[1] += 2;", "++a[2];", "++p.x;", "p.x = 3;"];
+        for stmt in assign.iter() {
+            let err = check(&format!("
+                struct point {{ int x; }};
+                int main() {{
+                    const int[3] a;
+                    const struct point p;
+                    {}
+                    return 0;
+                }}
+            ", stmt)).unwrap_err();
+            assert!(matches!(err.error, SemanticErrorType::AssignToConst(_)), "{}", stmt);
+        }
+
+        // 条件、返回值和初始值里的表达式也要检查
+        let stmts = ["if (c = 2) {}", "while (c++) {}", "do {} while (c += 1);", "return c = 2;", "int x = c++;"];
+        for stmt in stmts.iter() {
+            let err = check(&format!("
+                int main() {{
+                    const int c;
+                    {}
+                    return 0;
+                }}
+            ", stmt)).unwrap_err();
+            assert_eq!(SemanticErrorType::AssignToConst(String::from("c")), err.error, "{}", stmt);
+        }
+    }
+
+    #[test]
+    fn test_check_init_list() {
+        let check = |source: &str| {
+            let ast = parse(&mut Lexer::new(String::from(source))).unwrap();
+            let mut symboltable = local_resolver(&ast).unwrap();
+            check_expr(&ast, &mut symboltable)
+        };
+
+        check("
+            struct point { int x; int y; };
+            struct line { struct point from; struct point to; };
+            int[3] table = {1, 2, 3};
+            struct line l = {{1, 2}, .to = {.y = 4}};
+            int main() {
+                int[2][2] m = {{1, 2}, [1] = {3}};
+                struct point p = {.y = 2, .x = 1};
+                char *s = {\"init\"};
+                return 0;
+            }
+        ").unwrap();
+
+        let errors = [
+            ("int[2] a = {1, 2, 3};", SemanticErrorType::ExcessElements),
+            ("struct point p = {1, 2, 3};", SemanticErrorType::ExcessElements),
+            ("int a = {1, 2};", SemanticErrorType::ExcessElements),
+            ("int[2] a = {[2] = 1};", SemanticErrorType::InvalidDesignator(String::from("[2]"))),
+            ("int[2] a = {.x = 1};", SemanticErrorType::InvalidDesignator(String::from(".x"))),
+            ("struct point p = {.z = 1};", SemanticErrorType::NoMember(String::from("point"), String::from("z"))),
+        ];
+        for (stmt, error) in errors.iter() {
+            let err = check(&format!("
+                struct point {{ int x; int y; }};
+                int main() {{
+                    {}
+                    return 0;
+                }}
+            ", stmt)).unwrap_err();
+            assert_eq!(*error, err.error, "{}", stmt);
+        }
+
+        let err = check("
+            struct point { int x; int y; };
+            int main() {
+                struct point p = {1, \"two\"};
+                return 0;
+            }
+        ").unwrap_err();
+        assert!(matches!(err.error, SemanticErrorType::IncompatibleTypes(_, _)));
+
+        // 取地址和解引用会改变初始值的类型，整数之间可以互相初始化
+        check("
+            struct point { int x; int y; };
+            struct point p;
+            int[4] arr;
+            int main() {
+                int x;
+                int *px = &x;
+                int y = *px;
+                int *py = &p.y;
+                int *first = &arr[0];
+                int *start = arr;
+                char c = 1;
+                long l = 1;
+                short s = l;
+                unsigned char u = y + c;
+                double d = *px;
+                return 0;
+            }
+        ").unwrap();
+
+        // 不是初始化列表的初始值也要和声明的类型兼容
+        for stmt in ["int x = p;", "char *s = 1.5;", "double d = s;", "int *q = *s;", "int y = &p.x;"].iter() {
+            let err = check(&format!("
+                struct point {{ int x; int y; }};
+                struct point p;
+                char *s;
+                int main() {{
+                    {}
+                    return 0;
+                }}
+            ", stmt)).unwrap_err();
+            assert!(matches!(err.error, SemanticErrorType::IncompatibleTypes(_, _)), "{}", stmt);
+        }
+        // 类型按C的写法显示
+        let err = check("
+            int main() {
+                int x;
+                double d = &x;
+                return 0;
+            }
+        ").unwrap_err();
+        assert_eq!(SemanticErrorType::IncompatibleTypes(String::from("double"), String::from("int *")), err.error);
+
+        let err = check("
+            struct point { int x; int y; };
+            struct point p;
+            int main() {
+                int z = *p.x;
+                return 0;
+            }
+        ").unwrap_err();
+        assert_eq!(SemanticErrorType::NotArray(String::from("x")), err.error);
+    }
+
+    #[test]
+    fn test_for_scope() {
+        let resolve = |source: &str| {
+            let ast = parse(&mut Lexer::new(String::from(source))).unwrap();
+            let mut symboltable = local_resolver(&ast)?;
+            check_expr(&ast, &mut symboltable)
+        };
+
+        resolve("
+            int main() {
+                int n;
+                for (int i = 0; i < 10; i++) {
+                    int i;
+                    n = i;
+                }
+                for (int i = 0; ; ) {
+                    n = i;
+                    break;
+                }
+                return n;
+            }
+        ").unwrap();
+
+        let err = resolve("
+            int main() {
+                int n;
+                for (int i = 0; i < 10; i++) {}
+                n = i;
+                return n;
+            }
+        ").unwrap_err();
+        assert_eq!(SemanticErrorType::UndefinedSymbol(String::from("i")), err.error);
+
+        // 三个子句都要检查
+        let clauses = ["i = p; i; i = 1", "i = 0; i; i = \"s\"", "; j = 1;", "c = 0;;", "int k = 0; k; c++"];
+        for clause in clauses.iter() {
+            let err = resolve(&format!("
+                struct point {{ int x; }};
+                int main() {{
+                    int i;
+                    const int c;
+                    struct point p;
+                    for ({}) {{}}
+                    return 0;
+                }}
+            ", clause)).unwrap_err();
+            assert!(matches!(err.error, SemanticErrorType::IncompatibleTypes(_, _) | SemanticErrorType::UndefinedSymbol(_) | SemanticErrorType::AssignToConst(_)), "{}", clause);
+        }
+    }
+
+    #[test]
+    fn test_declaration_point() {
+        let check = |source: &str| {
+            let ast = parse(&mut Lexer::new(String::from(source))).unwrap();
+            let mut symboltable = local_resolver(&ast).unwrap();
+            check_expr(&ast, &mut symboltable)
+        };
+
+        let err = check("
+            int main() {
+                y = 2;
+                int y;
+                return 0;
+            }
+        ").unwrap_err();
+        assert_eq!(SemanticErrorType::UndefinedSymbol(String::from("y")), err.error);
+
+        // 内层的声明之前用的还是外层的变量
+        check("
+            int main() {
+                char *s;
+                {
+                    s = \"outer\";
+                    int s;
+                    s = 1;
+                }
+                return 0;
+            }
+        ").unwrap();
+    }
+}
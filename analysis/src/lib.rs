@@ -1,3 +1,6 @@
+// 项目里的函数统一用显式的return返回值
+#![allow(clippy::needless_return)]
+
 pub mod local_resolver;
 pub mod type_def_check;
 pub mod expr_check;
\ No newline at end of file
@@ -1,33 +1,37 @@
-use std::collections::HashMap;
 use parse::ast:: {
     ProgramNode,
     TopDefNode,
 };
-use lex::lexer::Lexer;
-use parse::parser::parse;
 use parse::symbol_table::TopLevelScope;
+use parse::error::SemanticError;
 
-pub fn local_resolver(ast: &ProgramNode) -> TopLevelScope {
+pub fn local_resolver(ast: &ProgramNode) -> Result<TopLevelScope, SemanticError> {
     return get_symboltable(&ast.defs)
 }
 
-fn get_symboltable(node: &TopDefNode) -> TopLevelScope {
+fn get_symboltable(node: &TopDefNode) -> Result<TopLevelScope, SemanticError> {
     let mut scope = TopLevelScope::new();
     for var in &node.var_defs {
-        var.fill_symbol(&mut scope);
+        var.fill_symbol(&mut scope)?;
     }
 
     for func in &node.func_defs {
-        func.fill_symbol(&mut scope);
+        func.fill_symbol(&mut scope)?;
     }
 
-    return scope
+    return Ok(scope)
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use parse::parser::parse;
+    use lex::lexer::Lexer;
+    use lex::location::Location;
+    use parse::error::SemanticErrorType;
+    use parse::ast::TypeDef;
+    use lex::token::Token;
 
     #[test]
     fn test_local_resolver() {
@@ -79,7 +83,334 @@ mod tests {
                 return a;
             }
         "));
-        let ast = parse(&mut lxr);
-        println!("{:?}", local_resolver(&ast));
+        let ast = parse(&mut lxr).unwrap();
+        println!("{:?}", local_resolver(&ast).unwrap());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_redefinition() {
+        let mut lxr = Lexer::new(String::from("
+            int main(int argc, char **argv) {
+                int a;
+                char a;
+                return 0;
+            }
+        "));
+        let ast = parse(&mut lxr).unwrap();
+        let err = local_resolver(&ast).unwrap_err();
+        match err.error {
+            SemanticErrorType::Redefinition(name, prev) => {
+                assert_eq!("a", name);
+                assert_eq!(Location::new(3, 17), prev.start);
+            },
+            _ => panic!("expect redefinition, found {:?}", err),
+        }
+        assert_eq!(Some(Location::new(4, 17)), err.span.map(|span| span.start));
+
+        let ast = parse(&mut Lexer::new(String::from("
+            union u {
+                int a;
+                int a;
+            };
+        "))).unwrap();
+        let err = local_resolver(&ast).unwrap_err();
+        match err.error {
+            SemanticErrorType::Redefinition(name, prev) => {
+                assert_eq!("a", name);
+                assert_eq!(Location::new(3, 17), prev.start);
+            },
+            _ => panic!("expect redefinition, found {:?}", err),
+        }
+        assert_eq!(Some(Location::new(4, 17)), err.span.map(|span| span.start));
+    }
+
+    #[test]
+    fn test_duplicate_label() {
+        let mut lxr = Lexer::new(String::from("
+            int main() {
+                retry: ;
+                {
+                    retry: return 1;
+                }
+                return 0;
+            }
+
+            int calc() {
+                retry: return 0;
+            }
+        "));
+        let ast = parse(&mut lxr).unwrap();
+        let err = local_resolver(&ast).unwrap_err();
+        match err.error {
+            SemanticErrorType::Redefinition(name, prev) => {
+                assert_eq!("retry", name);
+                assert_eq!(Location::new(3, 17), prev.start);
+            },
+            _ => panic!("expect redefinition, found {:?}", err),
+        }
+        assert_eq!(Some(Location::new(5, 21)), err.span.map(|span| span.start));
+    }
+
+    #[test]
+    fn test_typedef() {
+        let mut lxr = Lexer::new(String::from("
+            typedef struct node Node;
+            typedef Node *NodePtr;
+            typedef NodePtr (*Next)(NodePtr);
+            struct node {
+                NodePtr next;
+            };
+            int main(NodePtr p) {
+                Next f;
+                return 0;
+            }
+        "));
+        let ast = parse(&mut lxr).unwrap();
+        let mut symboltable = local_resolver(&ast).unwrap();
+        let member = &symboltable.global_define_map["node"].member_list[0];
+        assert_eq!(Token::Struct, member.typeref.type_base.base);
+        assert_eq!(vec![TypeDef::Pointer], member.typeref.nested_def);
+        assert_eq!(vec![TypeDef::Pointer], symboltable.func_map["main"].params.params[0].typeref.nested_def);
+
+        symboltable.push_func(String::from("main"));
+        symboltable.push_block();
+        // 进入block之后要走到声明才能找到变量
+        assert!(symboltable.get_var(&String::from("f")).is_none());
+        symboltable.declare(&String::from("f"));
+        let f = symboltable.get_var(&String::from("f")).unwrap();
+        match f.typeref.nested_def.as_slice() {
+            // 返回值是指针，所以Func前面还有一层Pointer
+            [TypeDef::Pointer, TypeDef::Func(func), TypeDef::Pointer] => {
+                assert_eq!(Token::Struct, func.params[0].type_base.base);
+                assert_eq!(vec![TypeDef::Pointer], func.return_type.nested_def);
+            },
+            nested => panic!("expect function pointer, found {:?}", nested),
+        }
+
+        let mut lxr = Lexer::new(String::from("
+            typedef int Int;
+            typedef char Int;
+        "));
+        let ast = parse(&mut lxr).unwrap();
+        let err = local_resolver(&ast).unwrap_err();
+        assert!(matches!(err.error, SemanticErrorType::Redefinition(_, _)));
+
+        // 块里的变量可以和typedef同名，全局变量不行
+        let mut lxr = Lexer::new(String::from("
+            typedef int A;
+            int main() {
+                int A;
+                A = 1;
+                for (int A = 0; A; A = 0) {}
+                return A;
+            }
+            A f(A a) {
+                A b = a;
+                return b;
+            }
+        "));
+        let ast = parse(&mut lxr).unwrap();
+        local_resolver(&ast).unwrap();
+
+        let mut lxr = Lexer::new(String::from("
+            typedef int A;
+            int A;
+        "));
+        let ast = parse(&mut lxr).unwrap();
+        let err = local_resolver(&ast).unwrap_err();
+        match err.error {
+            SemanticErrorType::Redefinition(name, prev) => {
+                assert_eq!("A", name);
+                assert_eq!(Location::new(2, 13), prev.start);
+            },
+            error => panic!("expect redefinition, found {:?}", error),
+        }
+        assert_eq!(Some(Location::new(3, 13)), err.span.map(|span| span.start));
+
+        // 没定义的类型报错时指向用到它的声明
+        let mut lxr = Lexer::new(String::from("
+            struct s {
+                enum color c;
+            };
+        "));
+        let ast = parse(&mut lxr).unwrap();
+        let err = local_resolver(&ast).unwrap_err();
+        assert_eq!(SemanticErrorType::UndefinedEnum(String::from("color")), err.error);
+        assert_eq!(Some(Location::new(3, 17)), err.span.map(|span| span.start));
+    }
+
+    #[test]
+    fn test_prototype() {
+        let resolve = |source: &str| local_resolver(&parse(&mut Lexer::new(String::from(source))).unwrap());
+
+        let symboltable = resolve("
+            int calc(int a);
+            int main() {
+                return calc(1);
+            }
+            int calc(int b) {
+                return b;
+            }
+            int calc(int c);
+        ").unwrap();
+        assert!(symboltable.func_map["calc"].block.is_some());
+        assert_eq!("b", symboltable.func_map["calc"].params.params[0].name);
+
+        // 声明的参数没有名字，和定义的签名还是一样的
+        let symboltable = resolve("
+            int f(int);
+            int puts(const char *);
+            int main(void) {
+                return f(1);
+            }
+            int f(int a) {
+                return a;
+            }
+        ").unwrap();
+        assert_eq!("a", symboltable.func_map["f"].params.params[0].name);
+        assert!(symboltable.func_map["puts"].block.is_none());
+
+        let err = resolve("
+            int calc(int a);
+            int calc(char a) {
+                return 0;
+            }
+        ").unwrap_err();
+        assert!(matches!(err.error, SemanticErrorType::ConflictingTypes(name, _) if name == "calc"));
+        assert_eq!(Some(Location::new(3, 13)), err.span.map(|span| span.start));
+
+        let err = resolve("
+            int calc(int a) { return a; }
+            char *calc(int a);
+        ").unwrap_err();
+        assert!(matches!(err.error, SemanticErrorType::ConflictingTypes(_, _)));
+
+        let err = resolve("
+            int calc(int a);
+            int calc(int a) { return a; }
+            int calc(int a) { return a; }
+        ").unwrap_err();
+        assert!(matches!(err.error, SemanticErrorType::Redefinition(_, _)));
+
+        // extern声明之后的定义补全了这个变量
+        let symboltable = resolve("
+            extern int e;
+            int e = 2;
+            extern int e;
+        ").unwrap();
+        let e = symboltable.get_var(&String::from("e")).unwrap();
+        assert!(!e.typeref.is_extern());
+        assert_eq!(Location::new(3, 13), e.span.start);
+
+        let err = resolve("
+            extern int e;
+            char e;
+        ").unwrap_err();
+        assert!(matches!(err.error, SemanticErrorType::ConflictingTypes(name, _) if name == "e"));
+        assert_eq!(Some(Location::new(3, 13)), err.span.map(|span| span.start));
+
+        let err = resolve("
+            extern int e;
+            int e = 1;
+            int e = 2;
+        ").unwrap_err();
+        assert!(matches!(err.error, SemanticErrorType::Redefinition(name, _) if name == "e"));
+
+        let err = resolve("
+            int main() {
+                extern int e;
+                int e;
+                return e;
+            }
+        ").unwrap_err();
+        assert!(matches!(err.error, SemanticErrorType::Redefinition(_, _)));
+    }
+
+    #[test]
+    fn test_union_layout() {
+        let mut lxr = Lexer::new(String::from("
+            union value {
+                char c;
+                int[3] a;
+                double d;
+            };
+            struct tagged {
+                char tag;
+                union value v;
+            };
+        "));
+        let ast = parse(&mut lxr).unwrap();
+        let symboltable = local_resolver(&ast).unwrap();
+        assert_eq!(Token::Union, symboltable.global_define_map["value"].kind);
+        let (offsets, size) = symboltable.struct_layout(&String::from("value")).unwrap();
+        assert_eq!(0, offsets["c"]);
+        assert_eq!(0, offsets["a"]);
+        assert_eq!(0, offsets["d"]);
+        assert_eq!(16, size);
+        let (offsets, size) = symboltable.struct_layout(&String::from("tagged")).unwrap();
+        assert_eq!(8, offsets["v"]);
+        assert_eq!(24, size);
+
+        // struct和union的标签在同一个命名空间里，种类不对要报错
+        let sources = [
+            "union value { int i; }; struct value v;",
+            "struct point { int x; }; union point *p;",
+            "union value { int i; }; struct holder { struct value v; };",
+        ];
+        for source in sources.iter() {
+            let ast = parse(&mut Lexer::new(source.to_string())).unwrap();
+            let err = local_resolver(&ast).unwrap_err();
+            assert!(matches!(err.error, SemanticErrorType::TagMismatch(_, _)), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_enum() {
+        let resolve = |source: &str| local_resolver(&parse(&mut Lexer::new(String::from(source))).unwrap());
+
+        let symboltable = resolve("
+            enum color { RED, GREEN = 5, BLUE };
+            enum { SIZE = BLUE * 2 };
+            enum color c;
+            int[SIZE] a;
+        ").unwrap();
+        assert_eq!(0, symboltable.enum_constants["RED"].0);
+        assert_eq!(6, symboltable.enum_constants["BLUE"].0);
+        assert_eq!(12, symboltable.enum_constants["SIZE"].0);
+        assert_eq!(vec![TypeDef::FixedArray(12)], symboltable.get_var(&String::from("a")).unwrap().typeref.nested_def);
+
+        let err = resolve("enum color { RED }; enum light { GREEN, RED };").unwrap_err();
+        assert!(matches!(err.error, SemanticErrorType::Redefinition(name, _) if name == "RED"));
+
+        let err = resolve("enum color { RED }; enum color { GREEN };").unwrap_err();
+        assert!(matches!(err.error, SemanticErrorType::Redefinition(name, _) if name == "color"));
+
+        let err = resolve("int a; enum color { RED = a };").unwrap_err();
+        assert!(matches!(err.error, SemanticErrorType::NotConstant));
+
+        let err = resolve("enum color { RED }; int[RED] a;").unwrap_err();
+        assert!(matches!(err.error, SemanticErrorType::InvalidArraySize(0)));
+
+        let err = resolve("enum light c;").unwrap_err();
+        assert!(matches!(err.error, SemanticErrorType::UndefinedEnum(_)));
+
+        // 枚举成员和全局变量、typedef不能同名
+        for source in ["enum c { R }; int R;", "int R; enum c { R };", "typedef int R; enum c { R };", "enum c { R }; typedef int R;"] {
+            let err = resolve(source).unwrap_err();
+            assert!(matches!(&err.error, SemanticErrorType::Redefinition(name, _) if name == "R"), "{}", source);
+        }
+        // 局部变量可以遮住枚举成员
+        resolve("enum c { R }; int main() { int R = 1; return R; }").unwrap();
+
+        // 枚举成员是int，不能超出int的范围
+        let err = resolve("enum big { A = 2147483647, B };").unwrap_err();
+        assert_eq!(SemanticErrorType::EnumOverflow(String::from("B")), err.error);
+        assert_eq!(Some(Location::new(1, 28)), err.span.map(|span| span.start));
+        resolve("enum big { A = 2147483646, B, C = -2147483648 };").unwrap();
+
+        let err = resolve("enum big { A = 2147483648L };").unwrap_err();
+        assert_eq!(SemanticErrorType::EnumOverflow(String::from("A")), err.error);
+        let err = resolve("enum big { A = -2147483649L };").unwrap_err();
+        assert_eq!(SemanticErrorType::EnumOverflow(String::from("A")), err.error);
+    }
+}
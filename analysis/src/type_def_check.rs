@@ -1,51 +1,69 @@
 use std::collections::HashMap;
 use parse::ast::DefStructNode;
-use lex::token::Token;
-use parse::parser::parse;
-use lex::lexer::Lexer;
-use crate::local_resolver::local_resolver;
+use lex::token::is_aggregate;
+use lex::location::Span;
+use parse::error:: {
+    SemanticError,
+    SemanticErrorType,
+};
 
 #[derive(Debug, Clone)]
 struct StructTypeNode {
     pub name: String,
+    pub span: Span,
     pub next: HashMap<String, usize>,
     pub checked: bool,
+    // 在当前搜索路径上，再次遇到说明有环
+    pub visiting: bool,
 }
 
-fn check_circle_def(defines: HashMap<String, DefStructNode>) {
-    let mut type_graph = build_type_graph(defines);
-    println!("{:?}", type_graph);
-    depth_traversal(0, &mut type_graph);
+pub fn check_circle_def(defines: HashMap<String, DefStructNode>) -> Result<(), SemanticError> {
+    let mut type_graph = build_type_graph(defines)?;
+    // 环不一定能从第一个结构体走到，每个都要作为起点
+    for index in 0..type_graph.len() {
+        depth_traversal(index, &mut type_graph)?;
+    }
+
+    Ok(())
 }
 
-fn depth_traversal(index: usize, mut graph: &mut Vec<StructTypeNode>) {
-    let mut node = &mut graph[index];
+fn depth_traversal(index: usize, graph: &mut Vec<StructTypeNode>) -> Result<(), SemanticError> {
+    let node = &mut graph[index];
+    if node.visiting {
+        return Err(SemanticError::at(SemanticErrorType::RecursiveDefinition(node.name.clone()), node.span.clone()))
+    }
+    // 检查过的结构体不会再出环，两个成员用到同一个结构体也不算递归
     if node.checked {
-        panic!("\"{}\" recursive definition ", node.name);
+        return Ok(())
     }
-    node.checked = true;
-    println!("set node: {}", node.name);
-    for (name, i) in node.next.clone() {
-        depth_traversal(i, &mut graph);
+    node.visiting = true;
+    for (_name, i) in node.next.clone() {
+        depth_traversal(i, graph)?;
     }
+    graph[index].visiting = false;
+    graph[index].checked = true;
+
+    Ok(())
 }
 
-fn build_type_graph(defines: HashMap<String, DefStructNode>) -> Vec<StructTypeNode> {
+fn build_type_graph(defines: HashMap<String, DefStructNode>) -> Result<Vec<StructTypeNode>, SemanticError> {
     let mut type_graph: Vec<StructTypeNode> = Vec::new();
     for (name, def) in defines {
         let mut next = HashMap::new();
         for member in def.member_list {
             let type_base = member.typeref.type_base;
-            let is_struct = type_base.base == Token::Struct;
-            let not_pointer = member.typeref.nested_def.len() == 0;
+            let is_struct = is_aggregate(&type_base.base);
+            let not_pointer = member.typeref.nested_def.is_empty();
             if is_struct && not_pointer {
                 next.insert(type_base.name.unwrap(), 0);
             }
         } 
         type_graph.push(StructTypeNode {
             name,
+            span: def.span,
             next,
             checked: false,
+            visiting: false,
         });
     }
 
@@ -56,16 +74,22 @@ fn build_type_graph(defines: HashMap<String, DefStructNode>) -> Vec<StructTypeNo
 
     for node in &mut type_graph {
         for name in node.next.clone().keys() {
-            node.next.insert(name.clone(), index_map[&name.clone()]);
+            match index_map.get(name) {
+                Some(i) => node.next.insert(name.clone(), *i),
+                None => return Err(SemanticError::new(SemanticErrorType::UndefinedStruct(name.clone()))),
+            };
         }
     }
 
-    return type_graph
+    return Ok(type_graph)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use parse::parser::parse;
+    use lex::lexer::Lexer;
+    use crate::local_resolver::local_resolver;
 
     #[test]
     fn test_build_type_graph() {
@@ -108,9 +132,9 @@ mod tests {
                 return 0;
             }
         "));
-        let ast = parse(&mut lxr);
-        let symboltable = local_resolver(&ast);
-        println!("{:?}", build_type_graph(symboltable.global_define_map))
+        let ast = parse(&mut lxr).unwrap();
+        let symboltable = local_resolver(&ast).unwrap();
+        println!("{:?}", build_type_graph(symboltable.global_define_map).unwrap())
     }
 
     #[test]
@@ -154,9 +178,37 @@ mod tests {
                 return 0;
             }
         "));
-        let ast = parse(&mut lxr);
-        let symboltable = local_resolver(&ast);
-        check_circle_def(symboltable.global_define_map);
+        let ast = parse(&mut lxr).unwrap();
+        let symboltable = local_resolver(&ast).unwrap();
+        let err = check_circle_def(symboltable.global_define_map).unwrap_err();
+        assert!(matches!(err.error, SemanticErrorType::RecursiveDefinition(_)));
+    }
+
+    #[test]
+    fn test_check_union_circle() {
+        let check = |source: &str| {
+            let ast = parse(&mut Lexer::new(String::from(source))).unwrap();
+            check_circle_def(local_resolver(&ast).unwrap().global_define_map)
+        };
+
+        // 两个成员用到同一个类型不是递归定义
+        check("
+            struct point { int x; int y; };
+            union value { struct point p; int i; };
+            struct line { struct point a; union value b; };
+            struct shape { struct line l; struct point c; };
+        ").unwrap();
+
+        let err = check("
+            struct a { int x; };
+            struct b { union c u; };
+            union c { struct b s; int i; };
+        ").unwrap_err();
+        assert!(matches!(err.error, SemanticErrorType::RecursiveDefinition(_)));
+
+        check("
+            union node { union node *next; int i; };
+        ").unwrap();
     }
 
     #[test]
@@ -200,8 +252,9 @@ mod tests {
                 return 0;
             }
         "));
-        let ast = parse(&mut lxr);
-        let symboltable = local_resolver(&ast);
-        println!("{:?}", symboltable.get_type(&String::from("bb")));
+        let ast = parse(&mut lxr).unwrap();
+        let symboltable = local_resolver(&ast).unwrap();
+        let err = symboltable.get_type(&String::from("bb")).unwrap_err();
+        assert_eq!(SemanticErrorType::UndefinedSymbol(String::from("bb")), err.error);
     }
 }
\ No newline at end of file
@@ -1,35 +1,30 @@
-use std::collections::HashMap;
-use parse::ast:: {
-    ProgramNode,
-    TypeDef,
-};
-use parse::ir:: {
-    IRNode,
-    IR,
-};
-use lex::token::Token;
-use parse::parser::parse;
-use lex::lexer::Lexer;
+use parse::ast::ProgramNode;
+use parse::ir::IR;
 use parse::symbol_table::TopLevelScope;
-use analysis::local_resolver::local_resolver;
-use analysis::expr_check::check_expr;
+use parse::error::SemanticError;
 
-fn ir_generate(ast: ProgramNode) -> IR {
-    let funcs = ast.defs.func_defs;
+pub fn ir_generate(ast: &ProgramNode, symboltable: &mut TopLevelScope) -> Result<IR, SemanticError> {
+    let mut ir_tree = IR::new();
 
-    let ir_tree = IR {
-        variables: Vec::new(),
-        functions: Vec::new(),
+    for var in &ast.defs.var_defs {
+        var.generate(symboltable, &mut ir_tree)?;
     }
 
-    for func in funcs {
-        func.generate(&mut ir_tree);
+    for func in &ast.defs.func_defs {
+        func.generate(symboltable, &mut ir_tree)?;
     }
+
+    return Ok(ir_tree)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use parse::parser::parse;
+    use lex::lexer::Lexer;
+    use analysis::local_resolver::local_resolver;
+    use analysis::expr_check::check_expr;
+    use parse::error::SemanticErrorType;
 
     #[test]
     fn test_generate() {
@@ -55,9 +50,10 @@ mod tests {
                 int ba1 = 1;
                 int[] aa;
                 int[10] bb = aa;
-                bb[ba1] = 1 * 2 << 3 && 4 + 5 / 6 + calc();
-                a.age = 2 + calc(a);
+                bb[ba1] = 1 * 2 << 3 && 4 + 5 / 6 + calc(2);
+                a.age = 2 + calc(a.age);
                 &a;
+                int i;
                 for(i = 0; i < 10; i++) {
                     int ca = 1;
                     if (a == 2) {
@@ -80,10 +76,49 @@ mod tests {
                 
             }
         "));
-        let ast = parse(&mut lxr);
+        let ast = parse(&mut lxr).unwrap();
         println!(" === {:?} ", ast);
-        let mut symboltable = local_resolver(&ast);
+        let mut symboltable = local_resolver(&ast).unwrap();
         println!("symboltable : {:?}", symboltable);
-        check_expr(&ast, &mut symboltable);
+        check_expr(&ast, &mut symboltable).unwrap();
+        println!("ir : {:?}", ir_generate(&ast, &mut symboltable).unwrap());
+    }
+
+    #[test]
+    fn test_generate_globals_and_funcs() {
+        let mut lxr = Lexer::new(String::from("
+            int count = 0;
+
+            int main(int argc, char **argv) {
+                while(count < 10) {
+                    count = count + 1;
+                }
+
+                return count;
+            }
+        "));
+        let ast = parse(&mut lxr).unwrap();
+        let mut symboltable = local_resolver(&ast).unwrap();
+        check_expr(&ast, &mut symboltable).unwrap();
+        let ir = ir_generate(&ast, &mut symboltable).unwrap();
+
+        assert_eq!(1, ir.variables.len());
+        assert_eq!(1, ir.functions.len());
+        assert_eq!("main", ir.functions[0].name);
+        // begin cjump body assign jump end return
+        assert_eq!(7, ir.functions[0].body.len());
+    }
+
+    #[test]
+    fn test_generate_break_outside_loop() {
+        let mut lxr = Lexer::new(String::from("
+            int main() {
+                break;
+            }
+        "));
+        let ast = parse(&mut lxr).unwrap();
+        let mut symboltable = local_resolver(&ast).unwrap();
+        let err = ir_generate(&ast, &mut symboltable).unwrap_err();
+        assert_eq!(SemanticErrorType::BreakOutsideLoop, err.error);
     }
 }
\ No newline at end of file
@@ -1 +1,4 @@
-mod generate;
\ No newline at end of file
+// 项目里的函数统一用显式的return返回值
+#![allow(clippy::needless_return)]
+
+pub mod generate;
\ No newline at end of file
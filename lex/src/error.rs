@@ -2,13 +2,159 @@ use crate::location::Location;
 use crate::token::Token;
 use std::fmt;
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct LexicalError {
     pub location: Location,
-    pub token: Token,
+    pub error: LexicalErrorType,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LexicalErrorType {
+    UnexpectToken {
+        expect: Token,
+        found: Token,
+    },
+    UnmatchedQuotes,
+    UnclosedQuotes,
+    InvalidCharacter,
+    InvalidNumber,
+    UnterminatedComment,
+    NumberOverflow,
+    InvalidEscape,
+}
+
+impl LexicalErrorType {
+    /**
+     *  诊断信息里显示的错误码
+     */
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexicalErrorType::UnexpectToken { .. } => "E0001",
+            LexicalErrorType::UnmatchedQuotes => "E0002",
+            LexicalErrorType::UnclosedQuotes => "E0003",
+            LexicalErrorType::InvalidCharacter => "E0004",
+            LexicalErrorType::InvalidNumber => "E0005",
+            LexicalErrorType::UnterminatedComment => "E0006",
+            LexicalErrorType::NumberOverflow => "E0007",
+            LexicalErrorType::InvalidEscape => "E0008",
+        }
+    }
 }
 
 impl fmt::Display for LexicalError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "unexcept token! {} in {}", self.token, self.location)
+        write!(f, "{} in {}", self.error, self.location)
     }
-}
\ No newline at end of file
+}
+
+impl fmt::Display for LexicalErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LexicalErrorType::UnexpectToken { expect, found } => {
+                write!(f, "unexcept token! {}, expect {}", found, expect)
+            },
+            LexicalErrorType::UnmatchedQuotes => {
+                write!(f, "The quotes do not match correctly")
+            },
+            LexicalErrorType::UnclosedQuotes => {
+                write!(f, "The quotes are not closed")
+            },
+            LexicalErrorType::InvalidCharacter => {
+                write!(f, "Char types can only have a single character")
+            },
+            LexicalErrorType::InvalidNumber => {
+                write!(f, "Numeric constants unexpect token")
+            },
+            LexicalErrorType::UnterminatedComment => {
+                write!(f, "The block comment is not closed")
+            },
+            LexicalErrorType::NumberOverflow => {
+                write!(f, "Numeric constant is too large for its type")
+            },
+            LexicalErrorType::InvalidEscape => {
+                write!(f, "Unknown escape sequence")
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreprocessError {
+    pub file: String,
+    pub line: usize,
+    // 只有注释没闭合这类能定位到字符的错误才有列号
+    pub column: Option<usize>,
+    // 出错的那一行原始代码，错误在头文件里时也能显示出来
+    pub text: String,
+    pub error: PreprocessErrorType,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PreprocessErrorType {
+    UnknownDirective(String),
+    UnmatchedDirective(String),
+    UnterminatedConditional,
+    UnterminatedComment,
+    IncludeNotFound(String),
+    IncludeTooDeep(String),
+    InvalidInclude,
+    InvalidMacro,
+    MacroArguments {
+        name: String,
+        expect: usize,
+        found: usize,
+    },
+    UnterminatedMacroCall(String),
+    InvalidExpression,
+    ErrorDirective(String),
+}
+
+impl PreprocessErrorType {
+    pub fn code(&self) -> &'static str {
+        match self {
+            PreprocessErrorType::UnknownDirective(_) => "E0301",
+            PreprocessErrorType::UnmatchedDirective(_) => "E0302",
+            PreprocessErrorType::UnterminatedConditional => "E0303",
+            PreprocessErrorType::UnterminatedComment => "E0304",
+            PreprocessErrorType::IncludeNotFound(_) => "E0305",
+            PreprocessErrorType::IncludeTooDeep(_) => "E0306",
+            PreprocessErrorType::InvalidInclude => "E0307",
+            PreprocessErrorType::InvalidMacro => "E0308",
+            PreprocessErrorType::MacroArguments { .. } => "E0309",
+            PreprocessErrorType::UnterminatedMacroCall(_) => "E0310",
+            PreprocessErrorType::InvalidExpression => "E0311",
+            PreprocessErrorType::ErrorDirective(_) => "E0312",
+        }
+    }
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} in {} line {}", self.error, self.file, self.line)?;
+        if let Some(column) = self.column {
+            write!(f, " column {}", column)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PreprocessErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PreprocessErrorType::UnknownDirective(name) => write!(f, "Invalid preprocessing directive #{}", name),
+            PreprocessErrorType::UnmatchedDirective(name) => write!(f, "#{} without #if", name),
+            PreprocessErrorType::UnterminatedConditional => write!(f, "Unterminated conditional directive"),
+            PreprocessErrorType::UnterminatedComment => write!(f, "The block comment is not closed"),
+            PreprocessErrorType::IncludeNotFound(name) => write!(f, "\"{}\" file not found", name),
+            PreprocessErrorType::IncludeTooDeep(name) => write!(f, "#include nested too deeply when including \"{}\"", name),
+            PreprocessErrorType::InvalidInclude => write!(f, "#include expects \"FILENAME\" or <FILENAME>"),
+            PreprocessErrorType::InvalidMacro => write!(f, "Invalid macro definition"),
+            PreprocessErrorType::MacroArguments { name, expect, found } => {
+                write!(f, "Macro \"{}\" requires {} arguments, but {} given", name, expect, found)
+            },
+            PreprocessErrorType::UnterminatedMacroCall(name) => write!(f, "Unterminated argument list invoking macro \"{}\"", name),
+            PreprocessErrorType::InvalidExpression => write!(f, "Invalid expression in preprocessor conditional"),
+            PreprocessErrorType::ErrorDirective(message) => write!(f, "#error {}", message),
+        }
+    }
+}
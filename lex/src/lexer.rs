@@ -1,39 +1,119 @@
 use crate::token:: {
     Token,
+    SpannedToken,
+    LiteralType,
     get_keywords,
 };
-use crate::location::Location;
+use crate::location:: {
+    Location,
+    Span,
+    DEFAULT_TAB_WIDTH,
+};
 use std::collections::HashMap;
-use std::process;
-use crate::error::LexicalError;
+use std::num::IntErrorKind;
+use crate::error:: {
+    LexicalError,
+    LexicalErrorType,
+};
 
 pub struct Lexer {
     chars: Vec<char>,
+    line_starts: Vec<usize>,
     location: Location,
+    prev_end: Location,
     read_pos: usize,
     cur_text: Vec<char>,
-    lookahead: Vec<Token>,
+    lookahead: Vec<SpannedToken>,
     keywords: HashMap<String, Token>,
+    tab_width: usize,
+    // typedef定义的名字，语法分析需要区分类型名和普通的标识符
+    // 每层作用域一张表，值为false的是遮住了外层typedef的普通标识符
+    type_names: Vec<HashMap<String, bool>>,
 }
 
 impl Lexer {
     pub fn new(input: String) -> Lexer {
         let mut chars = Vec::new();
+        // 记录每一行第一个字符的下标，用来把下标换算成行列
+        let mut line_starts = vec![0];
         for c in input.chars() {
-            chars.push(c)
+            chars.push(c);
+            if c == '\n' {
+                line_starts.push(chars.len());
+            }
         }
         
         Lexer {
-            chars: chars,
-            location: Location::new(0, 0),
+            chars,
+            line_starts,
+            location: Location::new(1, 1),
+            prev_end: Location::new(1, 1),
             read_pos: 0,
             cur_text: Vec::new(),
             lookahead: Vec::new(),
             keywords: get_keywords(),
+            tab_width: DEFAULT_TAB_WIDTH,
+            type_names: vec![HashMap::new()],
         }
     }
 
-    fn lex(&mut self) -> Token {
+    /**
+     *  计算列号时一个tab占的宽度，要在读第一个token之前设置
+     */
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.tab_width = tab_width;
+    }
+
+    /**
+     *  之后读到这个名字时返回TypeName而不是Name
+     */
+    pub fn add_type_name(&mut self, name: &str) {
+        self.declare_name(name, true);
+    }
+
+    /**
+     *  普通的声明在当前作用域里遮住同名的typedef，之后读到这个名字时返回Name
+     */
+    pub fn add_var_name(&mut self, name: &str) {
+        self.declare_name(name, false);
+    }
+
+    /**
+     *  进入block、函数参数和for的作用域，离开时这一层声明的名字都失效
+     */
+    pub fn enter_scope(&mut self) {
+        self.type_names.push(HashMap::new());
+    }
+
+    pub fn leave_scope(&mut self) {
+        if self.type_names.len() > 1 {
+            self.type_names.pop();
+        }
+    }
+
+    fn declare_name(&mut self, name: &str, is_type: bool) {
+        if let Some(scope) = self.type_names.last_mut() {
+            scope.insert(name.to_string(), is_type);
+        }
+    }
+
+    /**
+     *  lookahead里可能已经有typedef之前读进来的名字，所以在取出token的时候再区分
+     */
+    fn classify(&self, mut t: SpannedToken) -> SpannedToken {
+        if let Token::Name(name) = &t.token {
+            // 里层的声明优先
+            let is_type = self.type_names.iter().rev().find_map(|scope| scope.get(name));
+            if is_type == Some(&true) {
+                t.token = Token::TypeName(name.clone());
+            }
+        }
+
+        t
+    }
+
+    fn lex(&mut self) -> Result<SpannedToken, LexicalError> {
+        self.skip_comments()?;
         let start = self.read_pos;
         for index in start..self.chars.len() {
             let ch = self.chars[index];
@@ -42,71 +122,159 @@ impl Lexer {
                 continue
             } 
 
-            let token = self.handle_valid_char(ch);
+            if self.cur_text.is_empty() {
+                self.location = self.location_at(index);
+            }
+
+            let token = self.handle_valid_char(ch)?;
 
             match token {
-                Some(t) => return t,
+                Some(t) => return Ok(self.spanned(t)),
                 None => {
                     self.cur_text.push(ch);
                 },
             }
             
             if self.can_be_identifier() {
-                return self.keywords_or_name()
+                let t = self.keywords_or_name();
+                return Ok(self.spanned(t))
             }
         }
 
-        Token::Eof
+        self.location = self.location_at(self.chars.len());
+        Ok(self.spanned(Token::Eof))
     }
 
-    pub fn advance(&mut self) -> Token {
-        if self.lookahead.len() <= 0 {
-            self.lookahead(1);
+    pub fn advance(&mut self) -> Result<SpannedToken, LexicalError> {
+        if self.lookahead.is_empty() {
+            self.lookahead(1)?;
         }
 
-        println!("advance {}", self.lookahead(1));
-        return self.lookahead.remove(0)
+        let t = self.lookahead.remove(0);
+        self.prev_end = t.span.end.clone();
+        return Ok(self.classify(t))
     }
 
-    pub fn lookahead(&mut self, number: usize) -> Token {
-        let len = self.lookahead.len();
-        if number >= len && self.read_pos < self.chars.len() {
-            for i in len..number {
-                let t = self.lex();
-                self.lookahead.push(t);
-            } 
-        }
-        
-        if self.lookahead.len() > 0 {
-            return self.lookahead[number - 1].clone()
-        } else {
-            return Token::Eof
+    pub fn lookahead(&mut self, number: usize) -> Result<SpannedToken, LexicalError> {
+        // 读到文件末尾之后lex会一直返回Eof
+        while self.lookahead.len() < number {
+            let t = self.lex()?;
+            self.lookahead.push(t);
         }
+
+        return Ok(self.classify(self.lookahead[number - 1].clone()))
     }
 
-    pub fn matcher(&mut self, token: Token) -> Token {
-        self.lookahead(1);
-        if self.lookahead[0] != token {
-            panic!("unexcept token!: {} in {}", self.lookahead[0], self.location);
+    pub fn matcher(&mut self, token: Token) -> Result<SpannedToken, LexicalError> {
+        let t = self.lookahead(1)?;
+        if t.token != token {
+            return Err(LexicalError {
+                location: t.span.start,
+                error: LexicalErrorType::UnexpectToken {
+                    expect: token,
+                    found: t.token,
+                },
+            })
         };
 
         self.advance()
     }
 
+    /**
+     *  下一个还没有被消耗的token的起始位置
+     */
+    pub fn location(&self) -> Location {
+        match self.lookahead.first() {
+            Some(t) => t.span.start.clone(),
+            None => self.location.clone(),
+        }
+    }
+
+    /**
+     *  解析AST节点时先记下起始位置，节点结束后用span_from得到整个节点的范围
+     */
+    pub fn next_location(&mut self) -> Result<Location, LexicalError> {
+        return Ok(self.lookahead(1)?.span.start)
+    }
+
+    pub fn span_from(&self, start: Location) -> Span {
+        return Span::new(start, self.prev_end.clone())
+    }
+
+    fn location_at(&self, pos: usize) -> Location {
+        let row = match self.line_starts.binary_search(&pos) {
+            Ok(i) => i,
+            Err(i) => i - 1,
+        };
+
+        let mut location = Location::new(row + 1, 1);
+        for ch in &self.chars[self.line_starts[row]..pos] {
+            location = location.advance(*ch, self.tab_width);
+        }
+
+        location
+    }
+
+    fn spanned(&self, token: Token) -> SpannedToken {
+        SpannedToken {
+            token,
+            span: Span::new(self.location.clone(), self.location_at(self.read_pos)),
+        }
+    }
+
+    fn error(&self, error: LexicalErrorType) -> LexicalError {
+        LexicalError {
+            location: self.location.clone(),
+            error,
+        }
+    }
+
+    /**
+     *  跳过空白和注释，和C一样块注释不能嵌套，遇到第一个结束符就结束
+     */
+    fn skip_comments(&mut self) -> Result<(), LexicalError> {
+        while self.read_pos < self.chars.len() {
+            let ch = self.chars[self.read_pos];
+            let next = self.chars.get(self.read_pos + 1);
+            if Lexer::whitespace_char(ch) {
+                self.read_pos += 1;
+            } else if ch == '/' && next == Some(&'/') {
+                while self.read_pos < self.chars.len() && self.chars[self.read_pos] != '\n' {
+                    self.read_pos += 1;
+                }
+            } else if ch == '/' && next == Some(&'*') {
+                self.location = self.location_at(self.read_pos);
+                let body = &self.chars[self.read_pos + 2..];
+                match body.windows(2).position(|w| w == ['*', '/']) {
+                    Some(end) => self.read_pos += end + 4,
+                    None => return Err(self.error(LexicalErrorType::UnterminatedComment)),
+                }
+            } else {
+                break
+            }
+        }
+
+        Ok(())
+    }
+
     fn whitespace_char(ch: char) -> bool {
-        ch == ' ' || ch == '\n' || ch == '\r'
+        // \x0b和\x0c是\v和\f
+        matches!(ch, ' ' | '\n' | '\r' | '\t' | '\x0b' | '\x0c')
     }
 
     fn can_be_identifier(&mut self) -> bool {
+        if self.read_pos >= self.chars.len() {
+            return !self.cur_text.is_empty()
+        }
         let next_char = self.chars[self.read_pos];
-        let not_null = self.cur_text.len() > 0;
+        let not_null = !self.cur_text.is_empty();
         let next_invalid = !(next_char.is_ascii_alphabetic()) && !(next_char.is_ascii_digit());
         return  not_null && next_invalid && next_char != '_' || next_char == '.'
     }
 
-    fn handle_valid_char(&mut self, ch: char) -> Option<Token> {
+    fn handle_valid_char(&mut self, ch: char) -> Result<Option<Token>, LexicalError> {
         self.read_pos += 1;
-        match ch {
+        let token = match ch {
             '(' => Some(Token::LParentheses),
             ')' => Some(Token::RParentheses),
             '[' => Some(Token::LBrackets),
@@ -114,12 +282,24 @@ impl Lexer {
             '{' => Some(Token::LBrace),
             '}' => Some(Token::RBrace),
             ';' => Some(Token::Semi),
-            '/' => Some(Token::Div),
-            '%' => Some(Token::Mod),
-            '*' => Some(Token::Mul),
-            '.' => Some(Token::Dot),
+            '/' => Some(self.op_or_assgin_token(Token::Div, Token::DivAssgin)),
+            '%' => Some(self.op_or_assgin_token(Token::Mod, Token::ModAssgin)),
+            '*' => Some(self.op_or_assgin_token(Token::Mul, Token::MulAssgin)),
+            '.' => {
+                // .5这样以小数点开头的浮点数
+                if self.cur_text.is_empty() && self.chars.get(self.read_pos).is_some_and(|c| c.is_ascii_digit()) {
+                    Some(self.number_token()?)
+                } else if self.chars.get(self.read_pos) == Some(&'.') && self.chars.get(self.read_pos + 1) == Some(&'.') {
+                    self.read_pos += 2;
+                    Some(Token::Ellipsis)
+                } else {
+                    Some(Token::Dot)
+                }
+            },
             ',' => Some(Token::Comma),
-            '^' => Some(Token::Bitxor),
+            '^' => Some(self.op_or_assgin_token(Token::Bitxor, Token::BitxorAssgin)),
+            '?' => Some(Token::Question),
+            ':' => Some(Token::Colon),
             '|' => Some(self.or_or_bitor_token()),
             '>' => Some(self.greater_or_shift_token()),
             '<' => Some(self.less_or_shift_token()),
@@ -128,98 +308,214 @@ impl Lexer {
             '-' => Some(self.sub_or_dec_token()),
             '&' => Some(self.and_or_bitand_token()),
             '=' => Some(self.assgin_or_equal_tokean()),
-            '"' => Some(self.string_token()),
-            '\'' => Some(self.char_token()),
+            '"' => Some(self.string_token()?),
+            '\'' => Some(self.char_token()?),
             '0'|'1'|'2'|'3'|'4'|'5'|'6'|'7'|'8'|'9' => {
-                println!(" == = = = = === = == = {} {}", ch, self.cur_text.len());
-                if self.cur_text.len() == 0 {
-                    return Some(self.number_token())
+                if self.cur_text.is_empty() {
+                    Some(self.number_token()?)
                 } else {
-                    return None
+                    None
                 }
             },
             _ => None,
-        }
+        };
+
+        Ok(token)
     }
 
-    fn string_token(&mut self) -> Token {
-        let mut s = Vec::new();
-        let mut enclose = false;
-        for index in self.read_pos..self.chars.len() {
-            let ch = self.chars[index];
-            match ch {
-                '\n' => panic!("The quotes do not match correctly in {} ", self.location),
+    fn string_token(&mut self) -> Result<Token, LexicalError> {
+        let mut s = String::new();
+        let mut index = self.read_pos;
+        while index < self.chars.len() {
+            match self.chars[index] {
+                '\n' => return Err(self.error(LexicalErrorType::UnmatchedQuotes)),
                 '"' => {
-                    enclose = true;
-                    break;
+                    self.read_pos = index + 1;
+                    return Ok(Token::String(s))
                 },
-                _ => {
+                '\\' => {
+                    let (ch, next) = self.escape_char(index)?;
                     s.push(ch);
+                    index = next;
+                },
+                ch => {
+                    s.push(ch);
+                    index += 1;
                 },
             }
         }
 
-        if enclose {
-            let sv: String = s.iter().collect();
-            self.read_pos += sv.len() + 1;
-            return Token::String(String::from(sv))
-        } else {
-            panic!("The quotes are not closed");
+        return Err(self.error(LexicalErrorType::UnclosedQuotes))
+    }
+
+    fn char_token(&mut self) -> Result<Token, LexicalError> {
+        let (ch, next) = match self.chars.get(self.read_pos) {
+            Some('\\') => self.escape_char(self.read_pos)?,
+            Some(&ch) => (ch, self.read_pos + 1),
+            None => return Err(self.error(LexicalErrorType::InvalidCharacter)),
+        };
+        if self.chars.get(next) != Some(&'\'') {
+            return Err(self.error(LexicalErrorType::InvalidCharacter))
         }
-        
+        self.read_pos = next + 1;
+        return Ok(Token::Character(ch));
     }
 
-    fn char_token(&mut self) -> Token {
-        let next = self.chars[self.read_pos];
-        let next2 = self.chars[self.read_pos + 1];
-        if next2 != '\'' {
-            panic!("Char types can only have a single character");
+    /**
+     *  pos指向反斜杠，返回转义后的字符和转义序列之后的下标
+     *  \xHH最多取两位，\ooo最多取三位，值都不能超过一个字节
+     */
+    fn escape_char(&self, pos: usize) -> Result<(char, usize), LexicalError> {
+        let invalid = LexicalError {
+            location: self.location_at(pos),
+            error: LexicalErrorType::InvalidEscape,
+        };
+        let ch = match self.chars.get(pos + 1) {
+            Some(&ch) => ch,
+            None => return Err(invalid),
+        };
+        let simple = match ch {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            'v' => Some('\u{0b}'),
+            'f' => Some('\u{0c}'),
+            'a' => Some('\u{07}'),
+            'b' => Some('\u{08}'),
+            '\\' | '\'' | '"' | '?' => Some(ch),
+            _ => None,
+        };
+        if let Some(c) = simple {
+            return Ok((c, pos + 2))
+        }
+
+        let (radix, start, max_len) = match ch {
+            'x' => (16, pos + 2, 2),
+            '0'..='7' => (8, pos + 1, 3),
+            _ => return Err(invalid),
+        };
+        let digits: String = self.chars[start..].iter()
+            .take(max_len)
+            .take_while(|c| c.is_digit(radix))
+            .collect();
+        match u8::from_str_radix(&digits, radix) {
+            Ok(value) => Ok((value as char, start + digits.len())),
+            Err(_) => Err(invalid),
         }
-        self.read_pos += 2;
-        return Token::Character(next);
     }
 
-    fn number_token(&mut self) -> Token {
-        let mut v = Vec::new();
-        for index in self.read_pos - 1..self.chars.len() {
-            let ch = self.chars[index];
-            if ch.is_ascii_digit() {
-                v.push(ch);
+    fn number_token(&mut self) -> Result<Token, LexicalError> {
+        /*
+            先按C的预处理数字的规则读出整个字面量，再按前缀和后缀解析
+            0x1F 017 0b101 3.14 1e-9 2.0f 10UL
+        */
+        let start = self.read_pos - 1;
+        let mut end = start;
+        while end < self.chars.len() {
+            let ch = self.chars[end];
+            let after_e = end > start && self.chars[end - 1].eq_ignore_ascii_case(&'e');
+            let is_exponent = (ch == '+' || ch == '-') && after_e;
+            if ch.is_ascii_alphanumeric() || ch == '_' || ch == '.' || is_exponent {
+                end += 1;
             } else {
-                if index + 1 < self.chars.len() {
-                    if self.chars[index + 1].is_ascii_alphabetic() {
-                        panic!("Numeric constants unexpect token,");
-                    } else {
-                        break
-                    }
-                }
+                break
             }
         }
-        self.read_pos += v.len() - 1;
-        let num = Lexer::digit_from_vec(v);
-        return Token::Number(num as i64)
+
+        let text: String = self.chars[start..end].iter().collect();
+        self.read_pos = end;
+        return Lexer::parse_number(&text).map_err(|e| self.error(e))
     }
 
-    fn digit_from_vec(mut vec: Vec<char>) -> u32 {
-        let mut num = 0;
-        vec.reverse();
-        for (i, ch) in vec.iter().enumerate() {
-            let index = i as u32;
-            let radix: u32 = 10;
-            let ci = ch.to_digit(10).unwrap();
-            num += ci * radix.pow(index);
+    fn parse_number(text: &str) -> Result<Token, LexicalErrorType> {
+        let lower = text.to_ascii_lowercase();
+        let (radix, body) = if lower.starts_with("0x") {
+            (16, &text[2..])
+        } else if lower.starts_with("0b") {
+            (2, &text[2..])
+        } else if lower.contains('.') || lower.contains('e') {
+            return Lexer::parse_float(text)
+        } else if text.starts_with('0') && text[1..].starts_with(|c: char| c.is_ascii_digit()) {
+            (8, &text[1..])
+        } else {
+            (10, text)
+        };
+
+        let split = body.find(|c: char| !c.is_digit(radix)).unwrap_or(body.len());
+        let (digits, suffix) = body.split_at(split);
+        // 按顺序取第一个放得下的类型，十进制不会变成无符号数
+        // 十六进制、八进制和二进制可以用同样长度的无符号类型，比如0xFFFFFFFF是unsigned int
+        let decimal = radix == 10;
+        let candidates: &[LiteralType] = match suffix {
+            "" if decimal => &[LiteralType::Int, LiteralType::Long],
+            "" => &[LiteralType::Int, LiteralType::UnsignedInt, LiteralType::Long, LiteralType::UnsignedLong],
+            "l" | "L" | "ll" | "LL" if decimal => &[LiteralType::Long],
+            "l" | "L" | "ll" | "LL" => &[LiteralType::Long, LiteralType::UnsignedLong],
+            "u" | "U" => &[LiteralType::UnsignedInt, LiteralType::UnsignedLong],
+            "ul" | "uL" | "Ul" | "UL" | "lu" | "lU" | "Lu" | "LU"
+                | "ull" | "uLL" | "Ull" | "ULL" | "llu" | "llU" | "LLu" | "LLU" => &[LiteralType::UnsignedLong],
+            _ => return Err(LexicalErrorType::InvalidNumber),
+        };
+
+        let value = match u64::from_str_radix(digits, radix) {
+            Ok(value) => value,
+            Err(e) if *e.kind() == IntErrorKind::PosOverflow => return Err(LexicalErrorType::NumberOverflow),
+            Err(_) => return Err(LexicalErrorType::InvalidNumber),
+        };
+        match candidates.iter().find(|literal_type| value <= literal_type.max_value()) {
+            Some(literal_type) => return Ok(Token::Number(value, *literal_type)),
+            None => return Err(LexicalErrorType::NumberOverflow),
         }
+    }
 
-        return num
+    fn parse_float(text: &str) -> Result<Token, LexicalErrorType> {
+        let (body, literal_type) = match text.strip_suffix(['f', 'F']) {
+            Some(body) => (body, LiteralType::Float),
+            None => (text.strip_suffix(['l', 'L']).unwrap_or(text), LiteralType::Double),
+        };
+        let valid = body.chars().all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'));
+        let value: f64 = match body.parse() {
+            Ok(value) if valid => value,
+            _ => return Err(LexicalErrorType::InvalidNumber),
+        };
+        let overflow = match literal_type {
+            LiteralType::Float => (value as f32).is_infinite(),
+            _ => value.is_infinite(),
+        };
+        if overflow {
+            return Err(LexicalErrorType::NumberOverflow)
+        }
+
+        return Ok(Token::FloatNumber(value, literal_type))
     }
 
-    fn greater_or_shift_token(&mut self) -> Token {
-        let ch = self.chars[self.read_pos]; 
-        if ch == '>' {
-            self.read_pos += 1;
-            return Token::Rightshift
-        } else if ch == '=' {
+    /**
+     *  下一个字符是ch时吃掉它，到了文件末尾也不会越界
+     */
+    fn follow(&mut self, ch: char) -> bool {
+        if self.chars.get(self.read_pos) == Some(&ch) {
             self.read_pos += 1;
+            return true
+        } else {
+            return false
+        }
+    }
+
+    /**
+     *  op后面紧跟=时是对应的复合赋值，比如*和*=
+     */
+    fn op_or_assgin_token(&mut self, op: Token, assgin: Token) -> Token {
+        if self.follow('=') {
+            return assgin
+        } else {
+            return op
+        }
+    }
+
+    fn greater_or_shift_token(&mut self) -> Token {
+        if self.follow('>') {
+            return self.op_or_assgin_token(Token::Rightshift, Token::RightshiftAssgin)
+        } else if self.follow('=') {
             return Token::Greaterequal
         } else {
             return Token::Greater
@@ -227,12 +523,9 @@ impl Lexer {
     }
 
     fn less_or_shift_token(&mut self) -> Token {
-        let ch = self.chars[self.read_pos]; 
-        if ch == '<' {
-            self.read_pos += 1;
-            return Token::Leftshift
-        } else if ch == '=' {
-            self.read_pos += 1;
+        if self.follow('<') {
+            return self.op_or_assgin_token(Token::Leftshift, Token::LeftshiftAssgin)
+        } else if self.follow('=') {
             return Token::Lessequal
         } else {
             return Token::Less
@@ -240,9 +533,7 @@ impl Lexer {
     }
 
     fn not_or_equal_token(&mut self) -> Token {
-        let ch = self.chars[self.read_pos]; 
-        if ch == '=' {
-            self.read_pos += 1;
+        if self.follow('=') {
             return Token::Notequal
         } else {
             return Token::Not
@@ -250,48 +541,41 @@ impl Lexer {
     }
 
     fn or_or_bitor_token(&mut self) -> Token {
-        let ch = self.chars[self.read_pos]; 
-        if ch == '|' {
-            self.read_pos += 1;
+        if self.follow('|') {
             return Token::Or
         } else {
-            return Token::Bitor
+            return self.op_or_assgin_token(Token::Bitor, Token::BitorAssgin)
         }
     }
 
     fn add_or_inc_token(&mut self) -> Token {
-        if self.chars[self.read_pos] == '+' {
-            self.read_pos += 1;
+        if self.follow('+') {
             return Token::Inc
         } else {
-            return Token::Add
+            return self.op_or_assgin_token(Token::Add, Token::AddAssgin)
         }
     }
 
     fn sub_or_dec_token(&mut self) -> Token {
-        if self.chars[self.read_pos] == '-' {
-            self.read_pos += 1;
+        if self.follow('-') {
             return Token::Dec
-        } else if self.chars[self.read_pos] == '>' {
-            self.read_pos += 1;
+        } else if self.follow('>') {
             return Token::PointerRef
         } else {
-            return Token::Sub
+            return self.op_or_assgin_token(Token::Sub, Token::SubAssgin)
         }
     }
 
     fn and_or_bitand_token(&mut self) -> Token {
-        if self.chars[self.read_pos] == '&' {
-            self.read_pos += 1;
+        if self.follow('&') {
             return Token::And
         } else {
-            return Token::Bitand
+            return self.op_or_assgin_token(Token::Bitand, Token::BitandAssgin)
         }
     }
 
     fn assgin_or_equal_tokean(&mut self) -> Token {
-        if self.chars[self.read_pos] == '=' {
-            self.read_pos += 1;
+        if self.follow('=') {
             return Token::Equal
         } else {
             return Token::Assgin
@@ -304,7 +588,7 @@ impl Lexer {
         if self.keywords.contains_key(&s) {
             return self.keywords[&s].clone()
         } else {
-            return Token::Name(String::from(s))
+            return Token::Name(s)
         }
     }
 }
@@ -318,6 +602,10 @@ mod tests {
         assert!(Lexer::whitespace_char(' '));
         assert!(Lexer::whitespace_char('\n'));
         assert!(Lexer::whitespace_char('\r'));
+        assert!(Lexer::whitespace_char('\t'));
+        assert!(Lexer::whitespace_char('\x0b'));
+        assert!(Lexer::whitespace_char('\x0c'));
+        assert!(!Lexer::whitespace_char('a'));
     }
 
     #[test]
@@ -339,12 +627,239 @@ mod tests {
 
         let mut lexer = Lexer::new(content);
 
-        println!("lookahead 4 {}", lexer.lookahead(4));
-        println!("lookahead 6 {}", lexer.lookahead(6));
-        println!("lookahead 8 {}", lexer.lookahead(8));
+        println!("lookahead 4 {}", lexer.lookahead(4).unwrap());
+        println!("lookahead 6 {}", lexer.lookahead(6).unwrap());
+        println!("lookahead 8 {}", lexer.lookahead(8).unwrap());
+
+        for _ in 0..40 {
+            println!("{}", lexer.advance().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_lexical_error() {
+        let mut lexer = Lexer::new(String::from("int a;"));
+        let err = lexer.matcher(Token::Char).unwrap_err();
+        assert_eq!(LexicalErrorType::UnexpectToken { expect: Token::Char, found: Token::Int }, err.error);
+
+        let mut lexer = Lexer::new(String::from("\"abc"));
+        assert_eq!(LexicalErrorType::UnclosedQuotes, lexer.advance().unwrap_err().error);
+
+        let mut lexer = Lexer::new(String::from("'ab'"));
+        assert_eq!(LexicalErrorType::InvalidCharacter, lexer.advance().unwrap_err().error);
 
-        for i in 0..40 {
-            println!("{}", lexer.advance());
+        let mut lexer = Lexer::new(String::from("12ab"));
+        assert_eq!(LexicalErrorType::InvalidNumber, lexer.advance().unwrap_err().error);
+    }
+
+    #[test]
+    fn test_lookahead_past_eof() {
+        let mut lexer = Lexer::new(String::from("a"));
+        assert_eq!(Token::Eof, lexer.lookahead(3).unwrap().token);
+        assert_eq!(Token::Name(String::from("a")), lexer.advance().unwrap().token);
+        assert_eq!(Token::Eof, lexer.advance().unwrap().token);
+    }
+
+    #[test]
+    fn test_token_span() {
+        let mut lexer = Lexer::new(String::from("int main() {\n    return 10;\n}"));
+        let t = lexer.advance().unwrap();
+        assert_eq!(Span::new(Location::new(1, 1), Location::new(1, 4)), t.span);
+        let t = lexer.advance().unwrap();
+        assert_eq!(Span::new(Location::new(1, 5), Location::new(1, 9)), t.span);
+        for _ in 0..3 {
+            lexer.advance().unwrap();
         }
+        let t = lexer.advance().unwrap();
+        assert_eq!(Token::Return, t.token);
+        assert_eq!(Span::new(Location::new(2, 5), Location::new(2, 11)), t.span);
+        let t = lexer.advance().unwrap();
+        assert_eq!(Token::Number(10, LiteralType::Int), t.token);
+        assert_eq!(Span::new(Location::new(2, 12), Location::new(2, 14)), t.span);
+        lexer.advance().unwrap();
+        assert_eq!(Location::new(3, 1), lexer.advance().unwrap().span.start);
+        assert_eq!(Token::Eof, lexer.advance().unwrap().token);
+    }
+
+    #[test]
+    fn test_error_location() {
+        let mut lexer = Lexer::new(String::from("int a;\nchar b = 'xy';"));
+        for _ in 0..6 {
+            lexer.advance().unwrap();
+        }
+        assert_eq!(Location::new(2, 10), lexer.advance().unwrap_err().location);
+
+        let mut lexer = Lexer::new(String::from("int\n  a;"));
+        lexer.advance().unwrap();
+        let err = lexer.matcher(Token::Semi).unwrap_err();
+        assert_eq!(Location::new(2, 3), err.location);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_comments() {
+        let mut lexer = Lexer::new(String::from("a // b c\n/* d\n /* e // */ f/**/g /*/ h */"));
+        assert_eq!(Token::Name(String::from("a")), lexer.advance().unwrap().token);
+        let t = lexer.advance().unwrap();
+        assert_eq!(Token::Name(String::from("f")), t.token);
+        assert_eq!(Location::new(3, 13), t.span.start);
+        assert_eq!(Token::Name(String::from("g")), lexer.advance().unwrap().token);
+        assert_eq!(Token::Eof, lexer.advance().unwrap().token);
+
+        let mut lexer = Lexer::new(String::from("a / b // c"));
+        assert_eq!(Token::Name(String::from("a")), lexer.advance().unwrap().token);
+        assert_eq!(Token::Div, lexer.advance().unwrap().token);
+        assert_eq!(Token::Name(String::from("b")), lexer.advance().unwrap().token);
+        assert_eq!(Token::Eof, lexer.advance().unwrap().token);
+
+        let mut lexer = Lexer::new(String::from("int a;\n  /* a */ /* b"));
+        for _ in 0..3 {
+            lexer.advance().unwrap();
+        }
+        let err = lexer.advance().unwrap_err();
+        assert_eq!(LexicalErrorType::UnterminatedComment, err.error);
+        assert_eq!(Location::new(2, 11), err.location);
+    }
+
+    #[test]
+    fn test_compound_assgin() {
+        let mut lexer = Lexer::new(String::from("+= -= *= /= %= <<= >>= &= |= ^= ? : << >= || a+ ... a.b"));
+        let tokens = [
+            Token::AddAssgin, Token::SubAssgin, Token::MulAssgin, Token::DivAssgin, Token::ModAssgin,
+            Token::LeftshiftAssgin, Token::RightshiftAssgin, Token::BitandAssgin, Token::BitorAssgin,
+            Token::BitxorAssgin, Token::Question, Token::Colon, Token::Leftshift, Token::Greaterequal,
+            Token::Or, Token::Name(String::from("a")), Token::Add, Token::Ellipsis,
+            Token::Name(String::from("a")), Token::Dot, Token::Name(String::from("b")), Token::Eof,
+        ];
+        for token in tokens {
+            assert_eq!(token, lexer.advance().unwrap().token);
+        }
+    }
+
+    #[test]
+    fn test_type_name() {
+        let mut lexer = Lexer::new(String::from("Node Node"));
+        assert_eq!(Token::Name(String::from("Node")), lexer.lookahead(1).unwrap().token);
+        lexer.lookahead(2).unwrap();
+        // 已经读进lookahead的名字也要区分出来
+        lexer.add_type_name("Node");
+        assert_eq!(Token::TypeName(String::from("Node")), lexer.advance().unwrap().token);
+        assert_eq!(Token::TypeName(String::from("Node")), lexer.advance().unwrap().token);
+
+        // 里层的变量遮住typedef，离开作用域之后恢复
+        let mut lexer = Lexer::new(String::from("Node Node Node"));
+        lexer.add_type_name("Node");
+        lexer.enter_scope();
+        lexer.add_var_name("Node");
+        assert_eq!(Token::Name(String::from("Node")), lexer.advance().unwrap().token);
+        lexer.enter_scope();
+        assert_eq!(Token::Name(String::from("Node")), lexer.advance().unwrap().token);
+        lexer.leave_scope();
+        lexer.leave_scope();
+        assert_eq!(Token::TypeName(String::from("Node")), lexer.advance().unwrap().token);
+    }
+
+    #[test]
+    fn test_number_literal() {
+        let mut lexer = Lexer::new(String::from("0x1F 017 0b101 0 0u 10UL 2147483647 0xFFFFFFFF 9223372036854775807L 0xFFFFFFFFFFFFFFFFULL \
+            2147483648 5000000000 0x100000000 0x8000000000000000 037777777777 4294967296u 0xFFFFFFFFFFFFFFFFL"));
+        let numbers = [
+            (31, LiteralType::Int),
+            (15, LiteralType::Int),
+            (5, LiteralType::Int),
+            (0, LiteralType::Int),
+            (0, LiteralType::UnsignedInt),
+            (10, LiteralType::UnsignedLong),
+            (i32::MAX as u64, LiteralType::Int),
+            (u32::MAX as u64, LiteralType::UnsignedInt),
+            (i64::MAX as u64, LiteralType::Long),
+            (u64::MAX, LiteralType::UnsignedLong),
+            // 十进制放不下int时是long，十六进制和八进制先试无符号的同样长度
+            (1 << 31, LiteralType::Long),
+            (5000000000, LiteralType::Long),
+            (1 << 32, LiteralType::Long),
+            (1 << 63, LiteralType::UnsignedLong),
+            (u32::MAX as u64, LiteralType::UnsignedInt),
+            (1 << 32, LiteralType::UnsignedLong),
+            (u64::MAX, LiteralType::UnsignedLong),
+        ];
+        for (n, literal_type) in numbers {
+            assert_eq!(Token::Number(n, literal_type), lexer.advance().unwrap().token);
+        }
+
+        let mut lexer = Lexer::new(String::from("3.25 1e-9 2.0f .5 1.5E+3L 1.e2"));
+        let numbers = [
+            (3.25, LiteralType::Double),
+            (1e-9, LiteralType::Double),
+            (2.0, LiteralType::Float),
+            (0.5, LiteralType::Double),
+            (1500.0, LiteralType::Double),
+            (100.0, LiteralType::Double),
+        ];
+        for (n, literal_type) in numbers {
+            assert_eq!(Token::FloatNumber(n, literal_type), lexer.advance().unwrap().token);
+        }
+
+        let mut lexer = Lexer::new(String::from("a-1.5*b"));
+        assert_eq!(Token::Name(String::from("a")), lexer.advance().unwrap().token);
+        assert_eq!(Token::Sub, lexer.advance().unwrap().token);
+        assert_eq!(Token::FloatNumber(1.5, LiteralType::Double), lexer.advance().unwrap().token);
+        assert_eq!(Token::Mul, lexer.advance().unwrap().token);
+
+        for (text, error) in [
+            ("9223372036854775808", LexicalErrorType::NumberOverflow),
+            ("9223372036854775808L", LexicalErrorType::NumberOverflow),
+            ("1e39f", LexicalErrorType::NumberOverflow),
+            ("0x10000000000000000u", LexicalErrorType::NumberOverflow),
+            ("1e999", LexicalErrorType::NumberOverflow),
+            ("08", LexicalErrorType::InvalidNumber),
+            ("0b102", LexicalErrorType::InvalidNumber),
+            ("10uu", LexicalErrorType::InvalidNumber),
+            ("1.5x", LexicalErrorType::InvalidNumber),
+            ("0x", LexicalErrorType::InvalidNumber),
+        ] {
+            let mut lexer = Lexer::new(String::from(text));
+            assert_eq!(error, lexer.advance().unwrap_err().error, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_escape_sequence() {
+        let mut lexer = Lexer::new(String::from(r#""a\"b\n" "\x41\101\0\\" '\n' '\0' '\'' '\x7f' 'a'"#));
+        assert_eq!(Token::String(String::from("a\"b\n")), lexer.advance().unwrap().token);
+        assert_eq!(Token::String(String::from("AA\0\\")), lexer.advance().unwrap().token);
+        for c in ['\n', '\0', '\'', '\x7f', 'a'] {
+            assert_eq!(Token::Character(c), lexer.advance().unwrap().token);
+        }
+        assert_eq!(Token::Eof, lexer.advance().unwrap().token);
+
+        let mut lexer = Lexer::new(String::from(r#"int "ab\q""#));
+        lexer.advance().unwrap();
+        let err = lexer.advance().unwrap_err();
+        assert_eq!(LexicalErrorType::InvalidEscape, err.error);
+        assert_eq!(Location::new(1, 8), err.location);
+
+        for text in [r"'\xg'", r"'\400'", r#""\"#] {
+            let mut lexer = Lexer::new(String::from(text));
+            assert_eq!(LexicalErrorType::InvalidEscape, lexer.advance().unwrap_err().error, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_tab_width() {
+        let content = "int\ta;\n\t\x0c\x0breturn  \t1;";
+        let mut lexer = Lexer::new(String::from(content));
+        assert_eq!(Token::Int, lexer.advance().unwrap().token);
+        assert_eq!(Location::new(1, 9), lexer.advance().unwrap().span.start);
+        lexer.advance().unwrap();
+        assert_eq!(Span::new(Location::new(2, 11), Location::new(2, 17)), lexer.advance().unwrap().span);
+        assert_eq!(Location::new(2, 25), lexer.advance().unwrap().span.start);
+
+        let mut lexer = Lexer::new(String::from(content));
+        lexer.set_tab_width(4);
+        lexer.advance().unwrap();
+        assert_eq!(Location::new(1, 5), lexer.advance().unwrap().span.start);
+        lexer.advance().unwrap();
+        assert_eq!(Location::new(2, 7), lexer.advance().unwrap().span.start);
+        assert_eq!(Location::new(2, 17), lexer.advance().unwrap().span.start);
+    }
+}
@@ -1,5 +1,9 @@
+// 项目里的函数统一用显式的return返回值
+#![allow(clippy::needless_return)]
+
 pub mod lexer;
 pub mod token;
 pub mod token_str;
-mod error;
-mod location;
\ No newline at end of file
+pub mod error;
+pub mod location;
+pub mod preprocessor;
@@ -1,6 +1,9 @@
 use std::fmt;
 
-#[derive(Clone)]
+// 和gcc的-ftabstop一样默认8列
+pub const DEFAULT_TAB_WIDTH: usize = 8;
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Location {
     pub row: usize,
     pub column: usize,
@@ -13,10 +16,51 @@ impl Location {
             column,
         }
     }
+
+    /**
+     *  同一行里读过字符ch之后的位置，tab跳到下一个制表位
+     */
+    pub fn advance(&self, ch: char, tab_width: usize) -> Location {
+        let column = if ch == '\t' && tab_width > 0 {
+            (self.column - 1) / tab_width * tab_width + tab_width + 1
+        } else {
+            self.column + 1
+        };
+
+        Location::new(self.row, column)
+    }
 }
 
 impl fmt::Display for Location {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "line {} column {}", self.row, self.column)
     }
-}
\ No newline at end of file
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    /*
+        [start, end) 左闭右开
+    */
+    pub start: Location,
+    pub end: Location,
+}
+
+impl Span {
+    pub fn new(start: Location, end: Location) -> Span {
+        Span {
+            start,
+            end,
+        }
+    }
+
+    pub fn merge(&self, other: &Span) -> Span {
+        Span::new(self.start.clone(), other.end.clone())
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.start)
+    }
+}
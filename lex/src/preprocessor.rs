@@ -0,0 +1,1214 @@
+use std::collections:: {
+    HashMap,
+    HashSet,
+};
+use std::fs;
+use std::path:: {
+    Path,
+    PathBuf,
+};
+use crate::error:: {
+    PreprocessError,
+    PreprocessErrorType,
+};
+use crate::location:: {
+    Location,
+    Span,
+    DEFAULT_TAB_WIDTH,
+};
+
+// 防止头文件互相包含时无限递归
+const MAX_INCLUDE_DEPTH: usize = 200;
+
+const PUNCTUATORS: [&str; 23] = [
+    "...", "<<=", ">>=", "##", "||", "&&", "==", "!=", "<=", ">=", "<<", ">>", "->",
+    "++", "--", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=",
+];
+
+// 展开后的记号和它来自的记号范围[start, end)
+type Expanded = Vec<(String, (usize, usize))>;
+// (起始行号, 逻辑行, 占用的原始行数, 每一段在逻辑行里开始的字符下标和原始行号)
+type LogicalLine = (usize, String, usize, Vec<(usize, usize)>);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceLine {
+    pub file: String,
+    pub line: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Segment {
+    /*
+        展开后的一行里[column, end)这几列来自原始文件line行的[origin, origin_end)
+        宏展开出来的记号都对应宏调用的位置，原样输出的记号列号可以一一对应
+    */
+    column: usize,
+    end: usize,
+    line: usize,
+    origin: usize,
+    origin_end: usize,
+    verbatim: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SourceMap {
+    // 展开后文本的每一行对应的原始文件和行号
+    lines: Vec<SourceLine>,
+    // 展开后每一行里各段文本在原始文件里的位置
+    segments: Vec<Vec<Segment>>,
+    // 原始文件的内容，报错时显示展开之前的代码
+    sources: HashMap<String, Vec<String>>,
+    // 算列号时用的tab宽度，显示代码时要用同样的宽度
+    tab_width: usize,
+}
+
+impl SourceMap {
+    /**
+     *  row是展开后文本的行号，从1开始
+     */
+    pub fn lookup(&self, row: usize) -> Option<&SourceLine> {
+        if row == 0 {
+            return None
+        }
+        return self.lines.get(row - 1)
+    }
+
+    /**
+     *  把展开后文本里的span换成原始文件里的位置，返回的span里的行号也是原始文件的行号
+     */
+    pub fn map_span(&self, span: &Span) -> Option<(SourceLine, Span)> {
+        let line = self.lookup(span.start.row)?.clone();
+        let segments = &self.segments[span.start.row - 1];
+        let find = |column: usize| segments.iter().find(|s| s.column <= column && column < s.end);
+
+        let (row, start) = match find(span.start.column) {
+            Some(s) if s.verbatim => (s.line, s.origin + span.start.column - s.column),
+            Some(s) => (s.line, s.origin),
+            // 行尾之外的位置，比如文件结束，列号不变
+            None => (line.line, span.start.column),
+        };
+        let end = match find(span.end.column.saturating_sub(1)) {
+            _ if span.end.row != span.start.row => span.end.clone(),
+            Some(s) if s.line != row => Location::new(row + 1, 1),
+            Some(s) if s.verbatim => Location::new(row, s.origin + span.end.column - s.column),
+            Some(s) => Location::new(row, s.origin_end),
+            None => Location::new(row, start + span.end.column.saturating_sub(span.start.column)),
+        };
+        let line = SourceLine {
+            file: line.file,
+            line: row,
+        };
+
+        Some((line, Span::new(Location::new(row, start), end)))
+    }
+
+    /**
+     *  原始文件里的一行，没有记录这个文件时返回None
+     */
+    pub fn source_line(&self, line: &SourceLine) -> Option<&str> {
+        let lines = self.sources.get(&line.file)?;
+        return lines.get(line.line.checked_sub(1)?).map(|text| text.as_str())
+    }
+
+    pub fn tab_width(&self) -> usize {
+        return self.tab_width
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Macro {
+    Object(Vec<String>),
+    Function {
+        params: Vec<String>,
+        body: Vec<String>,
+    },
+}
+
+#[derive(Debug)]
+struct Conditional {
+    /*
+        active: 当前分支是否输出
+        taken: 之前是否已经有分支成立过
+        parent: 外层是否在输出
+    */
+    active: bool,
+    taken: bool,
+    parent: bool,
+    has_else: bool,
+    line: usize,
+}
+
+#[derive(Default)]
+struct Output {
+    text: String,
+    map: SourceMap,
+}
+
+impl Output {
+    fn push_line(&mut self, text: &str, file: &str, line: usize, segments: Vec<Segment>) {
+        self.text.push_str(text);
+        self.text.push('\n');
+        self.map.lines.push(SourceLine {
+            file: file.to_string(),
+            line,
+        });
+        self.map.segments.push(segments);
+    }
+}
+
+pub struct Preprocessor {
+    include_dirs: Vec<PathBuf>,
+    macros: HashMap<String, Macro>,
+    // #pragma once标记过的文件
+    once_files: HashSet<PathBuf>,
+    tab_width: usize,
+}
+
+impl Default for Preprocessor {
+    fn default() -> Self {
+        return Preprocessor::new()
+    }
+}
+
+impl Preprocessor {
+    pub fn new() -> Self {
+        Preprocessor {
+            include_dirs: Vec::new(),
+            macros: HashMap::new(),
+            once_files: HashSet::new(),
+            tab_width: DEFAULT_TAB_WIDTH,
+        }
+    }
+
+    /**
+     *  计算列号时一个tab占的宽度，要和之后词法分析用的一致，source map才能对上
+     */
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.tab_width = tab_width;
+    }
+
+    /**
+     *  #include <...>的搜索路径，#include "..."先找当前文件所在的目录
+     */
+    pub fn add_include_dir<P: Into<PathBuf>>(&mut self, dir: P) {
+        self.include_dirs.push(dir.into());
+    }
+
+    /**
+     *  相当于命令行的-Dname=value
+     */
+    pub fn define(&mut self, name: &str, value: &str) {
+        let body = tokenize(value).into_iter().filter(|t| !is_space(t)).collect();
+        self.macros.insert(name.to_string(), Macro::Object(body));
+    }
+
+    /**
+     *  filename用来解析相对路径的#include和生成source map
+     */
+    pub fn preprocess(&mut self, filename: &str, source: &str) -> Result<(String, SourceMap), PreprocessError> {
+        let mut output = Output::default();
+        output.map.tab_width = self.tab_width;
+        self.process(Path::new(filename), source, 0, &mut output)?;
+
+        Ok((output.text, output.map))
+    }
+
+    fn process(&mut self, path: &Path, source: &str, depth: usize, output: &mut Output) -> Result<(), PreprocessError> {
+        let file = path.display().to_string();
+        let originals: Vec<String> = source.lines().map(String::from).collect();
+        let text_of = |line: usize| originals.get(line - 1).cloned().unwrap_or_default();
+        let error = |line: usize, error: PreprocessErrorType| PreprocessError {
+            file: file.clone(),
+            line,
+            column: None,
+            text: text_of(line),
+            error,
+        };
+
+        let stripped = strip_comments(source, self.tab_width).map_err(|location| PreprocessError {
+            file: file.clone(),
+            line: location.row,
+            column: Some(location.column),
+            text: text_of(location.row),
+            error: PreprocessErrorType::UnterminatedComment,
+        })?;
+        output.map.sources.insert(file.clone(), originals.clone());
+        let lines = logical_lines(&stripped, source.lines().count() + 1);
+        let mut conditions: Vec<Conditional> = Vec::new();
+        let mut index = 0;
+        while index < lines.len() {
+            let (line, mut text, mut physical, mut pieces) = lines[index].clone();
+            index += 1;
+            let active = conditions.last().is_none_or(|c| c.active);
+            let trimmed = text.trim_start();
+            if let Some(directive) = trimmed.strip_prefix('#') {
+                let include = self.directive(directive, path, line, active, &mut conditions)
+                    .map_err(|e| error(line, e))?;
+                if let Some((name, header)) = include {
+                    if depth >= MAX_INCLUDE_DEPTH {
+                        return Err(error(line, PreprocessErrorType::IncludeTooDeep(name)))
+                    }
+                    let source = fs::read_to_string(&header)
+                        .map_err(|_| error(line, PreprocessErrorType::IncludeNotFound(name)))?;
+                    // 头文件里的错误带着头文件自己的位置
+                    self.process(&header, &source, depth + 1, output)?;
+                }
+                output.push_line("", &file, line, Vec::new());
+            } else if active {
+                // 函数宏的参数可以跨行，括号没闭合时把后面的行接上来，直到遇到预处理指令
+                let (tokens, expanded) = loop {
+                    let tokens = tokenize(&text);
+                    match self.expand_tokens(&tokens, &mut Vec::new()) {
+                        Err(PreprocessErrorType::UnterminatedMacroCall(_))
+                            if lines.get(index).is_some_and(|next| !next.1.trim_start().starts_with('#')) => {
+                            text.push(' ');
+                            let offset = text.chars().count();
+                            pieces.extend(lines[index].3.iter().map(|(start, line)| (start + offset, *line)));
+                            text.push_str(&lines[index].1);
+                            physical += lines[index].2;
+                            index += 1;
+                        },
+                        result => break (tokens, result.map_err(|e| error(line, e))?),
+                    }
+                };
+                let segments = segments(&tokens, &expanded, &pieces, &originals, self.tab_width);
+                let text: String = expanded.into_iter().map(|(token, _)| token).collect();
+                output.push_line(&text, &file, line, segments);
+            } else {
+                output.push_line("", &file, line, Vec::new());
+            }
+
+            // 续行符、块注释和跨行的宏调用拼起来的行补上空行，保证后面的行号不变
+            for offset in 1..physical {
+                output.push_line("", &file, line + offset, Vec::new());
+            }
+        }
+
+        match conditions.last() {
+            Some(c) => Err(error(c.line, PreprocessErrorType::UnterminatedConditional)),
+            None => Ok(()),
+        }
+    }
+
+    /**
+     *  处理一条预处理指令，#include返回要包含的文件名和路径
+     */
+    fn directive(
+        &mut self,
+        directive: &str,
+        path: &Path,
+        line: usize,
+        active: bool,
+        conditions: &mut Vec<Conditional>,
+    ) -> Result<Option<(String, PathBuf)>, PreprocessErrorType> {
+        let tokens: Vec<String> = tokenize(directive).into_iter().filter(|t| !is_space(t)).collect();
+        let name = match tokens.first() {
+            Some(name) => name.as_str(),
+            // 只有一个#的空指令
+            None => return Ok(None),
+        };
+        let rest = directive.trim_start()[name.len()..].trim();
+        let args = &tokens[1..];
+
+        match name {
+            "if" | "ifdef" | "ifndef" => {
+                let value = active && match name {
+                    "if" => self.evaluate(args)?,
+                    "ifdef" => self.macros.contains_key(macro_name(args)?),
+                    _ => !self.macros.contains_key(macro_name(args)?),
+                };
+                conditions.push(Conditional {
+                    active: value,
+                    taken: value,
+                    parent: active,
+                    has_else: false,
+                    line,
+                });
+            },
+            "elif" => {
+                let top = match conditions.last_mut() {
+                    Some(top) if !top.has_else => top,
+                    _ => return Err(PreprocessErrorType::UnmatchedDirective(name.to_string())),
+                };
+                if top.taken || !top.parent {
+                    top.active = false;
+                } else {
+                    top.active = self.evaluate(args)?;
+                    top.taken = top.active;
+                }
+            },
+            "else" => {
+                let top = match conditions.last_mut() {
+                    Some(top) if !top.has_else => top,
+                    _ => return Err(PreprocessErrorType::UnmatchedDirective(name.to_string())),
+                };
+                top.active = top.parent && !top.taken;
+                top.taken = true;
+                top.has_else = true;
+            },
+            "endif" => {
+                if conditions.pop().is_none() {
+                    return Err(PreprocessErrorType::UnmatchedDirective(name.to_string()))
+                }
+            },
+            // 不输出的分支里其它指令都忽略
+            _ if !active => {},
+            "define" => self.define_macro(rest)?,
+            "undef" => {
+                self.macros.remove(macro_name(args)?);
+            },
+            "include" => return self.include(rest, path),
+            "pragma" => {
+                if args.first().map(|t| t.as_str()) == Some("once") {
+                    self.once_files.insert(canonical(path));
+                }
+            },
+            "error" => return Err(PreprocessErrorType::ErrorDirective(rest.to_string())),
+            _ => return Err(PreprocessErrorType::UnknownDirective(name.to_string())),
+        }
+
+        Ok(None)
+    }
+
+    fn define_macro(&mut self, definition: &str) -> Result<(), PreprocessErrorType> {
+        /*
+            #define NAME body
+            #define NAME(a, b) body  名字和括号之间不能有空格
+        */
+        let tokens = tokenize(definition);
+        let name = match tokens.first() {
+            Some(name) if is_identifier(name) => name.clone(),
+            _ => return Err(PreprocessErrorType::InvalidMacro),
+        };
+
+        if tokens.get(1).map(|t| t.as_str()) != Some("(") {
+            let body = trim_spaces(&tokens[1..]);
+            self.macros.insert(name, Macro::Object(body));
+            return Ok(())
+        }
+
+        let mut params = Vec::new();
+        let mut index = 2;
+        loop {
+            let token = tokens.get(index).map(|t| t.as_str());
+            index += 1;
+            match token {
+                Some(t) if is_space(t) || t == "," => {},
+                Some(")") => break,
+                Some("...") => params.push(String::from("__VA_ARGS__")),
+                Some(param) if is_identifier(param) => params.push(param.to_string()),
+                _ => return Err(PreprocessErrorType::InvalidMacro),
+            }
+        }
+
+        let body = trim_spaces(&tokens[index..]);
+        self.macros.insert(name, Macro::Function { params, body });
+
+        Ok(())
+    }
+
+    /**
+     *  #pragma once包含过的文件返回None
+     */
+    fn include(&self, target: &str, path: &Path) -> Result<Option<(String, PathBuf)>, PreprocessErrorType> {
+        // #include后面也可以是一个展开成文件名的宏
+        let target = if target.starts_with('"') || target.starts_with('<') {
+            target.to_string()
+        } else {
+            self.expand(&tokenize(target), &mut Vec::new())?.concat().trim().to_string()
+        };
+
+        let (name, quoted) = if let Some(name) = target.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+            (name, true)
+        } else if let Some(name) = target.strip_prefix('<').and_then(|t| t.strip_suffix('>')) {
+            (name, false)
+        } else {
+            return Err(PreprocessErrorType::InvalidInclude)
+        };
+
+        let mut dirs = Vec::new();
+        if quoted {
+            dirs.push(path.parent().map(|p| p.to_path_buf()).unwrap_or_default());
+        }
+        dirs.extend(self.include_dirs.iter().cloned());
+
+        let file = match dirs.iter().map(|dir| dir.join(name)).find(|file| file.is_file()) {
+            Some(file) => file,
+            None => return Err(PreprocessErrorType::IncludeNotFound(name.to_string())),
+        };
+        if self.once_files.contains(&canonical(&file)) {
+            return Ok(None)
+        }
+
+        Ok(Some((name.to_string(), file)))
+    }
+
+    /**
+     *  展开一行里的宏，disabled是正在展开的宏，防止宏递归展开自己
+     */
+    fn expand(&self, tokens: &[String], disabled: &mut Vec<String>) -> Result<Vec<String>, PreprocessErrorType> {
+        let expanded = self.expand_tokens(tokens, disabled)?;
+        return Ok(expanded.into_iter().map(|(token, _)| token).collect())
+    }
+
+    /**
+     *  展开后的每个记号带着它来自tokens里的哪些记号，宏展开出来的记号对应整个宏调用
+     */
+    fn expand_tokens(&self, tokens: &[String], disabled: &mut Vec<String>) -> Result<Expanded, PreprocessErrorType> {
+        let mut result = Vec::new();
+        let mut index = 0;
+        while index < tokens.len() {
+            let token = &tokens[index];
+            let origin = index;
+            index += 1;
+            let def = match self.macros.get(token) {
+                Some(def) if !disabled.contains(token) => def,
+                _ => {
+                    result.push((token.clone(), (origin, index)));
+                    continue
+                },
+            };
+
+            let replaced = match def {
+                Macro::Object(body) => body.clone(),
+                Macro::Function { params, body } => {
+                    // 函数宏后面没有括号时按普通标识符处理
+                    let open = skip_spaces(tokens, index);
+                    if tokens.get(open).map(|t| t.as_str()) != Some("(") {
+                        result.push((token.clone(), (origin, index)));
+                        continue
+                    }
+                    let (args, next) = match collect_args(tokens, open + 1) {
+                        Some(found) => found,
+                        None => return Err(PreprocessErrorType::UnterminatedMacroCall(token.clone())),
+                    };
+                    index = next;
+                    self.substitute(token, params, body, args, disabled)?
+                },
+            };
+
+            disabled.push(token.clone());
+            let expanded = self.expand(&replaced, disabled);
+            disabled.pop();
+            result.extend(expanded?.into_iter().map(|t| (t, (origin, index))));
+        }
+
+        Ok(result)
+    }
+
+    fn substitute(
+        &self,
+        name: &str,
+        params: &[String],
+        body: &[String],
+        mut args: Vec<Vec<String>>,
+        disabled: &mut Vec<String>,
+    ) -> Result<Vec<String>, PreprocessErrorType> {
+        // F()既可以是没有参数，也可以是一个空参数
+        if params.is_empty() && args.len() == 1 && trim_spaces(&args[0]).is_empty() {
+            args.clear();
+        }
+        let variadic = params.last().map(|p| p.as_str()) == Some("__VA_ARGS__");
+        if variadic && args.len() > params.len() {
+            let rest = args.split_off(params.len() - 1);
+            args.push(rest.join(&String::from(",")));
+        }
+        if variadic && args.len() + 1 == params.len() {
+            args.push(Vec::new());
+        }
+        if args.len() != params.len() {
+            return Err(PreprocessErrorType::MacroArguments {
+                name: name.to_string(),
+                expect: params.len(),
+                found: args.len(),
+            })
+        }
+
+        let arg_of = |token: &str| params.iter().position(|p| p == token).map(|i| trim_spaces(&args[i]));
+        let mut result: Vec<String> = Vec::new();
+        let mut index = 0;
+        while index < body.len() {
+            let token = &body[index];
+            index += 1;
+            if token == "#" {
+                // #param 把参数变成字符串
+                let next = skip_spaces(body, index);
+                if let Some(arg) = body.get(next).and_then(|t| arg_of(t)) {
+                    result.push(stringify(&arg));
+                    index = next + 1;
+                    continue
+                }
+            }
+
+            if token == "##" {
+                // a ## b 把两边的记号拼起来，参数不展开
+                while result.last().is_some_and(|t| is_space(t)) {
+                    result.pop();
+                }
+                let next = skip_spaces(body, index);
+                let mut right = match body.get(next) {
+                    Some(t) => arg_of(t).unwrap_or_else(|| vec![t.clone()]),
+                    None => Vec::new(),
+                };
+                index = next + 1;
+                if let (Some(last), false) = (result.last_mut(), right.is_empty()) {
+                    last.push_str(&right.remove(0));
+                }
+                result.extend(right);
+                continue
+            }
+
+            match arg_of(token) {
+                Some(arg) => {
+                    let next = skip_spaces(body, index);
+                    if body.get(next).map(|t| t.as_str()) == Some("##") {
+                        result.extend(arg);
+                    } else {
+                        result.extend(self.expand(&arg, disabled)?);
+                    }
+                },
+                None => result.push(token.clone()),
+            }
+        }
+
+        Ok(result)
+    }
+
+    /**
+     *  #if和#elif后面的常量表达式
+     */
+    fn evaluate(&self, tokens: &[String]) -> Result<bool, PreprocessErrorType> {
+        // 先处理defined，再展开宏，剩下的标识符都当作0
+        let mut replaced = Vec::new();
+        let mut index = 0;
+        while index < tokens.len() {
+            if tokens[index] != "defined" {
+                replaced.push(tokens[index].clone());
+                index += 1;
+                continue
+            }
+            let name = match (tokens.get(index + 1), tokens.get(index + 3)) {
+                (Some(open), Some(close)) if open == "(" && close == ")" => {
+                    index += 4;
+                    &tokens[index - 2]
+                },
+                (Some(name), _) => {
+                    index += 2;
+                    name
+                },
+                _ => return Err(PreprocessErrorType::InvalidExpression),
+            };
+            let defined = if self.macros.contains_key(name) { "1" } else { "0" };
+            replaced.push(defined.to_string());
+        }
+
+        let expanded: Vec<String> = self.expand(&replaced, &mut Vec::new())?
+            .into_iter()
+            .filter(|t| !is_space(t))
+            .map(|t| if is_identifier(&t) { String::from("0") } else { t })
+            .collect();
+
+        let mut evaluator = Evaluator {
+            tokens: &expanded,
+            pos: 0,
+        };
+        let value = evaluator.conditional()?;
+        if evaluator.pos != expanded.len() {
+            return Err(PreprocessErrorType::InvalidExpression)
+        }
+
+        Ok(value != 0)
+    }
+}
+
+struct Evaluator<'a> {
+    tokens: &'a [String],
+    pos: usize,
+}
+
+impl Evaluator<'_> {
+    /*
+        conditional ::= binary [ ? conditional : conditional ]
+        binary ::= unary ( op unary ) *  按优先级从低到高
+        unary ::= ( ! | ~ | - | + ) unary | ( conditional ) | number | char
+    */
+    const LEVELS: [&'static [&'static str]; 10] = [
+        &["||"], &["&&"], &["|"], &["^"], &["&"], &["==", "!="],
+        &["<", ">", "<=", ">="], &["<<", ">>"], &["+", "-"], &["*", "/", "%"],
+    ];
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|t| t.as_str())
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), PreprocessErrorType> {
+        if self.peek() != Some(token) {
+            return Err(PreprocessErrorType::InvalidExpression)
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn conditional(&mut self) -> Result<i64, PreprocessErrorType> {
+        let cond = self.binary(0)?;
+        if self.peek() != Some("?") {
+            return Ok(cond)
+        }
+        self.pos += 1;
+        let left = self.conditional()?;
+        self.expect(":")?;
+        let right = self.conditional()?;
+
+        Ok(if cond != 0 { left } else { right })
+    }
+
+    fn binary(&mut self, level: usize) -> Result<i64, PreprocessErrorType> {
+        if level == Self::LEVELS.len() {
+            return self.unary()
+        }
+
+        let mut left = self.binary(level + 1)?;
+        while let Some(op) = self.peek().filter(|op| Self::LEVELS[level].contains(op)) {
+            let op = op.to_string();
+            self.pos += 1;
+            let right = self.binary(level + 1)?;
+            left = match op.as_str() {
+                "||" => (left != 0 || right != 0) as i64,
+                "&&" => (left != 0 && right != 0) as i64,
+                "|" => left | right,
+                "^" => left ^ right,
+                "&" => left & right,
+                "==" => (left == right) as i64,
+                "!=" => (left != right) as i64,
+                "<" => (left < right) as i64,
+                ">" => (left > right) as i64,
+                "<=" => (left <= right) as i64,
+                ">=" => (left >= right) as i64,
+                "<<" => left.wrapping_shl(right as u32),
+                ">>" => left.wrapping_shr(right as u32),
+                "+" => left.wrapping_add(right),
+                "-" => left.wrapping_sub(right),
+                "*" => left.wrapping_mul(right),
+                _ => {
+                    if right == 0 {
+                        return Err(PreprocessErrorType::InvalidExpression)
+                    }
+                    if op == "/" { left.wrapping_div(right) } else { left.wrapping_rem(right) }
+                },
+            };
+        }
+
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<i64, PreprocessErrorType> {
+        let token = match self.peek() {
+            Some(token) => token.to_string(),
+            None => return Err(PreprocessErrorType::InvalidExpression),
+        };
+        self.pos += 1;
+        match token.as_str() {
+            "!" => Ok((self.unary()? == 0) as i64),
+            "~" => Ok(!self.unary()?),
+            "-" => Ok(self.unary()?.wrapping_neg()),
+            "+" => self.unary(),
+            "(" => {
+                let value = self.conditional()?;
+                self.expect(")")?;
+                Ok(value)
+            },
+            _ => parse_value(&token).ok_or(PreprocessErrorType::InvalidExpression),
+        }
+    }
+}
+
+fn parse_value(token: &str) -> Option<i64> {
+    if let Some(c) = token.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) {
+        let mut chars = c.chars();
+        return match (chars.next(), chars.next()) {
+            (Some('\\'), Some(e)) => Some(match e {
+                'n' => 10,
+                't' => 9,
+                'r' => 13,
+                '0' => 0,
+                _ => e as i64,
+            }),
+            (Some(c), None) => Some(c as i64),
+            _ => None,
+        }
+    }
+
+    let digits = token.trim_end_matches(['u', 'U', 'l', 'L']);
+    let lower = digits.to_ascii_lowercase();
+    let value = if let Some(hex) = lower.strip_prefix("0x") {
+        u64::from_str_radix(hex, 16)
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        u64::from_str_radix(bin, 2)
+    } else if lower.len() > 1 && lower.starts_with('0') {
+        u64::from_str_radix(&lower[1..], 8)
+    } else {
+        lower.parse()
+    };
+
+    value.ok().map(|v| v as i64)
+}
+
+/**
+ *  把一行切成预处理记号，空白原样保留，没有宏的行展开后列号不变
+ */
+fn tokenize(line: &str) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let ch = chars[index];
+        let start = index;
+        index += 1;
+        if ch.is_whitespace() {
+            while index < chars.len() && chars[index].is_whitespace() {
+                index += 1;
+            }
+            tokens.push(chars[start..index].iter().collect());
+            continue
+        }
+
+        let next_digit = chars.get(index).is_some_and(|c| c.is_ascii_digit());
+        if ch.is_alphabetic() || ch == '_' {
+            while index < chars.len() && (chars[index].is_alphanumeric() || chars[index] == '_') {
+                index += 1;
+            }
+        } else if ch.is_ascii_digit() || (ch == '.' && next_digit) {
+            while index < chars.len() {
+                let c = chars[index];
+                let exponent = (c == '+' || c == '-') && matches!(chars[index - 1], 'e' | 'E' | 'p' | 'P');
+                if c.is_alphanumeric() || c == '_' || c == '.' || exponent {
+                    index += 1;
+                } else {
+                    break
+                }
+            }
+        } else if ch == '"' || ch == '\'' {
+            while index < chars.len() && chars[index] != ch {
+                index += if chars[index] == '\\' { 2 } else { 1 };
+            }
+            index = (index + 1).min(chars.len());
+        } else if let Some(p) = PUNCTUATORS.iter().find(|p| line_starts_with(&chars[start..], p)) {
+            index = start + p.chars().count();
+        }
+
+        tokens.push(chars[start..index].iter().collect());
+    }
+
+    tokens
+}
+
+fn line_starts_with(chars: &[char], prefix: &str) -> bool {
+    let prefix: Vec<char> = prefix.chars().collect();
+    chars.starts_with(&prefix)
+}
+
+/**
+ *  块注释换成同样宽度的空白，里面的换行保留下来，这样注释后面代码的行号和列号都不变
+ *  预处理指令里的块注释换成一个空格，#define X 1 /* a \n b */ + 2里X是1 + 2
+ *  返回每一行和它在原始文件里开始的行号，出错时返回注释开始的位置
+ */
+fn strip_comments(source: &str, tab_width: usize) -> Result<Vec<(usize, String)>, Location> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut result = String::new();
+    let mut starts = vec![1];
+    let mut line = 1;
+    // 当前原始行第一个字符的位置，用来算注释开始的列号
+    let mut line_start = 0;
+    // 当前逻辑行在result里开始的位置，用来判断是不是预处理指令
+    let mut logical_start = 0;
+    let mut index = 0;
+    while index < chars.len() {
+        let ch = chars[index];
+        let next = chars.get(index + 1).copied();
+        if ch == '/' && next == Some('/') {
+            while index < chars.len() && chars[index] != '\n' {
+                index += 1;
+            }
+            result.push(' ');
+        } else if ch == '/' && next == Some('*') {
+            let start = chars[line_start..index].iter()
+                .fold(Location::new(line, 1), |location, c| location.advance(*c, tab_width));
+            let directive = result[logical_start..].trim_start().starts_with('#');
+            let comment_start = index;
+            index += 2;
+            loop {
+                match chars.get(index) {
+                    None => return Err(start),
+                    Some('*') if chars.get(index + 1) == Some(&'/') => break,
+                    Some('\n') => {
+                        line += 1;
+                        line_start = index + 1;
+                    },
+                    _ => {},
+                }
+                index += 1;
+            }
+            index += 2;
+            if directive {
+                result.push(' ');
+                continue
+            }
+            for c in &chars[comment_start..index] {
+                match c {
+                    '\n' => {
+                        result.push('\n');
+                        logical_start = result.len();
+                    },
+                    '\t' => result.push('\t'),
+                    _ => result.push(' '),
+                }
+            }
+            starts.extend(line - chars[comment_start..index].iter().filter(|c| **c == '\n').count() + 1..=line);
+        } else if ch == '"' || ch == '\'' {
+            // 字符串里的//和/*不是注释
+            result.push(ch);
+            index += 1;
+            while index < chars.len() && chars[index] != ch && chars[index] != '\n' {
+                if chars[index] == '\\' && index + 1 < chars.len() {
+                    result.push(chars[index]);
+                    index += 1;
+                }
+                result.push(chars[index]);
+                index += 1;
+            }
+            if index < chars.len() && chars[index] == ch {
+                result.push(ch);
+                index += 1;
+            }
+        } else {
+            if ch == '\n' {
+                line += 1;
+                line_start = index + 1;
+                starts.push(line);
+                if !result.ends_with('\\') {
+                    logical_start = result.len() + 1;
+                }
+            }
+            result.push(ch);
+            index += 1;
+        }
+    }
+
+    Ok(starts.into_iter().zip(result.lines().map(String::from)).collect())
+}
+
+/**
+ *  用反斜杠续行的行拼成一个逻辑行，end是原始文件最后一行的下一行
+ */
+fn logical_lines(lines: &[(usize, String)], end: usize) -> Vec<LogicalLine> {
+    let mut result = Vec::new();
+    let mut current = String::new();
+    let mut pieces = Vec::new();
+    let mut start = None;
+    for (index, (line, text)) in lines.iter().enumerate() {
+        let first = *start.get_or_insert(*line);
+        pieces.push((current.chars().count(), *line));
+        match text.strip_suffix('\\') {
+            Some(body) => current.push_str(body),
+            None => {
+                current.push_str(text);
+                let next = lines.get(index + 1).map_or(end, |(line, _)| *line);
+                result.push((first, current.clone(), next - first, pieces.clone()));
+                current.clear();
+                pieces.clear();
+                start = None;
+            },
+        }
+    }
+    if let Some(first) = start {
+        result.push((first, current, end - first, pieces));
+    }
+
+    result
+}
+
+/**
+ *  算出展开后的一行里每一段对应的原始位置，相邻的来自同一个宏调用的记号合成一段
+ *  pieces是logical_lines返回的每一段的起始下标和行号，originals是原始文件的每一行
+ */
+fn segments(
+    tokens: &[String],
+    expanded: &[(String, (usize, usize))],
+    pieces: &[(usize, usize)],
+    originals: &[String],
+    tab_width: usize,
+) -> Vec<Segment> {
+    // 每个记号在原始文件里的行号和列号范围
+    let mut origins = Vec::new();
+    let mut offset = 0;
+    for token in tokens {
+        let (start, line) = pieces.iter().rev().find(|(start, _)| *start <= offset).copied().unwrap_or((0, 1));
+        let chars: Vec<char> = originals.get(line - 1).map(|text| text.chars().collect()).unwrap_or_default();
+        let column = |index: usize| chars.iter().take(index)
+            .fold(Location::new(line, 1), |location, c| location.advance(*c, tab_width)).column;
+        let length = token.chars().count();
+        origins.push((line, column(offset - start), column(offset - start + length)));
+        offset += length;
+    }
+
+    let mut result: Vec<Segment> = Vec::new();
+    let mut location = Location::new(1, 1);
+    let mut last = None;
+    for (token, (first, next)) in expanded {
+        let column = location.column;
+        location = token.chars().fold(location, |location, c| location.advance(c, tab_width));
+        let (line, start, name_end) = origins[*first];
+        // 跨行的宏调用只标出宏名
+        let end = match origins[*next - 1] {
+            (last_line, _, end) if last_line == line => end,
+            _ => name_end,
+        };
+        match result.last_mut() {
+            Some(segment) if last == Some(*first) => {
+                segment.end = location.column;
+                segment.verbatim = false;
+            },
+            _ => result.push(Segment {
+                column,
+                end: location.column,
+                line,
+                origin: start,
+                origin_end: end,
+                verbatim: *next == *first + 1 && tokens[*first] == *token,
+            }),
+        }
+        last = Some(*first);
+    }
+
+    result
+}
+
+/**
+ *  收集函数宏调用的参数，start是左括号之后的位置
+ *  返回参数和右括号之后的位置，括号不匹配时返回None
+ */
+fn collect_args(tokens: &[String], start: usize) -> Option<(Vec<Vec<String>>, usize)> {
+    let mut args = vec![Vec::new()];
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate().skip(start) {
+        match token.as_str() {
+            ")" if depth == 0 => return Some((args, index + 1)),
+            "," if depth == 0 => {
+                args.push(Vec::new());
+                continue
+            },
+            "(" => depth += 1,
+            ")" => depth -= 1,
+            _ => {},
+        }
+        args.last_mut()?.push(token.clone());
+    }
+
+    None
+}
+
+fn stringify(tokens: &[String]) -> String {
+    // 参数中间的空白合并成一个空格
+    let text: String = tokens.iter().map(|t| if is_space(t) { " " } else { t.as_str() }).collect();
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn skip_spaces(tokens: &[String], mut index: usize) -> usize {
+    while tokens.get(index).is_some_and(|t| is_space(t)) {
+        index += 1;
+    }
+    index
+}
+
+fn trim_spaces(tokens: &[String]) -> Vec<String> {
+    let start = skip_spaces(tokens, 0);
+    let mut end = tokens.len();
+    while end > start && is_space(&tokens[end - 1]) {
+        end -= 1;
+    }
+    tokens[start..end].to_vec()
+}
+
+fn macro_name(args: &[String]) -> Result<&str, PreprocessErrorType> {
+    match args.first() {
+        Some(name) if is_identifier(name) => Ok(name),
+        _ => Err(PreprocessErrorType::InvalidMacro),
+    }
+}
+
+fn is_space(token: &str) -> bool {
+    token.starts_with(char::is_whitespace)
+}
+
+fn is_identifier(token: &str) -> bool {
+    token.starts_with(|c: char| c.is_alphabetic() || c == '_')
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(source: &str) -> Result<String, PreprocessErrorType> {
+        let mut preprocessor = Preprocessor::new();
+        match preprocessor.preprocess("test.c", source) {
+            Ok((text, _)) => Ok(text.split_whitespace().collect::<Vec<&str>>().join(" ")),
+            Err(err) => Err(err.error),
+        }
+    }
+
+    #[test]
+    fn test_macro_expansion() {
+        let text = run("
+            #define N 10
+            #define SQUARE(x) ((x) * (x))
+            #define MAX(a, b) ((a) > (b) ? (a) : (b))
+            #define STR(s) #s
+            #define CAT(a, b) a ## b
+            #define LOOP LOOP + 1
+            #define EMPTY() 0
+            int a = SQUARE(N + 1);
+            int b = MAX(SQUARE(2), f(1, 2));
+            char *s = STR(a \"b\");
+            int CAT(var, N) = LOOP;
+            int SQUARE = EMPTY();
+            #undef N
+            int c = N;
+        ").unwrap();
+        assert_eq!("\
+int a = ((10 + 1) * (10 + 1)); \
+int b = ((((2) * (2))) > (f(1, 2)) ? (((2) * (2))) : (f(1, 2))); \
+char *s = \"a \\\"b\\\"\"; \
+int varN = LOOP + 1; \
+int SQUARE = 0; \
+int c = N;", text);
+    }
+
+    #[test]
+    fn test_conditional() {
+        let text = run("
+            #define A 2
+            #ifdef A
+            int a;
+            #else
+            int b;
+            #endif
+            #ifndef B
+            int c;
+            #endif
+            #if A * 2 == 4 && !defined(B)
+            int d;
+            #elif 1
+            int e;
+            #else
+            int f;
+            #endif
+            #if defined B || UNKNOWN
+            int g;
+            #elif A > 1 ? 0 : 1
+            int h;
+            #else
+            #if 1
+            int i;
+            #endif
+            #endif
+        ").unwrap();
+        assert_eq!("int a; int c; int d; int i;", text);
+    }
+
+    #[test]
+    fn test_source_map() {
+        let mut preprocessor = Preprocessor::new();
+        let source = "#define ADD(a, b) \\\n    a + b\n/* x\n y */ int a = ADD(1, 2);\n";
+        let (text, map) = preprocessor.preprocess("test.c", source).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        // 块注释换成同样宽度的空白，后面的代码还在原来的行和列
+        assert_eq!(4, lines.len());
+        assert_eq!("    ", lines[2]);
+        assert_eq!("      int a = 1 + 2;", lines[3]);
+        assert_eq!(Some(&SourceLine { file: String::from("test.c"), line: 3 }), map.lookup(3));
+        assert_eq!(Some(&SourceLine { file: String::from("test.c"), line: 4 }), map.lookup(4));
+        assert_eq!(None, map.lookup(5));
+        assert_eq!(Some(" y */ int a = ADD(1, 2);"), map.source_line(&SourceLine { file: String::from("test.c"), line: 4 }));
+
+        // 宏展开出来的记号对应整个宏调用，后面的记号换回原来的列号
+        let line = SourceLine { file: String::from("test.c"), line: 4 };
+        let span = |start: usize, end: usize| Span::new(Location::new(4, start), Location::new(4, end));
+        assert_eq!(Some((line.clone(), span(11, 12))), map.map_span(&span(11, 12)));
+        assert_eq!(Some((line.clone(), span(15, 24))), map.map_span(&span(17, 18)));
+        assert_eq!(Some((line.clone(), span(15, 25))), map.map_span(&span(15, 21)));
+        assert_eq!(Some((line, span(24, 25))), map.map_span(&span(20, 21)));
+
+        // 跨行的宏调用也一样，之后的行号不变
+        let source = "#define X 1 /* a\n b */ + 2\n#define ADD(a, b) a + b\nint b = ADD(X,\n    3);\nint c;\n";
+        let (text, map) = preprocessor.preprocess("test.c", source).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(6, lines.len());
+        assert_eq!("int b = 1 + 2 + 3;", lines[3].split_whitespace().collect::<Vec<&str>>().join(" "));
+        assert_eq!("int c;", lines[5]);
+        assert_eq!(Some(&SourceLine { file: String::from("test.c"), line: 4 }), map.lookup(4));
+        assert_eq!(Some(&SourceLine { file: String::from("test.c"), line: 6 }), map.lookup(6));
+
+        // 列号按设置的tab宽度算
+        let mut preprocessor = Preprocessor::new();
+        preprocessor.set_tab_width(4);
+        let (_, map) = preprocessor.preprocess("test.c", "#define ONE 1\n\tint d = ONE + x;\n").unwrap();
+        let line = SourceLine { file: String::from("test.c"), line: 2 };
+        let span = |start: usize, end: usize| Span::new(Location::new(2, start), Location::new(2, end));
+        assert_eq!(4, map.tab_width());
+        assert_eq!(Some((line.clone(), span(13, 16))), map.map_span(&span(13, 14)));
+        assert_eq!(Some((line, span(19, 20))), map.map_span(&span(17, 18)));
+    }
+
+    #[test]
+    fn test_include() {
+        let dir = std::env::temp_dir().join(format!("cr7c_preprocess_{}", std::process::id()));
+        fs::create_dir_all(dir.join("sys")).unwrap();
+        fs::write(dir.join("a.h"), "#pragma once\nint a;\n#include <b.h>\n").unwrap();
+        fs::write(dir.join("sys").join("b.h"), "#ifndef B_H\n#define B_H\nint b;\n#endif\n").unwrap();
+        let main = dir.join("main.c");
+
+        let mut preprocessor = Preprocessor::new();
+        preprocessor.add_include_dir(dir.join("sys"));
+        let source = "#include \"a.h\"\n#include \"a.h\"\n#include <b.h>\nint main;\n";
+        let (text, map) = preprocessor.preprocess(&main.display().to_string(), source).unwrap();
+        assert_eq!("int a; int b; int main;", text.split_whitespace().collect::<Vec<&str>>().join(" "));
+        let row = text.lines().position(|line| line == "int b;").unwrap() + 1;
+        let line = map.lookup(row).unwrap();
+        assert!(line.file.ends_with("b.h"));
+        assert_eq!(3, line.line);
+
+        let err = preprocessor.preprocess(&main.display().to_string(), "\n#include \"c.h\"").unwrap_err();
+        assert_eq!(PreprocessErrorType::IncludeNotFound(String::from("c.h")), err.error);
+        assert_eq!(2, err.line);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_preprocess_error() {
+        assert_eq!(Err(PreprocessErrorType::UnterminatedConditional), run("#if 1\nint a;"));
+        assert_eq!(Err(PreprocessErrorType::UnmatchedDirective(String::from("else"))), run("#else"));
+        assert_eq!(Err(PreprocessErrorType::UnmatchedDirective(String::from("elif"))), run("#if 1\n#else\n#elif 1\n#endif"));
+        assert_eq!(Err(PreprocessErrorType::UnknownDirective(String::from("foo"))), run("#foo"));
+        assert_eq!(Err(PreprocessErrorType::InvalidExpression), run("#if 1 +\n#endif"));
+        assert_eq!(Err(PreprocessErrorType::UnterminatedComment), run("/* a"));
+        let err = Preprocessor::new().preprocess("test.c", "int a;\n\tint b; /* b\n c").unwrap_err();
+        assert_eq!((2, Some(16)), (err.line, err.column));
+        let mut preprocessor = Preprocessor::new();
+        preprocessor.set_tab_width(4);
+        let err = preprocessor.preprocess("test.c", "int a;\n\tint b; /* b\n c").unwrap_err();
+        assert_eq!((2, Some(12)), (err.line, err.column));
+        assert_eq!(Err(PreprocessErrorType::ErrorDirective(String::from("stop"))), run("#error stop"));
+        assert_eq!(Err(PreprocessErrorType::MacroArguments {
+            name: String::from("F"),
+            expect: 2,
+            found: 1,
+        }), run("#define F(a, b) a\nF(1)"));
+        assert_eq!(Err(PreprocessErrorType::UnterminatedMacroCall(String::from("F"))), run("#define F(a) a\nF(1"));
+        // 不输出的分支里的未知指令不报错
+        assert_eq!(Ok(String::from("")), run("#if 0\n#foo\n#endif"));
+    }
+}
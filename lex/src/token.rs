@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::fmt;
+use crate::location::Span;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Token {
@@ -12,6 +13,14 @@ pub enum Token {
     Long,
     Short,
 
+    // qualifier
+    Const,
+    Volatile,
+    Unsigned,
+    Signed,
+    Static,
+    Extern,
+
     // keywords
     While,
     For,
@@ -24,9 +33,15 @@ pub enum Token {
     Switch,
     Case,
     Default,
+    Goto,
     Import,
+    Sizeof,
+    Alignof,
 
     Struct,
+    Union,
+    Enum,
+    Typedef,
 
     // operator
     Add,
@@ -49,17 +64,33 @@ pub enum Token {
     Rightshift,
     Leftshift,
     Assgin,
+    AddAssgin,
+    SubAssgin,
+    MulAssgin,
+    DivAssgin,
+    ModAssgin,
+    LeftshiftAssgin,
+    RightshiftAssgin,
+    BitandAssgin,
+    BitorAssgin,
+    BitxorAssgin,
+    Question,
+    Ellipsis,
+    Colon,
     Not,
     Notequal,
     Pointer,
     PointerRef,
     Dot,
 
-    // literal
-    Number(i64),
+    // literal，数字带着后缀决定的类型
+    Number(u64, LiteralType),
+    FloatNumber(f64, LiteralType),
     String(String),
     Character(char),
     Name(String),
+    // typedef定义过的名字
+    TypeName(String),
 
     // separator
     LParentheses,
@@ -75,6 +106,53 @@ pub enum Token {
     Eof,
 }
 
+/**
+ *  数字字面量的类型，没有后缀的整数是int，浮点数是double
+ *  long long和long一样按long处理，long double按double处理
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LiteralType {
+    Int,
+    UnsignedInt,
+    Long,
+    UnsignedLong,
+    Float,
+    Double,
+}
+
+impl LiteralType {
+    pub fn base(&self) -> Token {
+        match self {
+            LiteralType::Int | LiteralType::UnsignedInt => Token::Int,
+            LiteralType::Long | LiteralType::UnsignedLong => Token::Long,
+            LiteralType::Float => Token::Float,
+            LiteralType::Double => Token::Double,
+        }
+    }
+
+    pub fn is_unsigned(&self) -> bool {
+        *self == LiteralType::UnsignedInt || *self == LiteralType::UnsignedLong
+    }
+
+    /**
+     *  整数类型能放下的最大值
+     */
+    pub fn max_value(&self) -> u64 {
+        match self {
+            LiteralType::Int => i32::MAX as u64,
+            LiteralType::UnsignedInt => u32::MAX as u64,
+            LiteralType::Long => i64::MAX as u64,
+            _ => u64::MAX,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
 pub fn get_keywords() -> HashMap<String, Token> {
     let mut map = HashMap::new();
 
@@ -85,6 +163,12 @@ pub fn get_keywords() -> HashMap<String, Token> {
     map.insert(String::from("void"), Token::Void);
     map.insert(String::from("long"), Token::Long);
     map.insert(String::from("short"), Token::Short);
+    map.insert(String::from("const"), Token::Const);
+    map.insert(String::from("volatile"), Token::Volatile);
+    map.insert(String::from("unsigned"), Token::Unsigned);
+    map.insert(String::from("signed"), Token::Signed);
+    map.insert(String::from("static"), Token::Static);
+    map.insert(String::from("extern"), Token::Extern);
     map.insert(String::from("for"), Token::For);
     map.insert(String::from("while"), Token::While);
     map.insert(String::from("if"), Token::If);
@@ -96,9 +180,16 @@ pub fn get_keywords() -> HashMap<String, Token> {
     map.insert(String::from("switch"), Token::Switch);
     map.insert(String::from("case"), Token::Case);
     map.insert(String::from("default"), Token::Default);
+    map.insert(String::from("goto"), Token::Goto);
     map.insert(String::from("import"), Token::Import);
+    map.insert(String::from("sizeof"), Token::Sizeof);
+    map.insert(String::from("_Alignof"), Token::Alignof);
+    map.insert(String::from("alignof"), Token::Alignof);
 
     map.insert(String::from("struct"), Token::Struct);
+    map.insert(String::from("union"), Token::Union);
+    map.insert(String::from("enum"), Token::Enum);
+    map.insert(String::from("typedef"), Token::Typedef);
 
     map
 }
@@ -111,7 +202,30 @@ pub fn is_base_type(token: &Token) -> bool {
     *token == Token::Long   ||
     *token == Token::Short  ||
     *token == Token::Void   ||
-    *token == Token::Struct
+    *token == Token::Struct ||
+    *token == Token::Union  ||
+    *token == Token::Enum   ||
+    matches!(token, Token::TypeName(_))
+}
+
+/**
+ *  可以写在类型前后的修饰，static和extern也放在这里
+ */
+pub fn is_qualifier(token: &Token) -> bool {
+    *token == Token::Const    ||
+    *token == Token::Volatile ||
+    *token == Token::Unsigned ||
+    *token == Token::Signed   ||
+    *token == Token::Static   ||
+    *token == Token::Extern
+}
+
+/**
+ *  有成员列表的类型，成员用.和->访问
+ */
+pub fn is_aggregate(token: &Token) -> bool {
+    *token == Token::Struct ||
+    *token == Token::Union
 }
 
 pub fn is_prefix_op(token: &Token) -> bool {
@@ -134,6 +248,29 @@ pub fn is_postfix_op(token: &Token) -> bool {
     *token == Token::LParentheses
 }
 
+pub fn is_assgin_op(token: &Token) -> bool {
+    *token == Token::Assgin || compound_op(token).is_some()
+}
+
+/**
+ *  复合赋值对应的二元运算符，a += b就是a = a + b
+ */
+pub fn compound_op(token: &Token) -> Option<Token> {
+    match token {
+        Token::AddAssgin => Some(Token::Add),
+        Token::SubAssgin => Some(Token::Sub),
+        Token::MulAssgin => Some(Token::Mul),
+        Token::DivAssgin => Some(Token::Div),
+        Token::ModAssgin => Some(Token::Mod),
+        Token::LeftshiftAssgin => Some(Token::Leftshift),
+        Token::RightshiftAssgin => Some(Token::Rightshift),
+        Token::BitandAssgin => Some(Token::Bitand),
+        Token::BitorAssgin => Some(Token::Bitor),
+        Token::BitxorAssgin => Some(Token::Bitxor),
+        _ => None,
+    }
+}
+
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -143,6 +280,12 @@ impl fmt::Display for Token {
             Token::Char => write!(f, "Token: <Char>"),
             Token::Long => write!(f, "Token: <Long>"),
             Token::Short => write!(f, "Token: <Short>"),
+            Token::Const => write!(f, "Token: <Const>"),
+            Token::Volatile => write!(f, "Token: <Volatile>"),
+            Token::Unsigned => write!(f, "Token: <Unsigned>"),
+            Token::Signed => write!(f, "Token: <Signed>"),
+            Token::Static => write!(f, "Token: <Static>"),
+            Token::Extern => write!(f, "Token: <Extern>"),
             Token::Void => write!(f, "Token: <Void>"),
             Token::While => write!(f, "Token: <While>"),
             Token::For => write!(f, "Token: <For>"),
@@ -155,8 +298,14 @@ impl fmt::Display for Token {
             Token::Switch => write!(f, "Token: <Switch>"),
             Token::Case => write!(f, "Token: <Case>"),
             Token::Default => write!(f, "Token: <Default>"),
+            Token::Goto => write!(f, "Token: <Goto>"),
             Token::Import => write!(f, "Token: <Import>"),
+            Token::Sizeof => write!(f, "Token: <Sizeof>"),
+            Token::Alignof => write!(f, "Token: <Alignof>"),
             Token::Struct => write!(f, "Token: <Struct>"),
+            Token::Union => write!(f, "Token: <Union>"),
+            Token::Enum => write!(f, "Token: <Enum>"),
+            Token::Typedef => write!(f, "Token: <Typedef>"),
             Token::Add => write!(f, "Token: <Add>"),
             Token::Sub => write!(f, "Token: <Sub>"),
             Token::Div => write!(f, "Token: <Div>"),
@@ -174,19 +323,33 @@ impl fmt::Display for Token {
             Token::Leftshift => write!(f, "Token: <Leftshift>"),
             Token::Greater => write!(f, "Token: <Greater>"),
             Token::Less => write!(f, "Token: <Less>"),
-            Token::Greater => write!(f, "Token: <Greater>"),
             Token::Greaterequal => write!(f, "Token: <Greaterequal>"),
             Token::Lessequal => write!(f, "Token: <Lessequal>"),
             Token::Not => write!(f, "Token: <Not>"),
             Token::Notequal => write!(f, "Token: <Notequal>"),
             Token::Equal => write!(f, "Token: <Equal>"),
             Token::Assgin => write!(f, "Token: <Assgin>"),
+            Token::AddAssgin => write!(f, "Token: <AddAssgin>"),
+            Token::SubAssgin => write!(f, "Token: <SubAssgin>"),
+            Token::MulAssgin => write!(f, "Token: <MulAssgin>"),
+            Token::DivAssgin => write!(f, "Token: <DivAssgin>"),
+            Token::ModAssgin => write!(f, "Token: <ModAssgin>"),
+            Token::LeftshiftAssgin => write!(f, "Token: <LeftshiftAssgin>"),
+            Token::RightshiftAssgin => write!(f, "Token: <RightshiftAssgin>"),
+            Token::BitandAssgin => write!(f, "Token: <BitandAssgin>"),
+            Token::BitorAssgin => write!(f, "Token: <BitorAssgin>"),
+            Token::BitxorAssgin => write!(f, "Token: <BitxorAssgin>"),
+            Token::Question => write!(f, "Token: <Question>"),
+            Token::Ellipsis => write!(f, "Token: <Ellipsis>"),
+            Token::Colon => write!(f, "Token: <Colon>"),
             Token::Pointer => write!(f, "Token: <Pointer>"),
             Token::PointerRef => write!(f, "Token: <PointerRef>"),
-            Token::Number(i) => write!(f, "Token: <Number : {}>", i),
+            Token::Number(i, _) => write!(f, "Token: <Number : {}>", i),
+            Token::FloatNumber(n, _) => write!(f, "Token: <FloatNumber : {}>", n),
             Token::String(s) => write!(f, "Token: <String : \"{}\">", s),
             Token::Character(u) => write!(f, "Token: <Character : {}>", u),
             Token::Name(s) => write!(f, "Token: <Name : {}>", s),
+            Token::TypeName(s) => write!(f, "Token: <TypeName : {}>", s),
             Token::LParentheses => write!(f, "Token: <LParentheses>"),
             Token::RParentheses => write!(f, "Token: <RParentheses>"),
             Token::LBrace => write!(f, "Token: <LBrace>"),
@@ -199,4 +362,10 @@ impl fmt::Display for Token {
             Token::Eof => write!(f, "Token: <Eof>"),
         }
     }
-}
\ No newline at end of file
+}
+
+impl fmt::Display for SpannedToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} in {}", self.token, self.span)
+    }
+}
@@ -1,14 +1,37 @@
 use lex::token::Token;
-use std::collections::HashMap;
+use lex::location::Span;
 use std::rc::Rc;
 use std::cell::RefCell;
-use crate::ast_expr::ExprNode;
+use crate::ast_expr:: {
+    ExprNode,
+    check_type_compatible,
+    expect_type,
+    init_list,
+};
 use crate::ast_stmt::StmtNode;
 use std::fmt;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use crate::error:: {
+    SemanticError,
+    SemanticErrorType,
+};
 use crate::symbol_table:: {
     TopLevelScope,
     LocalScope,
 };
+use crate::ir:: {
+    IR,
+    Func,
+    Assign,
+    ExprStmt,
+    Var,
+    Data,
+};
+
+fn redefinition(name: &str, prev: &Span, span: &Span) -> SemanticError {
+    SemanticError::at(SemanticErrorType::Redefinition(name.to_string(), prev.clone()), span.clone())
+}
 
 #[derive(Debug)]
 pub struct ProgramNode {
@@ -28,8 +51,16 @@ pub struct ImportStmtNode {
 }
 
 pub trait DefNode:fmt::Debug {
-    fn fill_symbol(&self, scope: &mut TopLevelScope) {}
-    fn check_expr_validity(&self, mut scope: &mut TopLevelScope) {}
+    fn span(&self) -> Span;
+    fn fill_symbol(&self, _scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        return Ok(())
+    }
+    fn check_expr_validity(&self, _scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        return Ok(())
+    }
+    fn generate(&self, _scope: &mut TopLevelScope, _ir: &mut IR) -> Result<(), SemanticError> {
+        return Ok(())
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -41,17 +72,146 @@ pub struct TopDefNode {
 #[derive(Clone, Debug, PartialEq)]
 pub struct DefStructNode {
     /*
-        STRUCT name member_list ;
+        ( STRUCT | UNION ) name member_list ;
         member_list ::= { (slot ;) *} 
     */
+    pub kind: Token,
     pub name: String,
     pub member_list: Vec<SlotNode>,
+    pub span: Span,
 }
 
 impl DefNode for DefStructNode {
-    fn fill_symbol(&self, scope: &mut TopLevelScope) {
-        scope.global_define_map.insert(self.name.clone(), self.clone());
-    }   
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn fill_symbol(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        if let Some(prev) = scope.global_define_map.get(&self.name) {
+            return Err(redefinition(&self.name, &prev.span, &self.span))
+        }
+        // 成员名不能重复，报错时指向第一次出现的位置
+        let mut members: HashMap<&String, &Span> = HashMap::new();
+        for member in &self.member_list {
+            if let Some(prev) = members.get(&member.name) {
+                return Err(redefinition(&member.name, prev, &member.span))
+            }
+            members.insert(&member.name, &member.span);
+        }
+        let mut def = self.clone();
+        for member in &mut def.member_list {
+            member.typeref = scope.resolve_type(&member.typeref).map_err(|e| e.with_span(member.span.clone()))?;
+        }
+        scope.global_define_map.insert(self.name.clone(), def);
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DefEnumNode {
+    /*
+        ENUM [ name ] { enum_member ( , enum_member ) * [ , ] } ;
+    */
+    pub name: Option<String>,
+    pub members: Vec<EnumMemberNode>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub struct EnumMemberNode {
+    /*
+        name [ = expr ]
+    */
+    pub name: String,
+    pub value: Option<Rc<Box<dyn ExprNode>>>,
+    pub span: Span,
+}
+
+impl DefNode for DefEnumNode {
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn fill_symbol(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        if let Some(name) = &self.name {
+            if let Some(prev) = scope.enum_map.get(name) {
+                return Err(redefinition(name, &prev.span, &self.span))
+            }
+            scope.enum_map.insert(name.clone(), self.clone());
+        }
+
+        // 没有指定值的成员是前一个成员加1，第一个是0，超出int的范围时为None
+        let mut next = Some(0);
+        for member in &self.members {
+            // 枚举成员和全局变量、typedef在同一个命名空间里
+            if let Some((_, prev)) = scope.enum_constants.get(&member.name) {
+                return Err(redefinition(&member.name, prev, &member.span))
+            }
+            if let Some(prev) = scope.scope_stack[0].borrow().var_map.get(&member.name) {
+                return Err(redefinition(&member.name, &prev.span, &member.span))
+            }
+            if let Some(prev) = scope.typedef_map.get(&member.name) {
+                return Err(redefinition(&member.name, &prev.span, &member.span))
+            }
+            let value = match &member.value {
+                Some(expr) => match expr.const_value(scope) {
+                    Some(value) => value,
+                    None => return Err(SemanticError::at(SemanticErrorType::NotConstant, expr.span())),
+                },
+                None => match next {
+                    Some(value) => value,
+                    None => return Err(SemanticError::at(SemanticErrorType::EnumOverflow(member.name.clone()), member.span.clone())),
+                },
+            };
+            // 枚举成员的类型是int，指定的值也不能超出int的范围
+            let value = match i32::try_from(value) {
+                Ok(value) => value,
+                Err(_) => return Err(SemanticError::at(SemanticErrorType::EnumOverflow(member.name.clone()), member.span.clone())),
+            };
+            scope.enum_constants.insert(member.name.clone(), (value as i64, member.span.clone()));
+            next = value.checked_add(1).map(i64::from);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DefTypedefNode {
+    /*
+        TYPEDEF typeref declarator ;
+    */
+    pub name: String,
+    pub typeref: TypeNode,
+    pub span: Span,
+}
+
+impl DefNode for DefTypedefNode {
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn fill_symbol(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        if let Some(prev) = scope.typedef_map.get(&self.name) {
+            return Err(redefinition(&self.name, &prev.span, &self.span))
+        }
+        if let Some(prev) = scope.scope_stack[0].borrow().var_map.get(&self.name) {
+            return Err(redefinition(&self.name, &prev.span, &self.span))
+        }
+        if let Some((_, prev)) = scope.enum_constants.get(&self.name) {
+            return Err(redefinition(&self.name, prev, &self.span))
+        }
+        // 记录的是展开之后的类型，别名的别名也只需要查一次
+        let typedef = DefTypedefNode {
+            name: self.name.clone(),
+            typeref: scope.resolve_type(&self.typeref).map_err(|e| e.with_span(self.span.clone()))?,
+            span: self.span.clone(),
+        };
+        scope.typedef_map.insert(self.name.clone(), typedef);
+
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -61,29 +221,122 @@ pub struct SlotNode {
     */
     pub typeref: TypeNode,
     pub name: String,
+    pub span: Span,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct TypeNode {
     /*
-        TYPE_BASE ( [] | [ INTEGER ] | * | ( param_typeref ) ) *
+        qualifier TYPE_BASE qualifier ( [] | [ INTEGER ] | * | ( param_typeref ) ) *
     */
     pub type_base: TypeBase,
     pub nested_def: Vec<TypeDef>,
+    pub qualifier: Qualifier,
+}
+
+impl TypeNode {
+    pub fn is_static(&self) -> bool {
+        return self.qualifier.storage == Some(Token::Static)
+    }
+
+    pub fn is_extern(&self) -> bool {
+        return self.qualifier.storage == Some(Token::Extern)
+    }
+
+    /**
+     *  声明和定义的类型要一致，static、extern不算
+     */
+    pub fn same_type(&self, other: &TypeNode) -> bool {
+        let strip = |typeref: &TypeNode| TypeNode {
+            qualifier: Qualifier {
+                storage: None,
+                ..typeref.qualifier.clone()
+            },
+            ..typeref.clone()
+        };
+
+        return strip(self) == strip(other)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Qualifier {
+    /*
+        ( const | volatile | signed | unsigned | static | extern ) *
+        const修饰的是最里层的基础类型，const char *s里s可以改，s[0]不能改
+    */
+    pub is_const: bool,
+    pub is_volatile: bool,
+    pub is_unsigned: bool,
+    // static或者extern
+    pub storage: Option<Token>,
+    // *后面的const修饰这一层指针，记录的是nested_def里的下标，char * const s里s不能改
+    pub const_pointers: Vec<usize>,
+}
+
+impl Qualifier {
+    /**
+     *  typedef展开时把使用处的修饰加到别名原来的类型上
+     *  使用处写的指针接在别名的nested_def后面，下标要加上别名的层数
+     */
+    pub fn merge(&self, other: &Qualifier, depth: usize) -> Qualifier {
+        let mut const_pointers = self.const_pointers.clone();
+        const_pointers.extend(other.const_pointers.iter().map(|level| level + depth));
+        Qualifier {
+            is_const: self.is_const || other.is_const,
+            is_volatile: self.is_volatile || other.is_volatile,
+            is_unsigned: self.is_unsigned || other.is_unsigned,
+            storage: other.storage.clone(),
+            const_pointers,
+        }
+    }
+
+    /**
+     *  nested_def有depth层时，最外面的这一层是不是const
+     */
+    pub fn is_const_at(&self, depth: usize) -> bool {
+        match depth {
+            0 => self.is_const,
+            _ => self.const_pointers.contains(&(depth - 1)),
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum TypeDef {
     Array,
     FixedArray(usize),
+    // 长度是常量表达式的数组，分析阶段算出长度后换成FixedArray
+    ConstArray(ConstExpr),
     Pointer,
-    Func, 
+    Func(Box<FuncType>),
+}
+
+#[derive(Clone, Debug)]
+pub struct ConstExpr(pub Rc<Box<dyn ExprNode>>);
+
+impl PartialEq for ConstExpr {
+    fn eq(&self, other: &Self) -> bool {
+        // 表达式没法直接比较，同一个语法树节点才算相等
+        return Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FuncType {
+    /*
+        return_type ( [ typeref ( , typeref ) * [ , ... ] ] )
+        TypeNode里Func前面的修饰就是返回值类型，这里单独存一份方便检查
+    */
+    pub params: Vec<TypeNode>,
+    pub return_type: TypeNode,
+    pub variadic: bool,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct TypeBase {
     /*
-        int | float | double | struct xxx | char | void 
+        int | float | double | struct xxx | union xxx | enum xxx | char | void | long [ long ]
     */
     pub base: Token,
     pub name: Option<String>,
@@ -92,65 +345,373 @@ pub struct TypeBase {
 #[derive(Clone, Debug)]
 pub struct DefFuncNode {
     /*
-        typeref name ( [ param ] ) block
+        typeref name ( [ param ] ) ( block | ; )
+        没有函数体的是函数声明
     */
     pub typeref: TypeNode,
     pub name: String,
     pub params: ParamsNode,
-    pub block: Rc<Box<dyn StmtNode>>,
+    pub block: Option<Rc<Box<dyn StmtNode>>>,
+    pub span: Span,
+}
+
+impl DefFuncNode {
+    /**
+     *  函数名当成值使用时的类型
+     */
+    pub fn func_type(&self) -> TypeNode {
+        let mut typeref = self.typeref.clone();
+        typeref.nested_def.push(TypeDef::Func(Box::new(self.signature())));
+
+        return typeref
+    }
+
+    pub fn signature(&self) -> FuncType {
+        FuncType {
+            params: self.params.params.iter().map(|param| param.typeref.clone()).collect(),
+            return_type: self.typeref.clone(),
+            variadic: self.params.variadic,
+        }
+    }
+
+    /**
+     *  声明和定义的返回值、参数类型要一致，参数名和static、extern不算
+     */
+    pub fn same_signature(&self, other: &DefFuncNode) -> bool {
+        let params = &self.params.params;
+        let other_params = &other.params.params;
+
+        return self.typeref.same_type(&other.typeref)
+            && params.len() == other_params.len()
+            && params.iter().zip(other_params).all(|(a, b)| a.typeref.same_type(&b.typeref))
+            && self.params.variadic == other.params.variadic
+    }
 }
 
 impl DefNode for DefFuncNode {
-    fn fill_symbol(&self, scope: &mut TopLevelScope) {
-        scope.func_map.insert(self.name.clone(), self.clone());
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn fill_symbol(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        let mut func = self.clone();
+        func.typeref = scope.resolve_type(&self.typeref).map_err(|e| e.with_span(self.span.clone()))?;
+        for param in &mut func.params.params {
+            param.typeref = scope.resolve_type(&param.typeref).map_err(|e| e.with_span(param.span.clone()))?;
+        }
+        if let Some(prev) = scope.func_map.get(&self.name) {
+            if prev.block.is_some() && self.block.is_some() {
+                return Err(redefinition(&self.name, &prev.span, &self.span))
+            }
+            if !prev.same_signature(&func) {
+                let error = SemanticErrorType::ConflictingTypes(self.name.clone(), prev.span.clone());
+                return Err(SemanticError::at(error, self.span.clone()))
+            }
+            // 已经有定义时不再记录声明
+            if prev.block.is_some() {
+                return Ok(())
+            }
+        }
+        scope.func_map.insert(self.name.clone(), func.clone());
+        let block = match &self.block {
+            Some(block) => block,
+            None => return Ok(()),
+        };
         let local = Rc::new(RefCell::new(LocalScope::new()));
         scope.scopes.insert(self.name.clone(), Rc::clone(&local));
-        for param in &self.params.params {
-            let mut name_map: HashMap<String, Option<Rc<Box<dyn ExprNode>>>> = HashMap::new();
-            let typeref = param.typeref.clone();
-            name_map.insert(param.name.clone(), None);
+        for param in &func.params.params {
+            if let Some(prev) = local.borrow().var_map.get(&param.name) {
+                return Err(redefinition(&param.name, &prev.span, &param.span))
+            }
             local.borrow_mut().var_map.insert(param.name.clone(), DefVarNode {
-                typeref,
-                name_map,
+                typeref: param.typeref.clone(),
+                declarators: vec![(param.name.clone(), None)],
+                span: param.span.clone(),
             });
         }
         scope.scope_stack.push(local);
 
-        self.block.fill_symbol(scope);
+        let result = block.fill_symbol(scope);
 
         scope.scope_stack.pop();
-    }   
+        return result
+    }
 
-    fn check_expr_validity(&self, mut scope: &mut TopLevelScope) {
-        scope.scope_stack.clear();
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        match &self.block {
+            Some(block) => {
+                scope.push_func(self.name.clone());
+                block.check_expr_validity(scope)
+            },
+            None => Ok(()),
+        }
+    }
+
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        // 函数声明不生成代码
+        let block = match &self.block {
+            Some(block) => block,
+            None => return Ok(()),
+        };
         scope.push_func(self.name.clone());
-        self.block.check_expr_validity(&mut scope);
+        // func_map里记录的是展开typedef之后的定义
+        let origin = scope.func_map.get(&self.name).cloned().unwrap_or_else(|| self.clone());
+        ir.begin_func(Func {
+            name: self.name.clone(),
+            return_type: origin.typeref.clone(),
+            origin,
+            body: Vec::new(),
+        });
+        block.generate(scope, ir)?;
+        ir.end_func();
+
+        Ok(())
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct ParamsNode {
     /*
-        ( [ slot ( , slot) * ])
+        ( [ slot ( , slot) * [ , ... ] ])
     */
     pub params: Vec<SlotNode>, 
+    pub variadic: bool,
 }
 
+// 变量名和初始化的表达式
+pub type Declarator = (String, Option<Rc<Box<dyn ExprNode>>>);
+
 #[derive(Clone, Debug)]
 pub struct DefVarNode {
     /*
         type name [ = expr] (, name [ = expr]) * ;
      */
     pub typeref: TypeNode,
-    pub name_map: HashMap<String, Option<Rc<Box<dyn ExprNode>>>>,
+    pub declarators: Vec<Declarator>,
+    pub span: Span,
+}
+
+impl DefVarNode {
+    pub fn init_value(&self, name: &str) -> Option<&Rc<Box<dyn ExprNode>>> {
+        for (declared, value) in &self.declarators {
+            if declared == name {
+                return value.as_ref()
+            }
+        }
+
+        return None
+    }
+
+    /**
+     *  解析出每个变量的类型，没写长度的数组按初始值补上长度
+     *  int[] a = {1, 2}, b = {3};里a和b的长度不一样，类型相同的相邻变量放在一起
+     */
+    pub fn completed(&self, scope: &mut TopLevelScope) -> Result<Vec<DefVarNode>, SemanticError> {
+        let typeref = scope.resolve_type(&self.typeref).map_err(|e| e.with_span(self.span.clone()))?;
+        let mut defs: Vec<DefVarNode> = Vec::new();
+        for (name, value) in &self.declarators {
+            let completed = complete_type(&typeref, value, scope)?;
+            let declarator = (name.clone(), value.clone());
+            match defs.last_mut() {
+                Some(def) if def.typeref == completed => def.declarators.push(declarator),
+                _ => defs.push(DefVarNode {
+                    typeref: completed,
+                    declarators: vec![declarator],
+                    span: self.span.clone(),
+                }),
+            }
+        }
+
+        return Ok(defs)
+    }
+}
+
+/**
+ *  int[] a = {1, 2, 3};按初始化列表的长度变成int[3]
+ */
+fn complete_type(typeref: &TypeNode, value: &Option<Rc<Box<dyn ExprNode>>>, scope: &mut TopLevelScope) -> Result<TypeNode, SemanticError> {
+    let mut typeref = typeref.clone();
+    let value = match value {
+        Some(value) if typeref.nested_def.last() == Some(&TypeDef::Array) => value,
+        _ => return Ok(typeref),
+    };
+    if let Some(list) = init_list(value, &typeref) {
+        let len = list.array_len(scope)?;
+        if len == 0 {
+            return Err(SemanticError::at(SemanticErrorType::InvalidArraySize(0), value.span()))
+        }
+        typeref.nested_def.pop();
+        typeref.nested_def.push(TypeDef::FixedArray(len));
+    }
+
+    return Ok(typeref)
+}
+
+/**
+ *  全局变量和static变量的初始值放到ir.data里，初始值必须在编译期就能确定
+ */
+fn static_init(
+    name: String,
+    value: &Option<Rc<Box<dyn ExprNode>>>,
+    typeref: &TypeNode,
+    scope: &mut TopLevelScope,
+    ir: &mut IR,
+) -> Result<(), SemanticError> {
+    let expr = match value {
+        Some(expr) => expr,
+        None => return Ok(()),
+    };
+    let data = match init_list(expr, typeref) {
+        Some(list) => list.static_data(typeref, scope)?,
+        None => match expr.static_value(scope) {
+            Some(value) => vec![Data {
+                offset: 0,
+                size: scope.type_size(typeref)?,
+                value,
+            }],
+            None => return Err(SemanticError::at(SemanticErrorType::NotConstant, expr.span())),
+        },
+    };
+    ir.data.insert(name, data);
+
+    Ok(())
+}
+
+/**
+ *  初始化和赋值一样，值的类型要和声明的类型兼容
+ */
+fn check_init_type(typeref: &TypeNode, value: &Rc<Box<dyn ExprNode>>, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+    let info = expect_type(value.get_type(scope)?, value)?;
+    let left = (typeref.type_base.base.clone(), typeref.nested_def.clone());
+    let right = (info.base_type.clone(), info.nested_def.clone());
+    return check_type_compatible(left, right).map_err(|e| e.with_span(value.span()))
 }
 
 impl DefNode for DefVarNode {
-    fn fill_symbol(&self, scope: &mut TopLevelScope) {
-        for (name, value) in self.name_map.iter() {
-            let last = scope.scope_stack.len() - 1;
-            let local = &scope.scope_stack[last];
-            local.borrow_mut().var_map.insert(name.clone(), self.clone());
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn fill_symbol(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        for var in self.completed(scope)? {
+            for (name, _value) in &var.declarators {
+                // 全局变量和typedef、枚举成员的名字在同一个命名空间里
+                if scope.scope_stack.len() == 1 {
+                    if let Some(prev) = scope.typedef_map.get(name) {
+                        return Err(redefinition(name, &prev.span, &self.span))
+                    }
+                    if let Some((_, prev)) = scope.enum_constants.get(name) {
+                        return Err(redefinition(name, prev, &self.span))
+                    }
+                }
+                let last = scope.scope_stack.len() - 1;
+                let local = &scope.scope_stack[last];
+                let prev = local.borrow().var_map.get(name).cloned();
+                if let Some(prev) = prev {
+                    // 全局的extern声明可以在后面给出定义，类型要一致，定义只能有一个
+                    if last > 0 || !(prev.typeref.is_extern() || var.typeref.is_extern()) {
+                        return Err(redefinition(name, &prev.span, &self.span))
+                    }
+                    if !prev.typeref.same_type(&var.typeref) {
+                        let error = SemanticErrorType::ConflictingTypes(name.clone(), prev.span.clone());
+                        return Err(SemanticError::at(error, self.span.clone()))
+                    }
+                    // 已经有定义时不再记录声明
+                    if var.typeref.is_extern() {
+                        continue
+                    }
+                }
+                local.borrow_mut().var_map.insert(name.clone(), var.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    /**
+     *  普通的初始化在赋值时检查类型，初始化列表要按变量的类型展开之后检查
+     */
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        // 变量从自己的声明符之后就可见，int g = g + 1;里的g是正在声明的变量
+        // 后面的变量还看不到，int b = a, a = 1;里的a还没声明
+        for var in self.completed(scope)? {
+            for (name, value) in &var.declarators {
+                scope.declare(name);
+                if let Some(value) = value {
+                    match init_list(value, &var.typeref) {
+                        Some(list) => list.check_init(&var.typeref, scope)?,
+                        None => {
+                            value.check_expr_validity(scope).map_err(|e| e.with_span(value.span()))?;
+                            check_init_type(&var.typeref, value, scope)?;
+                        },
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        // 全局变量直接记录下来，局部变量的初始化转换成赋值
+        // extern变量在别的地方定义，这里不分配空间
+        if !ir.in_func() {
+            for var in self.completed(scope)? {
+                if var.typeref.is_extern() {
+                    continue
+                }
+                for (name, value) in &var.declarators {
+                    static_init(name.clone(), value, &var.typeref, scope, ir)?;
+                }
+                ir.variables.push(var);
+            }
+            return Ok(())
+        }
+
+        // 函数里的static变量也放到全局变量里，只在程序开始时初始化一次
+        if self.typeref.is_static() {
+            for mut var in self.completed(scope)? {
+                let locals: Vec<String> = var.declarators.iter().map(|(name, _)| name.clone()).collect();
+                for (name, _value) in var.declarators.iter_mut() {
+                    *name = ir.static_name(name);
+                }
+                for ((name, value), local) in var.declarators.iter().zip(&locals) {
+                    scope.bind_static(local, name.clone());
+                    scope.declare(local);
+                    static_init(name.clone(), value, &var.typeref, scope, ir)?;
+                }
+                ir.variables.push(var);
+            }
+            return Ok(())
         }
-    }   
+
+        // 按声明的顺序初始化，初始化的表达式里已经能看到正在声明的变量
+        for (name, value) in &self.declarators {
+            scope.declare(name);
+            let expr = match value {
+                Some(expr) => expr,
+                None => continue,
+            };
+            let var = match scope.local_var(name) {
+                Some(var) => var,
+                None => return Err(SemanticError::new(SemanticErrorType::UndefinedSymbol(name.clone()))),
+            };
+            let typeref = var.typeref.clone();
+            let lhs = ExprStmt::new(Box::new(Var {
+                name: name.clone(),
+                expr: var,
+            }));
+            if let Some(list) = init_list(expr, &typeref) {
+                list.generate_init(lhs, &typeref, scope, ir)?;
+            } else {
+                let rhs = expr.generate(scope, ir)?;
+                ir.emit(Box::new(Assign {
+                    lhs,
+                    rhs,
+                }));
+            }
+        }
+
+        Ok(())
+    }
 }
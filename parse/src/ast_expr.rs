@@ -1,64 +1,466 @@
-use lex::token::Token;
+use lex::token:: {
+    Token,
+    LiteralType,
+    compound_op,
+    is_aggregate,
+    get_keywords,
+};
 use std::rc::Rc;
 use std::fmt;
+use lex::location::Span;
 use crate::ast:: {
     TypeNode,
-    DefStructNode,
     TypeDef,
+    FuncType,
     SlotNode,
+    Qualifier,
 };
 use std::result::Result;
+use crate::error:: {
+    SemanticError,
+    SemanticErrorType,
+};
 use crate::symbol_table:: {
     TopLevelScope,
     TypeInfo,
 };
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use crate::ir:: {
+    IR,
+    Op,
+    ExprStmt,
+    Assign,
+    CJump,
+    Jump,
+    Uni,
+    Bin,
+    Call,
+    Addr,
+    Mem,
+    Var,
+    FuncAddr,
+    Int,
+    Float,
+    Str,
+    Data,
+};
+
+/**
+ *  const的变量和元素不能被赋值，也不能自增自减
+ */
+fn check_assignable(info: &Option<TypeInfo>, span: Span) -> Result<(), SemanticError> {
+    if let Some(info) = info {
+        if info.qualifier.is_const_at(info.nested_def.len()) {
+            return Err(SemanticError::at(SemanticErrorType::AssignToConst(info.name.clone()), span))
+        }
+    }
+
+    Ok(())
+}
+
+/**
+ *  按常用算术转换之后的类型决定是不是无符号运算，类型推导失败的操作数不参与
+ */
+fn is_unsigned(exprs: &[&Rc<Box<dyn ExprNode>>], scope: &mut TopLevelScope) -> bool {
+    let mut result: Option<TypeInfo> = None;
+    for expr in exprs {
+        if let Ok(Some(info)) = expr.get_type(scope) {
+            result = Some(match result {
+                Some(prev) => common_type(prev, info),
+                None => common_type(info.clone(), info),
+            });
+        }
+    }
+
+    return result.is_some_and(|info| info.qualifier.is_unsigned && info.nested_def.is_empty())
+}
+
+/**
+ *  移位的结果只取决于左边，其他运算要先转换成两边共同的类型
+ */
+fn operands<'a>(op: &Token, left: &'a Rc<Box<dyn ExprNode>>, right: &'a Rc<Box<dyn ExprNode>>) -> Vec<&'a Rc<Box<dyn ExprNode>>> {
+    if *op == Token::Leftshift || *op == Token::Rightshift {
+        return vec![left]
+    }
+    return vec![left, right]
+}
+
+/**
+ *  前缀运算会改变表达式的类型，&加一层指针，*去掉一层，!的结果是int
+ */
+fn prefix_type(prefix: &Option<Token>, info: Option<TypeInfo>, span: Span) -> Result<Option<TypeInfo>, SemanticError> {
+    let mut info = match info {
+        Some(info) => info,
+        None => return Ok(None),
+    };
+    match prefix {
+        Some(Token::Bitand) => {
+            info.nested_def.push(TypeDef::Pointer);
+            info.origin_base = None;
+        },
+        Some(Token::Mul) => match info.nested_def.last() {
+            // 对函数解引用还是这个函数
+            Some(TypeDef::Func(_)) => {},
+            Some(_) => {
+                info.nested_def.pop();
+                let depth = info.nested_def.len();
+                info.qualifier.const_pointers.retain(|level| *level < depth);
+                info.origin_base = None;
+            },
+            None => return Err(SemanticError::at(SemanticErrorType::NotArray(info.name), span)),
+        },
+        Some(Token::Not) => return Ok(Some(arithmetic_info(Token::Int, false))),
+        Some(Token::Add | Token::Sub) => return Ok(Some(common_type(info.clone(), info))),
+        _ => {},
+    }
+
+    return Ok(Some(info))
+}
+
+/**
+ *  整数和浮点数都是算术类型，相互之间可以隐式转换
+ */
+fn is_arithmetic(base: &Token, nested: &[TypeDef]) -> bool {
+    return nested.is_empty()
+        && matches!(base, Token::Char | Token::Short | Token::Int | Token::Long | Token::Enum | Token::Float | Token::Double)
+}
+
+fn arithmetic_info(base_type: Token, is_unsigned: bool) -> TypeInfo {
+    TypeInfo {
+        name: String::from("none"),
+        origin_struct: None,
+        origin_base: None,
+        base_type,
+        nested_def: Vec::new(),
+        qualifier: Qualifier {
+            is_unsigned,
+            ..Qualifier::default()
+        },
+    }
+}
+
+/**
+ *  常用算术转换，比int小的整数先提升成int，然后转换成两边里等级高的类型
+ *  等级相同时有一边是无符号数结果就是无符号数，long能放下所有的unsigned int
+ *  有一边不是算术类型时返回左边的类型
+ */
+pub fn common_type(left: TypeInfo, right: TypeInfo) -> TypeInfo {
+    if !is_arithmetic(&left.base_type, &left.nested_def) || !is_arithmetic(&right.base_type, &right.nested_def) {
+        return left
+    }
+    let rank = |info: &TypeInfo| match info.base_type {
+        Token::Int => (1, info.qualifier.is_unsigned),
+        Token::Long => (2, info.qualifier.is_unsigned),
+        Token::Float => (3, false),
+        Token::Double => (4, false),
+        _ => (1, false),
+    };
+    let (l_rank, l_unsigned) = rank(&left);
+    let (r_rank, r_unsigned) = rank(&right);
+    let (rank, is_unsigned) = if l_rank == r_rank {
+        (l_rank, l_unsigned || r_unsigned)
+    } else if l_rank > r_rank {
+        (l_rank, l_unsigned)
+    } else {
+        (r_rank, r_unsigned)
+    };
+    let base_type = match rank {
+        1 => Token::Int,
+        2 => Token::Long,
+        3 => Token::Float,
+        _ => Token::Double,
+    };
+
+    return arithmetic_info(base_type, is_unsigned)
+}
+
+pub fn check_type_compatible(left_type: (Token, Vec<TypeDef>), mut right_type: (Token, Vec<TypeDef>)) -> Result<(), SemanticError> {
+    // 数组当成指向第一个元素的指针，字符串字面量就是char*
+    if let Some(TypeDef::Array | TypeDef::FixedArray(_)) = right_type.1.last() {
+        right_type.1.pop();
+        right_type.1.push(TypeDef::Pointer);
+    }
 
-fn check_type_compatible(left_type: (Token, Vec<TypeDef>), right_type: (Token, Vec<TypeDef>)) {
+    // 算术类型之间按常用算术转换隐式转换，比如int可以赋值给long和double，枚举也当成int
+    if is_arithmetic(&left_type.0, &left_type.1) && is_arithmetic(&right_type.0, &right_type.1) {
+        return Ok(())
+    }
     if left_type.0 != right_type.0 {
-        panic!("{} and {} types are incompatible", left_type.0, right_type.0);
+        return Err(incompatible(&left_type, &right_type))
+    }
+
+    // 函数名当成指向这个函数的指针，函数指针要求签名完全一致
+    if let Some(TypeDef::Func(_)) = right_type.1.last() {
+        right_type.1.push(TypeDef::Pointer);
+    }
+    let is_func = |nested: &Vec<TypeDef>| nested.iter().any(|def| matches!(def, TypeDef::Func(_)));
+    if is_func(&left_type.1) || is_func(&right_type.1) {
+        if left_type.1 != right_type.1 {
+            return Err(incompatible(&left_type, &right_type))
+        }
+        return Ok(())
     }
 
     let l_len = left_type.1.len();
     let r_len = right_type.1.len();
 
     if l_len == 0 && r_len == 0 {
-        return
+        return Ok(())
+    }
+
+    match (left_type.1.last(), right_type.1.last()) {
+        (Some(l_last_type), Some(r_last_type)) => {
+            let is_address = |def: &TypeDef| matches!(def, TypeDef::Array | TypeDef::FixedArray(_) | TypeDef::Pointer);
+            if is_address(l_last_type) && !is_address(r_last_type) {
+                return Err(incompatible(&left_type, &right_type))
+            }
+        },
+        _ => return Err(incompatible(&left_type, &right_type)),
     }
 
+    Ok(())
+}
 
-    if let Some(l_last_type) = &left_type.1.last() {
-        if let Some(r_last_type) = &right_type.1.last() {
-            if **l_last_type == TypeDef::Array || **l_last_type == TypeDef::Pointer {
-                if **r_last_type != TypeDef::Array && **r_last_type != TypeDef::Pointer {
-                    panic!("{} and {} types are incompatible", left_type.0, right_type.0);
+/**
+ *  报错时按C的写法显示类型，比如int (*)(int, char *)
+ */
+fn c_type_name(base: String, nested: &[TypeDef]) -> String {
+    // 从最外层的修饰往里展开，指针在前，数组和函数在后
+    let wrap = |decl: String| if decl.starts_with('*') { format!("({})", decl) } else { decl };
+    let mut decl = String::new();
+    for def in nested.iter().rev() {
+        decl = match def {
+            TypeDef::Pointer => format!("*{}", decl),
+            TypeDef::Array | TypeDef::ConstArray(_) => format!("{}[]", wrap(decl)),
+            TypeDef::FixedArray(size) => format!("{}[{}]", wrap(decl), size),
+            TypeDef::Func(func) => {
+                let mut params = func.params.iter().map(|param| {
+                    let mut base = type_keyword(&param.type_base.base);
+                    if let Some(name) = &param.type_base.name {
+                        base = format!("{} {}", base, name);
+                    }
+                    if param.qualifier.is_unsigned {
+                        base = format!("unsigned {}", base);
+                    }
+                    if param.qualifier.is_const {
+                        base = format!("const {}", base);
+                    }
+                    c_type_name(base, &param.nested_def)
+                }).collect::<Vec<_>>();
+                if func.variadic {
+                    params.push(String::from("..."));
                 }
-            }
-        } else {
-            panic!("{:?} and {:?} types are incompatible", left_type.1, right_type.1);
-        }
-    } else {
-        panic!("{:?} and {:?} types are incompatible", left_type.1, right_type.1);
+                if params.is_empty() {
+                    params.push(String::from("void"));
+                }
+                format!("{}({})", wrap(decl), params.join(", "))
+            },
+        };
+    }
+
+    if decl.is_empty() {
+        return base
+    }
+    return format!("{} {}", base, decl)
+}
+
+pub fn type_name(base: &Token, nested: &[TypeDef]) -> String {
+    return c_type_name(type_keyword(base), nested)
+}
+
+fn type_keyword(base: &Token) -> String {
+    if let Token::TypeName(name) = base {
+        return name.clone()
+    }
+    return get_keywords().into_iter()
+        .find(|(_, token)| token == base)
+        .map(|(keyword, _)| keyword)
+        .unwrap_or_else(|| base.to_string())
+}
+
+/**
+ *  两边的类型都按C的写法显示
+ */
+fn incompatible(left: &(Token, Vec<TypeDef>), right: &(Token, Vec<TypeDef>)) -> SemanticError {
+    SemanticError::new(SemanticErrorType::IncompatibleTypes(type_name(&left.0, &left.1), type_name(&right.0, &right.1)))
+}
+
+/**
+ *  表达式推导不出类型时返回错误，而不是直接unwrap
+ */
+pub fn expect_type(info: Option<TypeInfo>, expr: &dyn fmt::Debug) -> Result<TypeInfo, SemanticError> {
+    match info {
+        Some(info) => Ok(info),
+        None => Err(SemanticError::new(SemanticErrorType::UnknownType(format!("{:?}", expr)))),
+    }
+}
+
+fn undefined_symbol(name: &str) -> SemanticError {
+    SemanticError::new(SemanticErrorType::UndefinedSymbol(name.to_string()))
+}
+
+/**
+ *  常量折叠，溢出或者除以0时不算常量
+ */
+fn eval_binary(op: &Token, left: i64, right: i64) -> Option<i64> {
+    match op {
+        Token::Add => left.checked_add(right),
+        Token::Sub => left.checked_sub(right),
+        Token::Mul => left.checked_mul(right),
+        Token::Div => left.checked_div(right),
+        Token::Mod => left.checked_rem(right),
+        Token::Bitand => Some(left & right),
+        Token::Bitor => Some(left | right),
+        Token::Bitxor => Some(left ^ right),
+        Token::Leftshift => left.checked_shl(u32::try_from(right).ok()?),
+        Token::Rightshift => left.checked_shr(u32::try_from(right).ok()?),
+        Token::Equal => Some((left == right) as i64),
+        Token::Notequal => Some((left != right) as i64),
+        Token::Greater => Some((left > right) as i64),
+        Token::Greaterequal => Some((left >= right) as i64),
+        Token::Less => Some((left < right) as i64),
+        Token::Lessequal => Some((left <= right) as i64),
+        Token::And => Some((left != 0 && right != 0) as i64),
+        Token::Or => Some((left != 0 || right != 0) as i64),
+        _ => None,
+    }
+}
+
+/**
+ *  根据前缀运算符生成对应的IR
+ */
+fn generate_prefix(prefix: &Option<Token>, value: ExprStmt, ir: &mut IR) -> ExprStmt {
+    match prefix {
+        Some(Token::Sub) => ExprStmt::new(Box::new(Uni {
+            op: Op::SUB,
+            expr: value,
+        })),
+        Some(Token::Not) => ExprStmt::new(Box::new(Uni {
+            op: Op::NOT,
+            expr: value,
+        })),
+        Some(Token::Mul) | Some(Token::Pointer) => ExprStmt::new(Box::new(Mem {
+            expr: value,
+        })),
+        Some(Token::Bitand) => ExprStmt::new(Box::new(Addr {
+            expr: value,
+        })),
+        Some(Token::Inc) | Some(Token::Dec) => {
+            generate_self_op(prefix.as_ref().unwrap(), value.clone(), ir);
+            value
+        },
+        _ => value,
     }
 }
 
+/**
+ *  ++ 和 -- 转换成 a = a + 1，返回的是运算之后的值
+ */
+fn generate_self_op(op: &Token, value: ExprStmt, ir: &mut IR) -> ExprStmt {
+    let op = if *op == Token::Inc { Op::ADD } else { Op::SUB };
+    let rhs = ExprStmt::new(Box::new(Bin {
+        op,
+        left: value.clone(),
+        right: ExprStmt::new(Box::new(Int { value: 1 })),
+    }));
+    ir.emit(Box::new(Assign {
+        lhs: value.clone(),
+        rhs,
+    }));
+
+    return value
+}
+
 pub trait ExprNode:fmt::Debug {
-    fn is_leftvalue(&self) -> Result<(), String> {
+    fn span(&self) -> Span;
+    fn is_leftvalue(&self) -> Result<(), SemanticError> {
         return Ok(())
     }
-    fn check_expr_validity(&self, mut scope: &mut TopLevelScope) {}
-    fn get_type(&self, mut scope: &mut TopLevelScope) -> Option<TypeInfo> {
+    fn check_expr_validity(&self, _scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        return Ok(())
+    }
+    fn get_type(&self, _scope: &mut TopLevelScope) -> Result<Option<TypeInfo>, SemanticError> {
+        return Ok(None)
+    }
+    // 编译期能算出来的整数常量，case标签之类的地方要用
+    fn const_value(&self, _scope: &mut TopLevelScope) -> Option<i64> {
         return None
     }
+    // 全局变量的初始值，除了整数常量还可以是浮点数和字符串字面量
+    fn static_value(&self, scope: &mut TopLevelScope) -> Option<ExprStmt> {
+        return self.const_value(scope).map(|value| ExprStmt::new(Box::new(Int { value })))
+    }
+    // 只有初始化列表返回Some
+    fn as_init_list(&self) -> Option<&InitListNode> {
+        return None
+    }
+    // 只有字符串字面量返回Some，char数组可以用它初始化
+    fn string_literal(&self) -> Option<String> {
+        return None
+    }
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<ExprStmt, SemanticError>;
+    // 作为单独的语句时只需要副作用
+    fn generate_stmt(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        let value = self.generate(scope, ir)?;
+        ir.emit(Box::new(value));
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CommaNode {
+    /*
+        expr0 , expr0
+    */
+    pub left_value: Rc<Box<dyn ExprNode>>,
+    pub right_value: Rc<Box<dyn ExprNode>>,
+    pub span: Span,
+}
+
+impl ExprNode for CommaNode {
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn is_leftvalue(&self) -> Result<(), SemanticError> {
+        return Err(SemanticError::at(SemanticErrorType::NotLeftValue(String::from("comma expression")), self.span()))
+    }
+
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        self.left_value.check_expr_validity(scope)?;
+        self.right_value.check_expr_validity(scope)
+    }
+
+    // 整个表达式的类型和值都是右边的
+    fn get_type(&self, scope: &mut TopLevelScope) -> Result<Option<TypeInfo>, SemanticError> {
+        return self.right_value.get_type(scope)
+    }
+
+    /**
+     *  左边只需要副作用
+     *  a , b => a ; b
+     */
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<ExprStmt, SemanticError> {
+        self.left_value.generate_stmt(scope, ir)?;
+        return self.right_value.generate(scope, ir)
+    }
+
+    fn generate_stmt(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        self.left_value.generate_stmt(scope, ir)?;
+        return self.right_value.generate_stmt(scope, ir)
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct AssginmentNode {
     /*
-        expr = expr
+        expr (= | += | -= | *= | /= | %= | <<= | >>= | &= | |= | ^=) expr
     */
+    pub operator: Token,
     pub left_value: Rc<Box<dyn ExprNode>>,
     pub right_value: Rc<Box<dyn ExprNode>>,
+    pub span: Span,
 }
 
 impl AssginmentNode {
@@ -67,46 +469,91 @@ impl AssginmentNode {
      *  首先左值的有效性之前就检查过了，所以这里可以确保左值有效
      *  把初始化的类型检查放到这来，也就是先检查Defvarnode里的
      */
-    fn check_type(&self, left_type: Option<TypeInfo>, right_type: Option<TypeInfo>, mut scope: &mut TopLevelScope) {
+    fn check_type(&self, left_type: Option<TypeInfo>, right_type: Option<TypeInfo>, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
         // 初始化的类型检查
-        self.check_init_type(&left_type, &mut scope);
-
-        println!("==========={:?}", left_type);
-        println!("==========={:?}", right_type);
+        self.check_init_type(&left_type, scope)?;
 
-        let left = (left_type.as_ref().unwrap().base_type.clone(), left_type.as_ref().unwrap().nested_def.clone());
-        let right = (right_type.as_ref().unwrap().base_type.clone(), right_type.as_ref().unwrap().nested_def.clone());
-        check_type_compatible(left, right);
+        let left_type = expect_type(left_type, &self.left_value)?;
+        let right_type = expect_type(right_type, &self.right_value)?;
+        let left = (left_type.base_type.clone(), left_type.nested_def.clone());
+        let right = (right_type.base_type.clone(), right_type.nested_def.clone());
+        return check_type_compatible(left, right)
     }
 
-    fn check_init_type(&self, value: &Option<TypeInfo>, mut scope: &mut TopLevelScope) {
+    fn check_init_type(&self, value: &Option<TypeInfo>, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
         if let Some(info) = value {
             if let Some(var) = &info.origin_base {
                 let left = (var.typeref.type_base.base.clone(), var.typeref.nested_def.clone());
-                let value = var.name_map.get(&info.name);
-                if let Some(expr) = value.unwrap() {
-                    let type_info = expr.get_type(&mut scope).unwrap();
+                if let Some(expr) = var.init_value(&info.name) {
+                    // 初始化列表在DefVarNode里按类型检查过了
+                    if init_list(expr, &var.typeref).is_some() {
+                        return Ok(())
+                    }
+                    let type_info = expect_type(expr.get_type(scope)?, expr)?;
                     let right = (type_info.base_type.clone(), type_info.nested_def.clone());
-                    check_type_compatible(left, right);
+                    check_type_compatible(left, right)?;
                 }
             }
         }
+
+        Ok(())
     }
 }
 
 impl ExprNode for AssginmentNode {
-    fn check_expr_validity(&self, mut scope: &mut TopLevelScope) {
-        let result = self.left_value.is_leftvalue();
-        if result.is_err() {
-            panic!(result.err().unwrap())
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        self.left_value.is_leftvalue()?;
+        // 枚举成员也是标识符，但它是常量
+        if self.left_value.const_value(scope).is_some() {
+            let name = self.left_value.get_type(scope)?.map(|info| info.name).unwrap_or_default();
+            return Err(SemanticError::at(SemanticErrorType::NotLeftValue(name), self.left_value.span()))
         }
 
-        self.left_value.check_expr_validity(scope);
-        self.right_value.check_expr_validity(scope);
+        self.left_value.check_expr_validity(scope)?;
+        self.right_value.check_expr_validity(scope)?;
+
+        let left = self.left_value.get_type(scope)?;
+        check_assignable(&left, self.left_value.span())?;
+        let right = self.right_value.get_type(scope)?;
+        return self.check_type(left, right, scope).map_err(|e| e.with_span(self.span()))
+    }
 
-        let left = self.left_value.get_type(&mut scope);
-        let right = self.right_value.get_type(&mut scope);
-        self.check_type(left, right, &mut scope);
+    fn get_type(&self, scope: &mut TopLevelScope) -> Result<Option<TypeInfo>, SemanticError> {
+        // 赋值表达式的值就是赋值之后的左值，a = b = c要用到
+        return self.left_value.get_type(scope)
+    }
+
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<ExprStmt, SemanticError> {
+        let mut rhs = self.right_value.generate(scope, ir)?;
+        let lhs = self.left_value.generate(scope, ir)?;
+        // a += b 转换成 a = a + b
+        if let Some(op) = compound_op(&self.operator) {
+            let unsigned = is_unsigned(&operands(&op, &self.left_value, &self.right_value), scope);
+            let mut op = Op::from_token(&op);
+            if unsigned {
+                op = op.to_unsigned();
+            }
+            rhs = ExprStmt::new(Box::new(Bin {
+                op,
+                left: lhs.clone(),
+                right: rhs,
+            }));
+        }
+        ir.emit(Box::new(Assign {
+            lhs: lhs.clone(),
+            rhs,
+        }));
+
+        return Ok(lhs)
+    }
+
+    fn generate_stmt(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        self.generate(scope, ir)?;
+        Ok(())
     }
 }
 
@@ -118,30 +565,488 @@ pub struct ArithmeticOpNode {
     pub operator: Token,
     pub left_value: Rc<Box<dyn ExprNode>>,
     pub right_value: Rc<Box<dyn ExprNode>>,
+    pub span: Span,
 }
 
 impl ArithmeticOpNode {
-    fn check_type(&self, mut scope: &mut TopLevelScope) -> Option<TypeInfo> {
-        let right = self.right_value.get_type(&mut scope);
-        let left = self.left_value.get_type(&mut scope);
+    fn check_type(&self, scope: &mut TopLevelScope) -> Result<Option<TypeInfo>, SemanticError> {
+        let right = expect_type(self.right_value.get_type(scope)?, &self.right_value)?;
+        let left = expect_type(self.left_value.get_type(scope)?, &self.left_value)?;
 
-        let left_type = (left.as_ref().unwrap().base_type.clone(), left.as_ref().unwrap().nested_def.clone());
-        let right_type = (right.as_ref().unwrap().base_type.clone(), right.as_ref().unwrap().nested_def.clone());
+        let left_type = (left.base_type.clone(), left.nested_def.clone());
+        let right_type = (right.base_type.clone(), right.nested_def.clone());
 
-        check_type_compatible(left_type, right_type);
+        check_type_compatible(left_type, right_type)?;
 
-        return left
+        // 比较和逻辑运算的结果是int，移位的结果是提升之后的左边
+        match self.operator {
+            Token::Equal | Token::Notequal | Token::Greater | Token::Less | Token::Greaterequal | Token::Lessequal
+                | Token::And | Token::Or => return Ok(Some(arithmetic_info(Token::Int, false))),
+            Token::Leftshift | Token::Rightshift => return Ok(Some(common_type(left.clone(), left))),
+            _ => return Ok(Some(common_type(left, right))),
+        }
     }
 }
 
 impl ExprNode for ArithmeticOpNode {
-    fn check_expr_validity(&self, mut scope: &mut TopLevelScope) {
-        self.left_value.check_expr_validity(&mut scope);
-        self.right_value.check_expr_validity(&mut scope);
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        self.left_value.check_expr_validity(scope)?;
+        self.right_value.check_expr_validity(scope)
     }
 
-    fn get_type(&self, mut scope: &mut TopLevelScope) -> Option<TypeInfo> {
-        return self.check_type(&mut scope)
+    fn get_type(&self, scope: &mut TopLevelScope) -> Result<Option<TypeInfo>, SemanticError> {
+        return self.check_type(scope).map_err(|e| e.with_span(self.span()))
+    }
+
+    fn const_value(&self, scope: &mut TopLevelScope) -> Option<i64> {
+        let left = self.left_value.const_value(scope)?;
+        let right = self.right_value.const_value(scope)?;
+        return eval_binary(&self.operator, left, right)
+    }
+
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<ExprStmt, SemanticError> {
+        let left = self.left_value.generate(scope, ir)?;
+        let right = self.right_value.generate(scope, ir)?;
+        let mut op = Op::from_token(&self.operator);
+        if is_unsigned(&operands(&self.operator, &self.left_value, &self.right_value), scope) {
+            op = op.to_unsigned();
+        }
+        Ok(ExprStmt::new(Box::new(Bin {
+            op,
+            left,
+            right,
+        })))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct InitListNode {
+    /*
+        { [ init_item ( , init_item ) * [ , ] ] }
+    */
+    pub items: Vec<InitItemNode>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub struct InitItemNode {
+    /*
+        [ . name = | [ expr ] = ] ( expr | init_list )
+    */
+    pub designator: Option<Designator>,
+    pub value: Rc<Box<dyn ExprNode>>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub enum Designator {
+    Member(String),
+    Index(Rc<Box<dyn ExprNode>>),
+}
+
+/**
+ *  初始化列表展开之后的一个标量，value是None的地方要补0
+ */
+#[derive(Clone, Debug)]
+pub struct InitLeaf {
+    pub offset: usize,
+    pub typeref: TypeNode,
+    pub value: Option<Rc<Box<dyn ExprNode>>>,
+}
+
+impl InitListNode {
+    /**
+     *  按变量的类型把初始化列表展开成每个标量的偏移和值
+     *  没有写到的元素也会展开，局部变量要把它们清0
+     */
+    pub fn flatten(&self, typeref: &TypeNode, offset: usize, scope: &mut TopLevelScope, leaves: &mut Vec<InitLeaf>) -> Result<(), SemanticError> {
+        if let Some(TypeDef::FixedArray(len)) = typeref.nested_def.last() {
+            let mut elem = typeref.clone();
+            elem.nested_def.pop();
+            let size = scope.type_size(&elem)?;
+            let slots = self.place(*len, scope, &|designator, scope| match designator {
+                Designator::Index(index) => match index.const_value(scope) {
+                    Some(i) if i >= 0 && (i as usize) < *len => Ok(i as usize),
+                    Some(i) => Err(SemanticError::at(SemanticErrorType::InvalidDesignator(format!("[{}]", i)), index.span())),
+                    None => Err(SemanticError::at(SemanticErrorType::NotConstant, index.span())),
+                },
+                Designator::Member(name) => Err(SemanticError::new(SemanticErrorType::InvalidDesignator(format!(".{}", name)))),
+            })?;
+            for (i, item) in slots.into_iter().enumerate() {
+                init_value(item, &elem, offset + i * size, &self.span, scope, leaves)?;
+            }
+            return Ok(())
+        }
+
+        if typeref.nested_def.is_empty() && is_aggregate(&typeref.type_base.base) {
+            let def = scope.get_struct(&typeref.type_base.name.clone().unwrap_or_default())?.clone();
+            let (offsets, _) = scope.struct_layout(&def.name)?;
+            let mut slots = self.place(def.member_list.len(), scope, &|designator, _scope| match designator {
+                Designator::Member(name) => match def.member_list.iter().position(|member| member.name == *name) {
+                    Some(i) => Ok(i),
+                    None => Err(SemanticError::new(SemanticErrorType::NoMember(def.name.clone(), name.clone()))),
+                },
+                Designator::Index(index) => Err(SemanticError::at(SemanticErrorType::InvalidDesignator(String::from("[]")), index.span())),
+            })?;
+            // 联合体只初始化最后指定的那个成员，都没写时初始化第一个
+            if def.kind == Token::Union {
+                let chosen = slots.iter().rposition(|slot| slot.is_some()).unwrap_or(0);
+                let member = &def.member_list[chosen];
+                return init_value(slots[chosen].take(), &member.typeref, offset, &self.span, scope, leaves)
+            }
+            for (member, item) in def.member_list.iter().zip(slots) {
+                init_value(item, &member.typeref, offset + offsets[&member.name], &self.span, scope, leaves)?;
+            }
+            return Ok(())
+        }
+
+        // 标量也可以用大括号括起来，比如int a = {1};
+        if let Some(item) = self.items.get(1) {
+            return Err(SemanticError::at(SemanticErrorType::ExcessElements, item.span.clone()))
+        }
+        match self.items.first() {
+            Some(item) if item.designator.is_some() => {
+                Err(SemanticError::at(SemanticErrorType::InvalidDesignator(String::from("scalar")), item.span.clone()))
+            },
+            Some(item) => init_value(Some(item), typeref, offset, &self.span, scope, leaves),
+            None => init_value(None, typeref, offset, &self.span, scope, leaves),
+        }
+    }
+
+    /**
+     *  没写长度的数组按初始化列表确定长度，就是写到的最大下标加1
+     *  int[] a = {1, [4] = 5, 6};的长度是6
+     */
+    pub fn array_len(&self, scope: &mut TopLevelScope) -> Result<usize, SemanticError> {
+        let mut len = 0;
+        let mut cursor = 0;
+        for item in &self.items {
+            cursor = match &item.designator {
+                Some(Designator::Index(index)) => match index.const_value(scope) {
+                    Some(i) if i >= 0 => i as usize,
+                    Some(i) => return Err(SemanticError::at(SemanticErrorType::InvalidDesignator(format!("[{}]", i)), index.span())),
+                    None => return Err(SemanticError::at(SemanticErrorType::NotConstant, index.span())),
+                },
+                Some(Designator::Member(name)) => {
+                    return Err(SemanticError::at(SemanticErrorType::InvalidDesignator(format!(".{}", name)), item.span.clone()))
+                },
+                None => cursor,
+            };
+            cursor += 1;
+            len = len.max(cursor);
+        }
+
+        return Ok(len)
+    }
+
+    /**
+     *  按位置和指示符把每一项对应到元素上，同一个元素写了多次时后面的覆盖前面的
+     */
+    fn place(
+        &self,
+        len: usize,
+        scope: &mut TopLevelScope,
+        position: &dyn Fn(&Designator, &mut TopLevelScope) -> Result<usize, SemanticError>,
+    ) -> Result<Vec<Option<&InitItemNode>>, SemanticError> {
+        let mut slots = vec![None; len];
+        let mut cursor = 0;
+        for item in &self.items {
+            if let Some(designator) = &item.designator {
+                cursor = position(designator, scope).map_err(|e| e.with_span(item.span.clone()))?;
+            }
+            if cursor >= len {
+                return Err(SemanticError::at(SemanticErrorType::ExcessElements, item.span.clone()))
+            }
+            slots[cursor] = Some(item);
+            cursor += 1;
+        }
+
+        return Ok(slots)
+    }
+
+    /**
+     *  检查元素个数、指示符和每个元素的类型
+     */
+    pub fn check_init(&self, typeref: &TypeNode, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        let mut leaves = Vec::new();
+        self.flatten(typeref, 0, scope, &mut leaves)?;
+        for leaf in leaves {
+            let value = match leaf.value {
+                Some(value) => value,
+                None => continue,
+            };
+            value.check_expr_validity(scope)?;
+            let info = expect_type(value.get_type(scope)?, &value)?;
+            let left = (leaf.typeref.type_base.base.clone(), leaf.typeref.nested_def.clone());
+            let right = (info.base_type.clone(), info.nested_def.clone());
+            check_type_compatible(left, right).map_err(|e| e.with_span(value.span()))?;
+        }
+
+        Ok(())
+    }
+
+    /**
+     *  局部变量逐个元素赋值，没写到的元素赋0
+     *  a = {...} => mem(addr(a) + offset) = value
+     */
+    pub fn generate_init(&self, var: ExprStmt, typeref: &TypeNode, scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        let mut leaves = Vec::new();
+        self.flatten(typeref, 0, scope, &mut leaves)?;
+        for leaf in leaves {
+            let rhs = match leaf.value {
+                Some(value) => value.generate(scope, ir)?,
+                None => zero_value(&leaf.typeref),
+            };
+            let lhs = ExprStmt::new(Box::new(Mem {
+                expr: ExprStmt::new(Box::new(Bin {
+                    op: Op::ADD,
+                    left: ExprStmt::new(Box::new(Addr { expr: var.clone() })),
+                    right: ExprStmt::new(Box::new(Int { value: leaf.offset as i64 })),
+                })),
+            }));
+            ir.emit(Box::new(Assign {
+                lhs,
+                rhs,
+            }));
+        }
+
+        Ok(())
+    }
+
+    /**
+     *  全局变量的初始值，每个元素都要是编译期能确定的值，没写到的地方默认就是0
+     */
+    pub fn static_data(&self, typeref: &TypeNode, scope: &mut TopLevelScope) -> Result<Vec<Data>, SemanticError> {
+        let mut leaves = Vec::new();
+        self.flatten(typeref, 0, scope, &mut leaves)?;
+        let mut data: Vec<Data> = Vec::new();
+        for leaf in leaves {
+            let value = match leaf.value {
+                Some(value) => value,
+                None => continue,
+            };
+            let size = scope.type_size(&leaf.typeref)?;
+            match value.static_value(scope) {
+                Some(stmt) => data.push(Data {
+                    offset: leaf.offset,
+                    size,
+                    value: stmt,
+                }),
+                None => return Err(SemanticError::at(SemanticErrorType::NotConstant, value.span())),
+            }
+        }
+
+        return Ok(data)
+    }
+}
+
+/**
+ *  变量的初始值要不要按初始化列表展开
+ *  char数组用字符串字面量初始化时拆成每个字符，char[4] s = "abc";和{'a', 'b', 'c', 0}一样
+ *  数组的长度正好放下所有字符时不要结尾的0
+ */
+pub fn init_list(value: &Rc<Box<dyn ExprNode>>, typeref: &TypeNode) -> Option<InitListNode> {
+    if let Some(list) = value.as_init_list() {
+        return Some(list.clone())
+    }
+    if typeref.type_base.base != Token::Char || typeref.nested_def.len() != 1 {
+        return None
+    }
+    let mut bytes = value.string_literal()?.into_bytes();
+    match typeref.nested_def[0] {
+        TypeDef::FixedArray(len) if len == bytes.len() => {},
+        TypeDef::FixedArray(_) | TypeDef::Array => bytes.push(0),
+        _ => return None,
+    }
+    let span = value.span();
+    let items = bytes.into_iter().map(|byte| {
+        let unary = SingeUnaryNode {
+            prefix: None,
+            primary: PrimaryNode {
+                name: None,
+                value: Const::Char(byte as char),
+                span: span.clone(),
+            },
+            span: span.clone(),
+        };
+        let term: Box<dyn ExprNode> = Box::new(TermNode {
+            case_type: None,
+            unary: Rc::new(Box::new(unary)),
+            span: span.clone(),
+        });
+        InitItemNode {
+            designator: None,
+            value: Rc::new(term),
+            span: span.clone(),
+        }
+    }).collect();
+
+    return Some(InitListNode {
+        items,
+        span,
+    })
+}
+
+fn init_value(
+    item: Option<&InitItemNode>,
+    typeref: &TypeNode,
+    offset: usize,
+    span: &Span,
+    scope: &mut TopLevelScope,
+    leaves: &mut Vec<InitLeaf>,
+) -> Result<(), SemanticError> {
+    let is_scalar = match typeref.nested_def.last() {
+        Some(def) => !matches!(def, TypeDef::FixedArray(_)),
+        None => !is_aggregate(&typeref.type_base.base),
+    };
+    match item {
+        Some(item) => match init_list(&item.value, typeref) {
+            Some(list) => list.flatten(typeref, offset, scope, leaves),
+            None => {
+                leaves.push(InitLeaf {
+                    offset,
+                    typeref: typeref.clone(),
+                    value: Some(Rc::clone(&item.value)),
+                });
+                Ok(())
+            },
+        },
+        None if is_scalar => {
+            leaves.push(InitLeaf {
+                offset,
+                typeref: typeref.clone(),
+                value: None,
+            });
+            Ok(())
+        },
+        // 没写到的数组和结构体按空的初始化列表展开
+        None => InitListNode {
+            items: Vec::new(),
+            span: span.clone(),
+        }.flatten(typeref, offset, scope, leaves),
+    }
+}
+
+fn zero_value(typeref: &TypeNode) -> ExprStmt {
+    let base = &typeref.type_base.base;
+    if typeref.nested_def.is_empty() && (*base == Token::Float || *base == Token::Double) {
+        return ExprStmt::new(Box::new(Float { value: 0.0 }))
+    }
+
+    return ExprStmt::new(Box::new(Int { value: 0 }))
+}
+
+impl ExprNode for InitListNode {
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn as_init_list(&self) -> Option<&InitListNode> {
+        return Some(self)
+    }
+
+    fn generate(&self, _scope: &mut TopLevelScope, _ir: &mut IR) -> Result<ExprStmt, SemanticError> {
+        // 语法上初始化列表只能出现在变量定义里，由DefVarNode按类型展开
+        return Err(SemanticError::at(SemanticErrorType::UnexpectedInitList, self.span()))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ConditionalNode {
+    /*
+        expr ? expr : expr
+    */
+    pub condition: Rc<Box<dyn ExprNode>>,
+    pub then_value: Rc<Box<dyn ExprNode>>,
+    pub else_value: Rc<Box<dyn ExprNode>>,
+    pub span: Span,
+}
+
+impl ConditionalNode {
+    /**
+     *  两个分支的类型要兼容，算术类型按常用算术转换，其他的就是第一个分支的类型
+     */
+    fn check_type(&self, scope: &mut TopLevelScope) -> Result<Option<TypeInfo>, SemanticError> {
+        expect_type(self.condition.get_type(scope)?, &self.condition)?;
+        let then_type = expect_type(self.then_value.get_type(scope)?, &self.then_value)?;
+        let else_type = expect_type(self.else_value.get_type(scope)?, &self.else_value)?;
+
+        let left = (then_type.base_type.clone(), then_type.nested_def.clone());
+        let right = (else_type.base_type.clone(), else_type.nested_def.clone());
+        check_type_compatible(left, right)?;
+
+        return Ok(Some(common_type(then_type, else_type)))
+    }
+}
+
+impl ExprNode for ConditionalNode {
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn is_leftvalue(&self) -> Result<(), SemanticError> {
+        return Err(SemanticError::at(SemanticErrorType::NotLeftValue(String::from("conditional expression")), self.span()))
+    }
+
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        self.condition.check_expr_validity(scope)?;
+        self.then_value.check_expr_validity(scope)?;
+        self.else_value.check_expr_validity(scope)
+    }
+
+    fn get_type(&self, scope: &mut TopLevelScope) -> Result<Option<TypeInfo>, SemanticError> {
+        return self.check_type(scope).map_err(|e| e.with_span(self.span()))
+    }
+
+    fn const_value(&self, scope: &mut TopLevelScope) -> Option<i64> {
+        if self.condition.const_value(scope)? != 0 {
+            return self.then_value.const_value(scope)
+        } else {
+            return self.else_value.const_value(scope)
+        }
+    }
+
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<ExprStmt, SemanticError> {
+        /*
+            cjump cond then else
+            then: tmp = expr jump end
+            else: tmp = expr
+            end:
+        */
+        let info = expect_type(self.get_type(scope)?, &self.then_value)?;
+        let tmp = ExprStmt::new(Box::new(ir.new_temp(info.typeref(), self.span())));
+
+        let cond = self.condition.generate(scope, ir)?;
+        let then_label = ir.new_label();
+        let else_label = ir.new_label();
+        let end_label = ir.new_label();
+        ir.emit(Box::new(CJump {
+            cond,
+            then_label: then_label.clone(),
+            else_label: else_label.clone(),
+        }));
+
+        ir.emit(Box::new(then_label));
+        let value = self.then_value.generate(scope, ir)?;
+        ir.emit(Box::new(Assign {
+            lhs: tmp.clone(),
+            rhs: value,
+        }));
+        ir.emit(Box::new(Jump { label: end_label.clone() }));
+
+        ir.emit(Box::new(else_label));
+        let value = self.else_value.generate(scope, ir)?;
+        ir.emit(Box::new(Assign {
+            lhs: tmp.clone(),
+            rhs: value,
+        }));
+        ir.emit(Box::new(end_label));
+
+        return Ok(tmp)
     }
 }
 
@@ -152,40 +1057,82 @@ pub struct TermNode {
     */
     pub case_type: Option<TypeNode>,
     pub unary: Rc<Box<dyn UnaryNode>>,
+    pub span: Span,
 }
 
 impl ExprNode for TermNode {
-    fn is_leftvalue(&self) -> Result<(), String> {
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn is_leftvalue(&self) -> Result<(), SemanticError> {
         return self.unary.is_leftvalue()
     }
 
-    fn check_expr_validity(&self, scope: &mut TopLevelScope) {
-        self.unary.check_expr_validity(scope);
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        return self.unary.check_expr_validity(scope)
     }
 
-    fn get_type(&self, mut scope: &mut TopLevelScope) -> Option<TypeInfo> {
-        return self.unary.get_type(&mut scope);
+    fn get_type(&self, scope: &mut TopLevelScope) -> Result<Option<TypeInfo>, SemanticError> {
+        return self.unary.get_type(scope);
+    }
+
+    fn const_value(&self, scope: &mut TopLevelScope) -> Option<i64> {
+        return self.unary.const_value(scope)
+    }
+
+    fn string_literal(&self) -> Option<String> {
+        if self.case_type.is_some() {
+            return None
+        }
+        return self.unary.string_literal()
+    }
+
+    fn static_value(&self, scope: &mut TopLevelScope) -> Option<ExprStmt> {
+        if self.case_type.is_some() {
+            return self.const_value(scope).map(|value| ExprStmt::new(Box::new(Int { value })))
+        }
+        return self.unary.static_value(scope)
+    }
+
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<ExprStmt, SemanticError> {
+        let value = self.unary.generate(scope, ir)?;
+        if self.case_type.is_some() {
+            return Ok(ExprStmt::new(Box::new(Uni {
+                op: Op::CAST,
+                expr: value,
+            })))
+        }
+
+        return Ok(value)
+    }
+
+    fn generate_stmt(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        if self.case_type.is_some() {
+            let value = self.generate(scope, ir)?;
+            ir.emit(Box::new(value));
+            Ok(())
+        } else {
+            self.unary.generate_stmt(scope, ir)
+        }
     }
 }
 
 pub trait UnaryNode:fmt::Debug {
-    fn is_leftvalue(&self) -> Result<(), String> {
+    fn span(&self) -> Span;
+    fn is_leftvalue(&self) -> Result<(), SemanticError> {
         return Ok(())
     }
 
-    fn check_expr_validity(&self, mut scope: &mut TopLevelScope) {
-        if let Some(prefix) = self.get_prefix() {
-            match prefix {
-                Token::Inc |
-                Token::Dec |
-                Token::Mul |
-                Token::Bitand |
-                Token::Not => {
-                    self.get_name();
-                },
-                _ => {}
-            }
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        if let Some(Token::Inc | Token::Dec | Token::Mul | Token::Bitand | Token::Not) = self.get_prefix() {
+            self.is_leftvalue()?;
         }
+        if let Some(Token::Inc | Token::Dec) = self.get_prefix() {
+            check_assignable(&self.get_type(scope)?, self.span())?;
+        }
+
+        Ok(())
     }
 
     fn get_postfix(&self) -> &Option<Rc<Box<dyn UnaryNode>>> {
@@ -196,12 +1143,31 @@ pub trait UnaryNode:fmt::Debug {
         return None
     }
 
-    fn get_type(&self, mut scope: &mut TopLevelScope) -> Option<TypeInfo> {
+    fn get_type(&self, _scope: &mut TopLevelScope) -> Result<Option<TypeInfo>, SemanticError> {
+        return Ok(None)
+    }
+
+    fn const_value(&self, _scope: &mut TopLevelScope) -> Option<i64> {
+        return None
+    }
+
+    fn static_value(&self, scope: &mut TopLevelScope) -> Option<ExprStmt> {
+        return self.const_value(scope).map(|value| ExprStmt::new(Box::new(Int { value })))
+    }
+
+    fn string_literal(&self) -> Option<String> {
         return None
     }
 
     fn get_name(&self) -> String;
     fn get_prefix(&self) -> Option<Token>;
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<ExprStmt, SemanticError>;
+
+    fn generate_stmt(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        let value = self.generate(scope, ir)?;
+        ir.emit(Box::new(value));
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -211,10 +1177,15 @@ pub struct SingeUnaryNode {
     */
     pub prefix: Option<Token>,
     pub primary: PrimaryNode,
+    pub span: Span,
 }
 
 impl UnaryNode for SingeUnaryNode {
-    fn is_leftvalue(&self) -> Result<(), String> {
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn is_leftvalue(&self) -> Result<(), SemanticError> {
         return self.primary.is_leftvalue()
     }
 
@@ -230,23 +1201,164 @@ impl UnaryNode for SingeUnaryNode {
         return self.prefix.clone()
     }
 
-    fn get_type(&self, mut scope: &mut TopLevelScope) -> Option<TypeInfo> {
-        let token = self.primary.get_primary_type();
-        if let Token::Name(n) = token {
-            let t = scope.get_type(&n);
-            return Some(t)
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        match self.prefix {
+            Some(Token::Inc | Token::Dec) => {
+                self.primary.check_modifiable(scope)?;
+                check_assignable(&self.get_type(scope)?, self.span())
+            },
+            Some(Token::Mul | Token::Bitand | Token::Not) => self.is_leftvalue(),
+            _ => Ok(()),
+        }
+    }
+
+    fn get_type(&self, scope: &mut TopLevelScope) -> Result<Option<TypeInfo>, SemanticError> {
+        let info = self.primary.get_primary_info(scope)?;
+        return prefix_type(&self.prefix, info, self.span())
+    }
+
+    fn const_value(&self, scope: &mut TopLevelScope) -> Option<i64> {
+        let value = self.primary.const_value(scope)?;
+        match self.prefix {
+            None | Some(Token::Add) => Some(value),
+            Some(Token::Sub) => value.checked_neg(),
+            Some(Token::Not) => Some((value == 0) as i64),
+            _ => None,
+        }
+    }
+
+    fn static_value(&self, scope: &mut TopLevelScope) -> Option<ExprStmt> {
+        match (&self.prefix, &self.primary.value) {
+            (None, _) => self.primary.static_value(scope),
+            (Some(Token::Sub), Const::Float(value, _)) => Some(ExprStmt::new(Box::new(Float { value: -value }))),
+            // 全局变量的地址也是常量
+            (Some(Token::Bitand), Const::Identifier) => {
+                let name = self.primary.get_name();
+                if !scope.is_global_var(&name) {
+                    return None
+                }
+                let var = scope.get_var(&name)?;
+                Some(ExprStmt::new(Box::new(Addr {
+                    expr: ExprStmt::new(Box::new(Var {
+                        name,
+                        expr: var,
+                    })),
+                })))
+            },
+            _ => self.const_value(scope).map(|value| ExprStmt::new(Box::new(Int { value }))),
+        }
+    }
+
+    fn string_literal(&self) -> Option<String> {
+        match (&self.prefix, &self.primary.value) {
+            (None, Const::String(value)) => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<ExprStmt, SemanticError> {
+        let value = self.primary.generate(scope, ir)?;
+        return Ok(generate_prefix(&self.prefix, value, ir))
+    }
+
+    fn generate_stmt(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        match self.prefix {
+            Some(Token::Inc) | Some(Token::Dec) => {
+                self.generate(scope, ir)?;
+            },
+            _ => {
+                let value = self.generate(scope, ir)?;
+                ir.emit(Box::new(value));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SizeofNode {
+    /*
+        [prefix] ( sizeof | alignof ) ( ( typeref ) | unary )
+    */
+    pub prefix: Option<Token>,
+    pub operator: Token,
+    pub target: SizeofTarget,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub enum SizeofTarget {
+    Type(TypeNode),
+    Expr(Rc<Box<dyn ExprNode>>),
+}
+
+impl SizeofNode {
+    /**
+     *  在编译期按类型算出大小或者对齐，表达式不会被求值，只用它的类型
+     */
+    fn eval(&self, scope: &mut TopLevelScope) -> Result<i64, SemanticError> {
+        let typeref = match &self.target {
+            SizeofTarget::Type(typeref) => scope.resolve_type(typeref).map_err(|e| e.with_span(self.span()))?,
+            SizeofTarget::Expr(expr) => expect_type(expr.get_type(scope)?, expr)?.typeref(),
+        };
+        let value = if self.operator == Token::Alignof {
+            scope.type_align(&typeref)
         } else {
-            return Some(TypeInfo {
-                name: String::from("none"),
-                origin_struct: None,
-                origin_base: None,
-                base_type: token,
-                nested_def: Vec::new(),
-            })
+            scope.type_size(&typeref)
+        };
+        let value = value.map_err(|e| e.with_span(self.span()))? as i64;
+
+        match self.prefix {
+            None | Some(Token::Add) => Ok(value),
+            Some(Token::Sub) => Ok(-value),
+            Some(Token::Not) => Ok((value == 0) as i64),
+            _ => Err(SemanticError::at(SemanticErrorType::NotLeftValue(self.operator.to_string()), self.span())),
         }
     }
 }
 
+impl UnaryNode for SizeofNode {
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn is_leftvalue(&self) -> Result<(), SemanticError> {
+        return Err(SemanticError::at(SemanticErrorType::NotLeftValue(self.operator.to_string()), self.span()))
+    }
+
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        if let SizeofTarget::Expr(expr) = &self.target {
+            expr.check_expr_validity(scope)?;
+        }
+        self.eval(scope)?;
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> String {
+        return String::from("none")
+    }
+
+    fn get_prefix(&self) -> Option<Token> {
+        return self.prefix.clone()
+    }
+
+    fn get_type(&self, _scope: &mut TopLevelScope) -> Result<Option<TypeInfo>, SemanticError> {
+        // 结果是size_t，也就是unsigned long
+        return Ok(Some(arithmetic_info(Token::Long, true)))
+    }
+
+    fn const_value(&self, scope: &mut TopLevelScope) -> Option<i64> {
+        return self.eval(scope).ok()
+    }
+
+    fn generate(&self, scope: &mut TopLevelScope, _ir: &mut IR) -> Result<ExprStmt, SemanticError> {
+        let value = self.eval(scope)?;
+        return Ok(ExprStmt::new(Box::new(Int { value })))
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct SelfOpUnaryNode {
     /*
@@ -255,28 +1367,22 @@ pub struct SelfOpUnaryNode {
     pub prefix: Option<Token>,
     pub primary: PrimaryNode,
     pub postfix: Token,
+    pub span: Span,
 }
 
 impl UnaryNode for SelfOpUnaryNode {
-    fn is_leftvalue(&self) -> Result<(), String> {
-        return self.primary.is_leftvalue()
+    fn span(&self) -> Span {
+        return self.span.clone()
     }
 
-    fn check_expr_validity(&self, mut scope: &mut TopLevelScope) {
-        if let Some(prefix) = self.get_prefix() {
-            match prefix {
-                Token::Inc |
-                Token::Dec |
-                Token::Mul |
-                Token::Bitand |
-                Token::Not => {
-                    self.get_name();
-                },
-                _ => {}
-            }
-        }
+    fn is_leftvalue(&self) -> Result<(), SemanticError> {
+        return self.primary.is_leftvalue()
+    }
 
-        self.get_name();
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        // 后置的自增自减本身就要求是左值
+        self.primary.check_modifiable(scope)?;
+        return check_assignable(&self.get_type(scope)?, self.span())
     }
 
     fn get_name(&self) -> String {
@@ -287,20 +1393,35 @@ impl UnaryNode for SelfOpUnaryNode {
         return self.prefix.clone()
     }
 
-    fn get_type(&self, mut scope: &mut TopLevelScope) -> Option<TypeInfo> {
-        let token = self.primary.get_primary_type();
-        if let Token::Name(n) = token {
-            let t = scope.get_type(&n);
-            return Some(t)
+    fn get_type(&self, scope: &mut TopLevelScope) -> Result<Option<TypeInfo>, SemanticError> {
+        let info = self.primary.get_primary_info(scope)?;
+        return prefix_type(&self.prefix, info, self.span())
+    }
+
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<ExprStmt, SemanticError> {
+        // 后置的自增自减，表达式的值是运算之前的值
+        let value = self.primary.generate(scope, ir)?;
+        let value = generate_self_op(&self.postfix, value, ir);
+        let op = if self.postfix == Token::Inc { Op::SUB } else { Op::ADD };
+        let origin = ExprStmt::new(Box::new(Bin {
+            op,
+            left: value,
+            right: ExprStmt::new(Box::new(Int { value: 1 })),
+        }));
+
+        return Ok(generate_prefix(&self.prefix, origin, ir))
+    }
+
+    fn generate_stmt(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        if self.prefix.is_none() {
+            let value = self.primary.generate(scope, ir)?;
+            generate_self_op(&self.postfix, value, ir);
         } else {
-            return Some(TypeInfo {
-                name: String::from("none"),
-                origin_struct: None,
-                origin_base: None,
-                base_type: token,
-                nested_def: Vec::new(),
-            })
+            let value = self.generate(scope, ir)?;
+            ir.emit(Box::new(value));
         }
+
+        Ok(())
     }
 }
 
@@ -312,38 +1433,52 @@ pub struct ArrayUnaryNode {
     pub prefix: Option<Token>,
     pub primary: PrimaryNode,
     pub postfix: Vec<Box<dyn ExprNode>>,
+    pub span: Span,
 }
 
 impl UnaryNode for ArrayUnaryNode {
-    fn is_leftvalue(&self) -> Result<(), String> {
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn is_leftvalue(&self) -> Result<(), SemanticError> {
         return self.primary.is_leftvalue()
     }
 
-    fn check_expr_validity(&self, scope: &mut TopLevelScope) {
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
         let literal = self.primary.get_primary_type();
-        let mut name = self.primary.get_name();
-        let var_type = scope.get_type(&name);
-        if var_type.nested_def.len() <= 0 {
-            panic!(format!("The identifier \"{}\" is not an array or a pointer", name));
+        let name = self.primary.get_name();
+        if !matches!(literal, Token::Name(_)) {
+            return Err(SemanticError::at(SemanticErrorType::NotIndexable(format!("{:?}", self.primary.value)), self.span()))
         }
-        if let Token::Name(n) = literal {
-            name = n;
-        } else {
-            panic!(format!("\"{}\" Type! Cannot be referenced as an array", name));
+        let var_type = scope.get_type(&name)?;
+        if var_type.nested_def.is_empty() {
+            return Err(SemanticError::at(SemanticErrorType::NotArray(name), self.span()))
+        }
+        if let Some(Token::Inc | Token::Dec) = self.prefix {
+            check_assignable(&self.get_type(scope)?, self.span())?;
         }
+
+        Ok(())
     }
 
-    fn get_type(&self, mut scope: &mut TopLevelScope) -> Option<TypeInfo> {
+    fn get_type(&self, scope: &mut TopLevelScope) -> Result<Option<TypeInfo>, SemanticError> {
         let name = self.primary.get_name();
-        let t = scope.get_type(&name);
-        // 这里返回的是每个元素的类型
-        return Some(TypeInfo {
-            name: String::from("none"),
+        let t = scope.get_type(&name)?;
+        // 每个下标去掉一层数组或者指针，剩下的是元素的类型
+        let mut nested_def = t.nested_def.clone();
+        nested_def.truncate(nested_def.len().saturating_sub(self.postfix.len()));
+        let mut qualifier = t.qualifier.clone();
+        qualifier.const_pointers.retain(|level| *level < nested_def.len());
+        let info = TypeInfo {
+            name,
             origin_struct: None,
             origin_base: None,
             base_type: t.base_type.clone(),
-            nested_def: Vec::new(),
-        })
+            nested_def,
+            qualifier,
+        };
+        return prefix_type(&self.prefix, Some(info), self.span())
     }
 
     fn get_name(&self) -> String {
@@ -353,6 +1488,47 @@ impl UnaryNode for ArrayUnaryNode {
     fn get_prefix(&self) -> Option<Token> {
         return self.prefix.clone()
     }
+
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<ExprStmt, SemanticError> {
+        /*
+            a[i] => mem(addr(a) + i * sizeof(elem))
+            指针类型的变量本身的值就是地址
+        */
+        let name = self.primary.get_name();
+        let mut typeref = match scope.get_var(&name) {
+            Some(var) => var.typeref,
+            None => return Err(undefined_symbol(&name).with_span(self.span())),
+        };
+        let var = self.primary.generate(scope, ir)?;
+        let mut addr = match typeref.nested_def.last() {
+            Some(TypeDef::FixedArray(_)) => ExprStmt::new(Box::new(Addr { expr: var })),
+            _ => var,
+        };
+
+        for (i, index) in self.postfix.iter().enumerate() {
+            typeref.nested_def.pop();
+            let size = scope.type_size(&typeref)? as i64;
+            let index = index.generate(scope, ir)?;
+            addr = ExprStmt::new(Box::new(Bin {
+                op: Op::ADD,
+                left: addr,
+                right: ExprStmt::new(Box::new(Bin {
+                    op: Op::MUL,
+                    left: index,
+                    right: ExprStmt::new(Box::new(Int { value: size })),
+                })),
+            }));
+
+            let is_last = i == self.postfix.len() - 1;
+            let is_fixed = matches!(typeref.nested_def.last(), Some(TypeDef::FixedArray(_)));
+            if !is_last && !is_fixed {
+                addr = ExprStmt::new(Box::new(Mem { expr: addr }));
+            }
+        }
+
+        let value = ExprStmt::new(Box::new(Mem { expr: addr }));
+        return Ok(generate_prefix(&self.prefix, value, ir))
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -364,122 +1540,135 @@ pub struct RefUnaryNode {
     pub operator: Token,
     pub primary: PrimaryNode,
     pub postfix: Option<Rc<Box<dyn UnaryNode>>>,
+    pub span: Span,
 }
 
 impl RefUnaryNode {
-    fn check_access_op(&self, option_op: &Option<Token>, name: String, member_list: &Vec<SlotNode>) {
+    fn check_access_op(&self, option_op: &Option<Token>, name: String, member_list: &Vec<SlotNode>) -> Result<(), SemanticError> {
         if let Some(op) = option_op {
-            let n = name.clone();
-            if *op == Token::PointerRef {
-                for mem in member_list {
-                    if mem.name == n {
-                        let nested_def = &mem.typeref.nested_def;
-                        if nested_def.len() == 0 || *nested_def.last().unwrap() != TypeDef::Pointer {
-                            panic!("Members of the \"{}\" should probably access through .", n);
-                        }
-                    }
+            for mem in member_list {
+                if mem.name != name {
+                    continue
                 }
-            } else {
-                for mem in member_list {
-                    if mem.name == n {
-                        let nested_def = &mem.typeref.nested_def;
-                        if nested_def.len() != 0 {
-                            panic!("Members of the \"{}\" should probably access through ->", n);
-                        }
+                let nested_def = &mem.typeref.nested_def;
+                if *op == Token::PointerRef {
+                    if nested_def.last() != Some(&TypeDef::Pointer) {
+                        return Err(SemanticError::at(SemanticErrorType::WrongAccessOp(name, Token::Dot), self.span()))
                     }
+                } else if !nested_def.is_empty() {
+                    return Err(SemanticError::at(SemanticErrorType::WrongAccessOp(name, Token::PointerRef), self.span()))
                 }
-            }            
+            }
+        }
+
+        Ok(())
+    }
+
+    /**
+     *  a.b.c的类型是最后一个成员c的类型，不算前缀
+     */
+    fn member_type(&self, scope: &mut TopLevelScope) -> Result<Option<TypeInfo>, SemanticError> {
+        let name = self.primary.get_name();
+        let struct_type = scope.get_type(&name)?;
+        let mut member_list = match &struct_type.origin_struct {
+            Some(origin) => origin.member_list.clone(),
+            None => return Err(SemanticError::at(SemanticErrorType::NotStruct(name), self.span())),
+        };
+        let mut postfix = self.get_postfix();
+        while let Some(unary) = postfix {
+            let mem_name = unary.get_name();
+            let mem = match member_list.iter().find(|mem| mem.name == mem_name) {
+                Some(mem) => mem.clone(),
+                None => return Ok(None),
+            };
+            let type_base = &mem.typeref.type_base;
+            // 枚举类型也有名字，但没有成员
+            let origin_struct = match (is_aggregate(&type_base.base), &type_base.name) {
+                (true, Some(name)) => Some(scope.get_struct(name)?.clone()),
+                _ => None,
+            };
+
+            postfix = unary.get_postfix();
+            match (postfix, &origin_struct) {
+                (Some(_), Some(origin)) => member_list = origin.member_list.clone(),
+                _ => return Ok(Some(TypeInfo {
+                    name: mem_name,
+                    origin_struct,
+                    origin_base: None,
+                    base_type: type_base.base.clone(),
+                    nested_def: mem.typeref.nested_def.clone(),
+                    qualifier: Qualifier {
+                        is_const: mem.typeref.qualifier.is_const || struct_type.qualifier.is_const,
+                        ..mem.typeref.qualifier.clone()
+                    },
+                })),
+            }
         }
+        return Ok(None)
     }
 }
 
 impl UnaryNode for RefUnaryNode {
-    fn is_leftvalue(&self) -> Result<(), String> {
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn is_leftvalue(&self) -> Result<(), SemanticError> {
         return self.primary.is_leftvalue()
     }
     
-    fn check_expr_validity(&self, mut scope: &mut TopLevelScope) {
-        let mut name = self.primary.get_name().clone();
-        let struct_type = scope.get_type(&name);
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        self.primary.is_leftvalue()?;
+        let mut name = self.primary.get_name();
+        let struct_type = scope.get_type(&name).map_err(|e| e.with_span(self.span()))?;
         let mut base_type = &struct_type.base_type;
-        let mut member_list = &struct_type.origin_struct.clone().unwrap().member_list;
+        let mut member_list = match &struct_type.origin_struct {
+            Some(origin) => &origin.member_list,
+            None => return Err(SemanticError::at(SemanticErrorType::NotStruct(name), self.span())),
+        };
         let mut postfix = &self.postfix;
-        self.check_access_op(&Some(self.operator.clone()), name.clone(), &member_list);
-        loop {
-            if let Some(unary) = postfix {
-                let mut names_type = HashMap::new();
-                let mem_name;
-                if *base_type == Token::Struct {
-                    let mut names = Vec::new();
-                    for var in member_list {
-                        if let Some(t) = var.typeref.type_base.name.as_ref() {
-                            names_type.insert(var.name.clone(), t.clone());
-                        } else {
-                            names_type.insert(var.name.clone(), String::from(""));
-                        }
-                        names.push(var.name.clone());
-                    }
-                    let suffix = postfix.as_ref().unwrap();
-                    mem_name = suffix.get_name();
-                    if !names.contains(&mem_name) {
-                        panic!("{} has no members of \"{}\"", name, mem_name);
+        self.check_access_op(&Some(self.operator.clone()), name.clone(), member_list)?;
+        while let Some(unary) = postfix {
+            let mut names_type = HashMap::new();
+            let mem_name;
+            if is_aggregate(base_type) {
+                let mut names = Vec::new();
+                for var in member_list {
+                    if let Some(t) = var.typeref.type_base.name.as_ref() {
+                        names_type.insert(var.name.clone(), t.clone());
+                    } else {
+                        names_type.insert(var.name.clone(), String::from(""));
                     }
-                } else {
-                    panic!("Type error! The identifier \"{}\" is not a struct", name);
+                    names.push(var.name.clone());
                 }
-                name = unary.get_name();
-                self.check_access_op(&unary.get_operator(), name.clone(), &member_list);
-                postfix = &unary.get_postfix();
-                let mem = names_type.get(&mem_name).unwrap();
-                if let Some(_type) = &scope.global_define_map.get(mem) { 
-                    member_list = &_type.member_list;
-                    base_type = &Token::Struct;
-                } else {
-                    break;
+                mem_name = unary.get_name();
+                if !names.contains(&mem_name) {
+                    return Err(SemanticError::at(SemanticErrorType::NoMember(name, mem_name), self.span()))
                 }
             } else {
-                break;
-            }        
-        }
-    }
-
-    fn get_type(&self, mut scope: &mut TopLevelScope) -> Option<TypeInfo> {
-        let name = self.primary.get_name();
-        let struct_type = scope.get_type(&name);
-        let mut member_list = &struct_type.origin_struct.clone().unwrap().member_list;    
-        let mut postfix = self.get_postfix();
-        loop {
-            if let Some(unary) = postfix {
-                let mem_name = unary.get_name();
-                for mem in member_list {
-                    if mem_name == mem.name {
-                        let type_base = &mem.typeref.type_base;
-                        if let Some(name) = &type_base.name {
-                            return Some(TypeInfo {
-                                name: mem_name.clone(),
-                                origin_struct: Some(scope.global_define_map.get(&name.clone()).unwrap().clone()),
-                                origin_base: None,
-                                base_type: type_base.base.clone(),
-                                nested_def: mem.typeref.nested_def.clone(),
-                            })
-                        } else {
-                            return Some(TypeInfo {
-                                name: mem_name.clone(),
-                                origin_struct: None,
-                                origin_base: None,
-                                base_type: type_base.base.clone(),
-                                nested_def: mem.typeref.nested_def.clone(),
-                            })
-                        }
-                    }
-                }
-                postfix = unary.get_postfix();
+                return Err(SemanticError::at(SemanticErrorType::NotStruct(name), self.span()))
+            }
+            name = unary.get_name();
+            self.check_access_op(&unary.get_operator(), name.clone(), member_list)?;
+            postfix = unary.get_postfix();
+            let mem = &names_type[&mem_name];
+            if let Some(_type) = &scope.global_define_map.get(mem) { 
+                member_list = &_type.member_list;
+                base_type = &_type.kind;
             } else {
                 break;
             }
-
         }
-        return None
+        if let Some(Token::Inc | Token::Dec) = self.prefix {
+            check_assignable(&self.get_type(scope)?, self.span())?;
+        }
+
+        Ok(())
+    }
+
+    fn get_type(&self, scope: &mut TopLevelScope) -> Result<Option<TypeInfo>, SemanticError> {
+        let info = self.member_type(scope)?;
+        return prefix_type(&self.prefix, info, self.span())
     }
 
     fn get_name(&self) -> String {
@@ -497,29 +1686,59 @@ impl UnaryNode for RefUnaryNode {
     fn get_prefix(&self) -> Option<Token> {
         return self.prefix.clone()
     }
-}
 
-#[derive(Clone, Debug)]
-pub struct PointerRefUnaryNode {
-    /*
-        [prefix] primary [-> unary]*
-    */
-    pub prefix: Option<Token>,
-    pub primary: PrimaryNode,
-    pub postfix: Option<Rc<Box<dyn UnaryNode>>>,
-}
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<ExprStmt, SemanticError> {
+        /*
+            a.b  => mem(addr(a) + offset(b))
+            a->b => mem(a + offset(b))
+        */
+        let name = self.primary.get_name();
+        let mut typeref = match scope.get_var(&name) {
+            Some(var) => var.typeref,
+            None => return Err(undefined_symbol(&name).with_span(self.span())),
+        };
+        let mut value = self.primary.generate(scope, ir)?;
+        let mut operator = self.operator.clone();
+        let mut postfix = &self.postfix;
 
-impl UnaryNode for PointerRefUnaryNode {
-    fn is_leftvalue(&self) -> Result<(), String> {
-        return self.primary.is_leftvalue()
-    }
-    
-    fn get_name(&self) -> String {
-        return self.primary.get_name();
-    }
+        while let Some(unary) = postfix {
+            let mem_name = unary.get_name();
+            let struct_name = match &typeref.type_base.name {
+                Some(n) => n.clone(),
+                None => return Err(SemanticError::at(SemanticErrorType::NotStruct(name), self.span())),
+            };
+            let (offsets, _) = scope.struct_layout(&struct_name)?;
+            let offset = match offsets.get(&mem_name) {
+                Some(offset) => *offset as i64,
+                None => return Err(SemanticError::at(SemanticErrorType::NoMember(struct_name, mem_name), self.span())),
+            };
+            let base = if operator == Token::Dot {
+                ExprStmt::new(Box::new(Addr { expr: value }))
+            } else {
+                value
+            };
+            value = ExprStmt::new(Box::new(Mem {
+                expr: ExprStmt::new(Box::new(Bin {
+                    op: Op::ADD,
+                    left: base,
+                    right: ExprStmt::new(Box::new(Int { value: offset })),
+                })),
+            }));
+
+            for mem in &scope.get_struct(&struct_name)?.member_list {
+                if mem.name == mem_name {
+                    typeref = mem.typeref.clone();
+                }
+            }
 
-    fn get_prefix(&self) -> Option<Token> {
-        return self.prefix.clone()
+            match unary.get_operator() {
+                Some(op) => operator = op,
+                None => break,
+            }
+            postfix = unary.get_postfix();
+        }
+
+        return Ok(generate_prefix(&self.prefix, value, ir))
     }
 }
 
@@ -531,10 +1750,70 @@ pub struct FuncCallNode {
     pub prefix: Option<Token>,
     pub primary: PrimaryNode,
     pub params: Option<Vec<Rc<Box<dyn ExprNode>>>>,
+    pub span: Span,
+}
+
+impl FuncCallNode {
+    /**
+     *  局部变量会遮住同名的函数，变量必须是函数指针才能调用
+     *  返回None时是直接调用函数
+     */
+    fn pointer_type(&self, scope: &mut TopLevelScope) -> Result<Option<FuncType>, SemanticError> {
+        let name = self.primary.get_name();
+        let var = match scope.get_var(&name) {
+            Some(var) => var,
+            None => return Ok(None),
+        };
+
+        match var.typeref.nested_def.as_slice() {
+            [.., TypeDef::Func(func), TypeDef::Pointer] | [.., TypeDef::Func(func)] => Ok(Some(*func.clone())),
+            _ => Err(SemanticError::at(SemanticErrorType::NotFunction(name), self.span())),
+        }
+    }
+
+    /**
+     *  调用的结果是函数的返回值类型
+     */
+    fn return_type(&self, scope: &mut TopLevelScope) -> Result<Option<TypeInfo>, SemanticError> {
+        if let Some(func) = self.pointer_type(scope)? {
+            let return_type = func.return_type;
+            let origin_struct = match return_type.type_base.base {
+                Token::Struct | Token::Union => Some(scope.get_struct(&return_type.type_base.name.clone().unwrap_or_default())?.clone()),
+                _ => None,
+            };
+            return Ok(Some(TypeInfo {
+                name: self.get_name(),
+                origin_struct,
+                origin_base: None,
+                base_type: return_type.type_base.base,
+                nested_def: return_type.nested_def,
+                qualifier: return_type.qualifier,
+            }))
+        }
+
+        let name = self.primary.get_name();
+        let func = match scope.func_map.get(&name) {
+            Some(func) => func,
+            None => return Err(SemanticError::at(SemanticErrorType::UndefinedFunction(name), self.span())),
+        };
+
+        return Ok(Some(TypeInfo {
+            name: name.clone(),
+            origin_struct: None,
+            origin_base: None,
+            base_type: func.typeref.type_base.base.clone(),
+            nested_def: func.typeref.nested_def.clone(),
+            qualifier: func.typeref.qualifier.clone(),
+        }))
+    }
 }
 
 impl UnaryNode for FuncCallNode {
-    fn is_leftvalue(&self) -> Result<(), String> {
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn is_leftvalue(&self) -> Result<(), SemanticError> {
         return self.primary.is_leftvalue()
     }
 
@@ -546,68 +1825,234 @@ impl UnaryNode for FuncCallNode {
         return self.prefix.clone()
     }
 
-    fn get_type(&self, mut scope: &mut TopLevelScope) -> Option<TypeInfo> {
-        let name = self.primary.get_name();
-        let func = scope.func_map.get(&name);
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        let func = match self.pointer_type(scope)? {
+            Some(func) => func,
+            None => match scope.func_map.get(&self.get_name()) {
+                Some(def) => def.signature(),
+                None => return Ok(()),
+            },
+        };
+
+        // 检查参数的个数和类型，可变参数函数只检查前面固定的参数
+        let args = self.params.clone().unwrap_or_default();
+        if func.variadic && args.len() < func.params.len() {
+            let error = SemanticErrorType::TooFewArguments(self.get_name(), func.params.len(), args.len());
+            return Err(SemanticError::at(error, self.span()))
+        }
+        if !func.variadic && args.len() != func.params.len() {
+            let error = SemanticErrorType::ArgumentCount(self.get_name(), func.params.len(), args.len());
+            return Err(SemanticError::at(error, self.span()))
+        }
+        for (i, arg) in args.iter().enumerate() {
+            arg.check_expr_validity(scope)?;
+            let param = match func.params.get(i) {
+                Some(param) => param,
+                None => continue,
+            };
+            let arg_type = expect_type(arg.get_type(scope)?, arg)?;
+            let left = (param.type_base.base.clone(), param.nested_def.clone());
+            let right = (arg_type.base_type.clone(), arg_type.nested_def.clone());
+            check_type_compatible(left, right).map_err(|e| e.with_span(arg.span()))?;
+        }
 
-        return Some(TypeInfo {
-            name: name.clone(),
-            origin_struct: None,
-            origin_base: None,
-            base_type: func.unwrap().typeref.type_base.base.clone(),
-            nested_def: func.unwrap().typeref.nested_def.clone(),
-        }) 
+        Ok(())
+    }
+
+    fn get_type(&self, scope: &mut TopLevelScope) -> Result<Option<TypeInfo>, SemanticError> {
+        let info = self.return_type(scope)?;
+        return prefix_type(&self.prefix, info, self.span())
+    }
+
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<ExprStmt, SemanticError> {
+        let mut args = Vec::new();
+        let mut sse_count = 0;
+        if let Some(params) = &self.params {
+            for param in params {
+                if let Ok(Some(info)) = param.get_type(scope) {
+                    let is_float = info.base_type == Token::Float || info.base_type == Token::Double;
+                    sse_count += (is_float && info.nested_def.is_empty()) as usize;
+                }
+                args.push(param.generate(scope, ir)?);
+            }
+        }
+        // 函数指针的值就是要调用的地址
+        let (expr, variadic) = match self.pointer_type(scope)? {
+            Some(func) => (self.primary.generate(scope, ir)?, func.variadic),
+            None => (ExprStmt::new(Box::new(FuncAddr {
+                name: self.primary.get_name(),
+            })), scope.func_map.get(&self.get_name()).is_some_and(|def| def.params.variadic)),
+        };
+        let call = ExprStmt::new(Box::new(Call {
+            expr,
+            args,
+            // 浮点数参数最多用8个xmm寄存器传递
+            varargs: if variadic { Some(sse_count.min(8)) } else { None },
+        }));
+
+        return Ok(generate_prefix(&self.prefix, call, ir))
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct PrimaryNode {
     /*
-        Integer | Char | String | Identifier | (expr)
+        Integer | Float | Char | String | Identifier | (expr)
     */
     pub name: Option<String>,
     pub value: Const,
+    pub span: Span,
 }
 
 impl PrimaryNode {
     fn get_primary_type(&self) -> Token {
         match &self.value {
-            Const::Integer(value) => return Token::Int,
-            Const::Char(value) => return Token::Char,
-            Const::String(value) => return Token::String(String::from(value)),
-            Const::Identifier => return Token::Name(String::from(self.name.as_ref().unwrap().clone())),
-            Const::ParenthesesExpr(value) => return Token::LParentheses,
+            Const::Integer(_value, literal_type) => return literal_type.base(),
+            Const::Float(_value, literal_type) => return literal_type.base(),
+            Const::Char(_value) => return Token::Char,
+            Const::String(_value) => return Token::Char,
+            Const::Identifier => return Token::Name(self.get_name()),
+            Const::ParenthesesExpr(_value) => return Token::LParentheses,
+        }
+    }
+
+    /**
+     *  自增自减的对象要是能修改的左值，枚举成员虽然是标识符但它是常量
+     */
+    fn check_modifiable(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        self.is_leftvalue()?;
+        if self.const_value(scope).is_some() {
+            return Err(SemanticError::at(SemanticErrorType::NotLeftValue(self.get_name()), self.span()))
+        }
+
+        Ok(())
+    }
+
+    fn get_primary_info(&self, scope: &mut TopLevelScope) -> Result<Option<TypeInfo>, SemanticError> {
+        // 括号表达式的类型就是括号里面的表达式的类型，比如(a, b)是b的类型
+        if let Const::ParenthesesExpr(value) = &self.value {
+            return value.get_type(scope)
+        }
+        let token = self.get_primary_type();
+        if let Token::Name(n) = token {
+            return Ok(Some(scope.get_type(&n).map_err(|e| e.with_span(self.span.clone()))?))
+        } else {
+            let is_unsigned = matches!(&self.value, Const::Integer(_, literal_type) if literal_type.is_unsigned());
+            // 字符串字面量是带着结尾0的char数组
+            let nested_def = match &self.value {
+                Const::String(value) => vec![TypeDef::FixedArray(value.len() + 1)],
+                _ => Vec::new(),
+            };
+            return Ok(Some(TypeInfo {
+                name: String::from("none"),
+                origin_struct: None,
+                origin_base: None,
+                base_type: token,
+                nested_def,
+                qualifier: Qualifier {
+                    is_unsigned,
+                    ..Qualifier::default()
+                },
+            }))
         }
     }
 }
 
 impl UnaryNode for PrimaryNode {
-    fn is_leftvalue(&self) -> Result<(), String> {
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn is_leftvalue(&self) -> Result<(), SemanticError> {
         match &self.value {
             Const::Identifier => Ok(()),
-            Const::Integer(value) => Err(format!("Unexpect an left value: {}", value)),
-            Const::Char(value) => Err(format!("Unexpect an left value: {}", value)),
-            Const::String(value) => Err(format!("Unexpect an left value: {}", value)),
-            Const::ParenthesesExpr(value) => Err(format!("Unexpect an left value: {:?}", value)),
+            Const::Integer(value, _) => Err(SemanticError::at(SemanticErrorType::NotLeftValue(value.to_string()), self.span())),
+            Const::Float(value, _) => Err(SemanticError::at(SemanticErrorType::NotLeftValue(value.to_string()), self.span())),
+            Const::Char(value) => Err(SemanticError::at(SemanticErrorType::NotLeftValue(value.to_string()), self.span())),
+            Const::String(value) => Err(SemanticError::at(SemanticErrorType::NotLeftValue(value.clone()), self.span())),
+            Const::ParenthesesExpr(value) => Err(SemanticError::at(SemanticErrorType::NotLeftValue(format!("{:?}", value)), self.span())),
         }
     }
 
     fn get_name(&self) -> String {
-        if let Some(name) = &self.name {
-            return name.clone()
-        } else {
-            panic!("Type error! {:?}, Expect an left value", self.value);
-        }
+        // 不是标识符时没有名字，调用前应该先用is_leftvalue检查
+        return self.name.clone().unwrap_or_default()
     }
 
     fn get_prefix(&self) -> Option<Token> {
         return None
     }
+
+    fn const_value(&self, scope: &mut TopLevelScope) -> Option<i64> {
+        match &self.value {
+            // 超过i64的无符号数不参与常量折叠
+            Const::Integer(value, _) => i64::try_from(*value).ok(),
+            Const::Char(value) => Some(*value as i64),
+            Const::ParenthesesExpr(value) => value.const_value(scope),
+            Const::Identifier => scope.enum_constant(&self.get_name()),
+            _ => None,
+        }
+    }
+
+    fn static_value(&self, scope: &mut TopLevelScope) -> Option<ExprStmt> {
+        match &self.value {
+            Const::Integer(value, _) => Some(ExprStmt::new(Box::new(Int { value: *value as i64 }))),
+            Const::Float(value, _) => Some(ExprStmt::new(Box::new(Float { value: *value }))),
+            Const::String(value) => Some(ExprStmt::new(Box::new(Str { value: value.clone() }))),
+            Const::ParenthesesExpr(value) => value.static_value(scope),
+            // 全局数组的名字和函数名都是编译期就能确定的地址
+            Const::Identifier => {
+                let name = self.get_name();
+                match scope.get_var(&name) {
+                    Some(var) if scope.is_global_var(&name) && matches!(var.typeref.nested_def.last(), Some(TypeDef::FixedArray(_))) => {
+                        Some(ExprStmt::new(Box::new(Addr {
+                            expr: ExprStmt::new(Box::new(Var {
+                                name,
+                                expr: var,
+                            })),
+                        })))
+                    },
+                    None if scope.func_map.contains_key(&name) => Some(ExprStmt::new(Box::new(FuncAddr { name }))),
+                    _ => self.const_value(scope).map(|value| ExprStmt::new(Box::new(Int { value }))),
+                }
+            },
+            _ => self.const_value(scope).map(|value| ExprStmt::new(Box::new(Int { value }))),
+        }
+    }
+
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<ExprStmt, SemanticError> {
+        match &self.value {
+            // 无符号数按位放进IR的i64里
+            Const::Integer(value, _) => Ok(ExprStmt::new(Box::new(Int { value: *value as i64 }))),
+            Const::Float(value, _) => Ok(ExprStmt::new(Box::new(Float { value: *value }))),
+            Const::Char(value) => Ok(ExprStmt::new(Box::new(Int { value: *value as i64 }))),
+            Const::String(value) => Ok(ExprStmt::new(Box::new(Str { value: value.clone() }))),
+            Const::Identifier => {
+                let name = self.get_name();
+                match scope.get_var(&name) {
+                    Some(var) => Ok(ExprStmt::new(Box::new(Var {
+                        name: scope.static_local(&name).unwrap_or(name),
+                        expr: var,
+                    }))),
+                    None if scope.func_map.contains_key(&name) => Ok(ExprStmt::new(Box::new(FuncAddr {
+                        name,
+                    }))),
+                    None => match scope.enum_constant(&name) {
+                        Some(value) => Ok(ExprStmt::new(Box::new(Int { value }))),
+                        None => Err(undefined_symbol(&name).with_span(self.span())),
+                    },
+                }
+            },
+            Const::ParenthesesExpr(value) => value.generate(scope, ir),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 pub enum Const {
-    Integer(i64),
+    // 无符号的字面量可能超过i64的范围，按u64保存
+    Integer(u64, LiteralType),
+    Float(f64, LiteralType),
     Char(char),
     String(String),
     Identifier,
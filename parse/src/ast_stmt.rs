@@ -1,55 +1,106 @@
-use crate::ast_expr::ExprNode;
-use crate::ast::DefVarNode;
+use crate::ast_expr:: {
+    ExprNode,
+    expect_type,
+    type_name,
+};
 use std::fmt;
+use lex::location::Span;
+use lex::token::Token;
 use std::rc::Rc;
-use std::cell::RefCell;
-use crate::symbol_table:: {
-    TopLevelScope,
-    LocalScope,
-};
+use crate::symbol_table::TopLevelScope;
 use crate::ast::DefNode;
+use crate::error:: {
+    SemanticError,
+    SemanticErrorType,
+};
+use crate::ir:: {
+    IR,
+    Op,
+    ExprStmt,
+    Assign,
+    Bin,
+    Int,
+    CJump,
+    Jump,
+    LabelStmt,
+    Return,
+};
+use std::collections::HashMap;
 
 pub trait StmtNode:fmt::Debug {
-    fn fill_symbol(&self, scope: &mut TopLevelScope) {}
-    fn check_expr_validity(&self, mut scope: &mut TopLevelScope) {}
+    fn span(&self) -> Span;
+    fn fill_symbol(&self, _scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        return Ok(())
+    }
+    fn check_expr_validity(&self, _scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        return Ok(())
+    }
+    fn generate(&self, _scope: &mut TopLevelScope, _ir: &mut IR) -> Result<(), SemanticError> {
+        return Ok(())
+    }
 }
 
 #[derive(Debug)]
 pub struct BlockNode {
     /*
         {
-            defvar *  stmts *
+            ( defvar | stmt ) *
         }
+        声明和语句按源码里的顺序保存
     */
-    pub defvars: Vec<Box<dyn DefNode>>,
-    pub stmts: Vec<Rc<Box<dyn StmtNode>>>,
+    pub items: Vec<BlockItem>,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub enum BlockItem {
+    Def(Box<dyn DefNode>),
+    Stmt(Rc<Box<dyn StmtNode>>),
 }
 
 impl StmtNode for BlockNode {
-    fn fill_symbol(&self, scope: &mut TopLevelScope) {
-        let local = Rc::new(RefCell::new(LocalScope::new()));
-        let parent = &scope.scope_stack[scope.scope_stack.len() - 1];
-        local.borrow_mut().parent = Some(Rc::clone(parent));
-        parent.borrow_mut().scopes.push(Rc::clone(&local));
-        scope.scope_stack.push(Rc::clone(&local));
-        for var in &self.defvars {
-            var.fill_symbol(scope);
-        }
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
 
-        for stmt in self.stmts.clone() {
-            stmt.fill_symbol(scope);
+    fn fill_symbol(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        scope.new_block();
+        for item in &self.items {
+            match item {
+                BlockItem::Def(var) => var.fill_symbol(scope)?,
+                BlockItem::Stmt(stmt) => stmt.fill_symbol(scope)?,
+            }
         }
         scope.scope_stack.pop();
+
+        Ok(())
+    }
+
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        // 进入block时变量都还没声明，走到声明的地方才能用
+        scope.push_block();
+        for item in &self.items {
+            match item {
+                BlockItem::Def(var) => var.check_expr_validity(scope)?,
+                BlockItem::Stmt(stmt) => stmt.check_expr_validity(scope)?,
+            }
+        }
+        scope.pop_block();
+
+        Ok(())
     }
 
-    fn check_expr_validity(&self, mut scope: &mut TopLevelScope) {
-        let index = scope.scope_stack.len() - 1;
-        let local = &scope.scope_stack[index];
-        scope.push_block(Rc::clone(local));
-        for stmt in &self.stmts {
-            stmt.check_expr_validity(&mut scope);
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        scope.push_block();
+        for item in &self.items {
+            match item {
+                BlockItem::Def(var) => var.generate(scope, ir)?,
+                BlockItem::Stmt(stmt) => stmt.generate(scope, ir)?,
+            }
         }
         scope.pop_block();
+
+        Ok(())
     }
 }
 
@@ -61,34 +112,80 @@ pub struct IfStmtNode {
     pub condition: Box<dyn ExprNode>,
     pub if_stmt: Box<dyn StmtNode>,
     pub else_stmt: Option<Box<dyn StmtNode>>,
+    pub span: Span,
 }
 
 impl StmtNode for IfStmtNode {
-    fn fill_symbol(&self, scope: &mut TopLevelScope) {
-        let local = Rc::new(RefCell::new(LocalScope::new()));
-        local.borrow_mut().parent = Some(Rc::clone(&scope.scope_stack[scope.scope_stack.len() - 1]));
-        self.if_stmt.fill_symbol(scope);
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn fill_symbol(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        self.if_stmt.fill_symbol(scope)?;
         if let Some(block) = &self.else_stmt {
-            block.fill_symbol(scope);
+            block.fill_symbol(scope)?;
         }
+
+        Ok(())
     }
 
-    fn check_expr_validity(&self, mut scope: &mut TopLevelScope) {
-        self.if_stmt.check_expr_validity(&mut scope);
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        self.condition.check_expr_validity(scope).map_err(|e| e.with_span(self.condition.span()))?;
+        self.if_stmt.check_expr_validity(scope)?;
         if let Some(block) = &self.else_stmt {
-            block.check_expr_validity(&mut scope);
+            block.check_expr_validity(scope)?;
         }
+
+        Ok(())
+    }
+
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        /*
+            cjump cond then else
+            then: if_stmt jump end
+            else: else_stmt
+            end:
+        */
+        let cond = self.condition.generate(scope, ir)?;
+        let then_label = ir.new_label();
+        let end_label = ir.new_label();
+        let else_label = if self.else_stmt.is_some() { ir.new_label() } else { end_label.clone() };
+
+        ir.emit(Box::new(CJump {
+            cond,
+            then_label: then_label.clone(),
+            else_label: else_label.clone(),
+        }));
+        ir.emit(Box::new(then_label));
+        self.if_stmt.generate(scope, ir)?;
+        if let Some(block) = &self.else_stmt {
+            ir.emit(Box::new(Jump { label: end_label.clone() }));
+            ir.emit(Box::new(else_label));
+            block.generate(scope, ir)?;
+        }
+        ir.emit(Box::new(end_label));
+
+        Ok(())
     }
 }
 
 #[derive(Debug)]
 pub struct ExprStmtNode {
     pub expr: Box<dyn ExprNode>,
+    pub span: Span,
 }
 
 impl StmtNode for ExprStmtNode {
-    fn check_expr_validity(&self, mut scope: &mut TopLevelScope) {
-        self.expr.check_expr_validity(&mut scope);
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        self.expr.check_expr_validity(scope).map_err(|e| e.with_span(self.span()))
+    }
+
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        self.expr.generate_stmt(scope, ir).map_err(|e| e.with_span(self.span()))
     }
 }
 
@@ -99,17 +196,48 @@ pub struct WhileStmtNode {
     */
     pub condition: Box<dyn ExprNode>,
     pub stmts: Box<dyn StmtNode>,
+    pub span: Span,
 }
 
 impl StmtNode for WhileStmtNode {
-    fn fill_symbol(&self, scope: &mut TopLevelScope) {
-        let local = Rc::new(RefCell::new(LocalScope::new()));
-        local.borrow_mut().parent = Some(Rc::clone(&scope.scope_stack[scope.scope_stack.len() - 1]));
-        self.stmts.fill_symbol(scope);
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn fill_symbol(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        self.stmts.fill_symbol(scope)
+    }
+
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        self.condition.check_expr_validity(scope).map_err(|e| e.with_span(self.condition.span()))?;
+        self.stmts.check_expr_validity(scope)
     }
 
-    fn check_expr_validity(&self, mut scope: &mut TopLevelScope) {
-        self.stmts.check_expr_validity(&mut scope);
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        /*
+            begin: cjump cond body end
+            body: stmts jump begin
+            end:
+        */
+        let begin_label = ir.new_label();
+        let body_label = ir.new_label();
+        let end_label = ir.new_label();
+
+        ir.emit(Box::new(begin_label.clone()));
+        let cond = self.condition.generate(scope, ir)?;
+        ir.emit(Box::new(CJump {
+            cond,
+            then_label: body_label.clone(),
+            else_label: end_label.clone(),
+        }));
+        ir.emit(Box::new(body_label));
+        ir.push_loop(end_label.clone(), begin_label.clone());
+        self.stmts.generate(scope, ir)?;
+        ir.pop_loop();
+        ir.emit(Box::new(Jump { label: begin_label }));
+        ir.emit(Box::new(end_label));
+
+        Ok(())
     }
 }
 
@@ -120,40 +248,296 @@ pub struct DoWhileStmtNode {
     */
     pub condition: Box<dyn ExprNode>,
     pub stmts: Box<dyn StmtNode>,
+    pub span: Span,
 }
 
 impl StmtNode for DoWhileStmtNode {
-    fn fill_symbol(&self, scope: &mut TopLevelScope) {
-        let local = Rc::new(RefCell::new(LocalScope::new()));
-        local.borrow_mut().parent = Some(Rc::clone(&scope.scope_stack[scope.scope_stack.len() - 1]));
-        self.stmts.fill_symbol(scope);
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn fill_symbol(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        self.stmts.fill_symbol(scope)
+    }
+
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        self.condition.check_expr_validity(scope).map_err(|e| e.with_span(self.condition.span()))?;
+        self.stmts.check_expr_validity(scope)
     }
 
-    fn check_expr_validity(&self, mut scope: &mut TopLevelScope) {
-        self.stmts.check_expr_validity(&mut scope);
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        /*
+            begin: stmts
+            continue: cjump cond begin end
+            end:
+        */
+        let begin_label = ir.new_label();
+        let continue_label = ir.new_label();
+        let end_label = ir.new_label();
+
+        ir.emit(Box::new(begin_label.clone()));
+        ir.push_loop(end_label.clone(), continue_label.clone());
+        self.stmts.generate(scope, ir)?;
+        ir.pop_loop();
+        ir.emit(Box::new(continue_label));
+        let cond = self.condition.generate(scope, ir)?;
+        ir.emit(Box::new(CJump {
+            cond,
+            then_label: begin_label,
+            else_label: end_label.clone(),
+        }));
+        ir.emit(Box::new(end_label));
+
+        Ok(())
     }
 }
 
 #[derive(Debug)]
 pub struct ForStmtNode {
     /*
-        for (expr; expr; expr;) block
+        for ( [ defvar | expr ; ] [ expr ] ; [ expr ] ) block
+        初始化部分声明的变量只在循环里有效，条件为空时一直循环
     */
-    pub initial_expr: Box<dyn ExprNode>,
-    pub condition: Box<dyn ExprNode>,
-    pub end_expr: Box<dyn ExprNode>,
+    pub initial_def: Option<Box<dyn DefNode>>,
+    pub initial_expr: Option<Box<dyn ExprNode>>,
+    pub condition: Option<Box<dyn ExprNode>>,
+    pub end_expr: Option<Box<dyn ExprNode>>,
     pub stmts: Box<dyn StmtNode>,
+    pub span: Span,
 }
 
 impl StmtNode for ForStmtNode {
-    fn fill_symbol(&self, scope: &mut TopLevelScope) {
-        let local = Rc::new(RefCell::new(LocalScope::new()));
-        local.borrow_mut().parent = Some(Rc::clone(&scope.scope_stack[scope.scope_stack.len() - 1]));
-        self.stmts.fill_symbol(scope);
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn fill_symbol(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        // 有声明时给循环单独建一个作用域，循环体的block在它里面
+        if let Some(def) = &self.initial_def {
+            scope.new_block();
+            def.fill_symbol(scope)?;
+            self.stmts.fill_symbol(scope)?;
+            scope.scope_stack.pop();
+            return Ok(())
+        }
+
+        self.stmts.fill_symbol(scope)
+    }
+
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        if let Some(def) = &self.initial_def {
+            scope.push_block();
+            def.check_expr_validity(scope)?;
+        }
+        // 三个子句都在循环的作用域里检查，能看到初始化部分声明的变量
+        for expr in [&self.initial_expr, &self.condition, &self.end_expr].iter().filter_map(|expr| expr.as_ref()) {
+            expr.check_expr_validity(scope).map_err(|e| e.with_span(expr.span()))?;
+        }
+        self.stmts.check_expr_validity(scope)?;
+        if self.initial_def.is_some() {
+            scope.pop_block();
+        }
+
+        Ok(())
+    }
+
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        /*
+            initial_expr
+            begin: cjump cond body end
+            body: stmts
+            continue: end_expr jump begin
+            end:
+        */
+        let begin_label = ir.new_label();
+        let body_label = ir.new_label();
+        let continue_label = ir.new_label();
+        let end_label = ir.new_label();
+
+        if let Some(def) = &self.initial_def {
+            scope.push_block();
+            def.generate(scope, ir)?;
+        }
+        if let Some(expr) = &self.initial_expr {
+            expr.generate_stmt(scope, ir)?;
+        }
+        ir.emit(Box::new(begin_label.clone()));
+        // 没有条件时不需要跳转，直接进入循环体
+        if let Some(condition) = &self.condition {
+            let cond = condition.generate(scope, ir)?;
+            ir.emit(Box::new(CJump {
+                cond,
+                then_label: body_label.clone(),
+                else_label: end_label.clone(),
+            }));
+        }
+        ir.emit(Box::new(body_label));
+        ir.push_loop(end_label.clone(), continue_label.clone());
+        self.stmts.generate(scope, ir)?;
+        ir.pop_loop();
+        ir.emit(Box::new(continue_label));
+        if let Some(expr) = &self.end_expr {
+            expr.generate_stmt(scope, ir)?;
+        }
+        ir.emit(Box::new(Jump { label: begin_label }));
+        ir.emit(Box::new(end_label));
+        if self.initial_def.is_some() {
+            scope.pop_block();
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct SwitchStmtNode {
+    /*
+        SWITCH (expr) { case_node * }
+    */
+    pub condition: Box<dyn ExprNode>,
+    pub cases: Vec<CaseNode>,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct CaseNode {
+    /*
+        (CASE expr | DEFAULT) : stmt *
+        value为None时是default
+    */
+    pub value: Option<Box<dyn ExprNode>>,
+    pub stmts: Vec<Rc<Box<dyn StmtNode>>>,
+    pub span: Span,
+}
+
+impl SwitchStmtNode {
+    /**
+     *  case的值必须是整数常量，而且不能重复，default最多一个
+     */
+    fn case_values(&self, scope: &mut TopLevelScope) -> Result<Vec<Option<i64>>, SemanticError> {
+        let mut values = Vec::new();
+        let mut labels: HashMap<i64, Span> = HashMap::new();
+        let mut default: Option<Span> = None;
+        for case in &self.cases {
+            match &case.value {
+                Some(expr) => {
+                    let value = match expr.const_value(scope) {
+                        Some(value) => value,
+                        None => return Err(SemanticError::at(SemanticErrorType::NotConstant, expr.span())),
+                    };
+                    if let Some(prev) = labels.get(&value) {
+                        return Err(SemanticError::at(SemanticErrorType::DuplicateCase(value, prev.clone()), case.span.clone()))
+                    }
+                    labels.insert(value, case.span.clone());
+                    values.push(Some(value));
+                },
+                None => {
+                    if let Some(prev) = default {
+                        return Err(SemanticError::at(SemanticErrorType::DuplicateDefault(prev), case.span.clone()))
+                    }
+                    default = Some(case.span.clone());
+                    values.push(None);
+                },
+            }
+        }
+
+        Ok(values)
+    }
+}
+
+impl StmtNode for SwitchStmtNode {
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn fill_symbol(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        for case in &self.cases {
+            for stmt in &case.stmts {
+                stmt.fill_symbol(scope)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        self.condition.check_expr_validity(scope).map_err(|e| e.with_span(self.condition.span()))?;
+        // 控制表达式只能是整数或者枚举，case的值按整数比较
+        let info = expect_type(self.condition.get_type(scope)?, &self.condition).map_err(|e| e.with_span(self.condition.span()))?;
+        let is_integer = matches!(info.base_type, Token::Char | Token::Short | Token::Int | Token::Long | Token::Enum);
+        if !is_integer || !info.nested_def.is_empty() {
+            let error = SemanticErrorType::IncompatibleTypes(String::from("int"), type_name(&info.base_type, &info.nested_def));
+            return Err(SemanticError::at(error, self.condition.span()))
+        }
+        self.case_values(scope)?;
+        for case in &self.cases {
+            for stmt in &case.stmts {
+                stmt.check_expr_validity(scope)?;
+            }
+        }
+
+        Ok(())
     }
 
-    fn check_expr_validity(&self, mut scope: &mut TopLevelScope) {
-        self.stmts.check_expr_validity(&mut scope);
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        /*
+            tmp = cond
+            cjump tmp == v1 case1 next1
+            next1: cjump tmp == v2 case2 next2
+            next2: jump default | end
+            case1: stmts
+            case2: stmts
+            end:
+            case之间没有跳转，不break就会顺序执行下去
+        */
+        let values = self.case_values(scope)?;
+        let info = match self.condition.get_type(scope)? {
+            Some(info) => info,
+            None => return Err(SemanticError::at(SemanticErrorType::UnknownType(format!("{:?}", self.condition)), self.condition.span())),
+        };
+        let tmp = ExprStmt::new(Box::new(ir.new_temp(info.typeref(), self.condition.span())));
+        let cond = self.condition.generate(scope, ir)?;
+        ir.emit(Box::new(Assign {
+            lhs: tmp.clone(),
+            rhs: cond,
+        }));
+
+        let end_label = ir.new_label();
+        let case_labels: Vec<LabelStmt> = self.cases.iter().map(|_| ir.new_label()).collect();
+        let mut default_label = end_label.clone();
+        for (value, label) in values.iter().zip(&case_labels) {
+            let value = match value {
+                Some(value) => *value,
+                None => {
+                    default_label = label.clone();
+                    continue
+                },
+            };
+            let next_label = ir.new_label();
+            ir.emit(Box::new(CJump {
+                cond: ExprStmt::new(Box::new(Bin {
+                    op: Op::EQ,
+                    left: tmp.clone(),
+                    right: ExprStmt::new(Box::new(Int { value })),
+                })),
+                then_label: label.clone(),
+                else_label: next_label.clone(),
+            }));
+            ir.emit(Box::new(next_label));
+        }
+        ir.emit(Box::new(Jump { label: default_label }));
+
+        ir.push_switch(end_label.clone());
+        for (case, label) in self.cases.iter().zip(case_labels) {
+            ir.emit(Box::new(label));
+            for stmt in &case.stmts {
+                stmt.generate(scope, ir)?;
+            }
+        }
+        ir.pop_switch();
+        ir.emit(Box::new(end_label));
+
+        Ok(())
     }
 }
 
@@ -163,24 +547,140 @@ pub struct ReturnStmtNode {
         RETURN expr
     */
     pub value: Box<dyn ExprNode>,
+    pub span: Span,
+}
+
+impl StmtNode for ReturnStmtNode {
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        self.value.check_expr_validity(scope).map_err(|e| e.with_span(self.span()))
+    }
+
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        let value = self.value.generate(scope, ir)?;
+        ir.emit(Box::new(Return {
+            expr: value.expr,
+        }));
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct GotoStmtNode {
+    /*
+        GOTO name ;
+    */
+    pub label: String,
+    pub span: Span,
+}
+
+impl StmtNode for GotoStmtNode {
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        // fill_symbol已经收集了整个函数的标签，所以可以往后跳
+        if !scope.has_label(&self.label) {
+            return Err(SemanticError::at(SemanticErrorType::UndefinedLabel(self.label.clone()), self.span()))
+        }
+
+        Ok(())
+    }
+
+    fn generate(&self, _scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        let label = ir.user_label(&self.label);
+        ir.emit(Box::new(Jump { label }));
+
+        Ok(())
+    }
 }
 
-impl StmtNode for ReturnStmtNode {}
+#[derive(Debug)]
+pub struct LabeledStmtNode {
+    /*
+        name : ( stmt | ; )
+    */
+    pub label: String,
+    pub stmt: Option<Box<dyn StmtNode>>,
+    pub span: Span,
+}
+
+impl StmtNode for LabeledStmtNode {
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn fill_symbol(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        scope.add_label(&self.label, &self.span)?;
+        if let Some(stmt) = &self.stmt {
+            stmt.fill_symbol(scope)?;
+        }
+
+        Ok(())
+    }
+
+    fn check_expr_validity(&self, scope: &mut TopLevelScope) -> Result<(), SemanticError> {
+        if let Some(stmt) = &self.stmt {
+            stmt.check_expr_validity(scope)?;
+        }
+
+        Ok(())
+    }
+
+    fn generate(&self, scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        let label = ir.user_label(&self.label);
+        ir.emit(Box::new(label));
+        if let Some(stmt) = &self.stmt {
+            stmt.generate(scope, ir)?;
+        }
+
+        Ok(())
+    }
+}
 
 #[derive(Debug)]
 pub struct BreakStmtNode {
     /*
         BREAK ;
     */
+    pub span: Span,
 }
 
-impl StmtNode for BreakStmtNode {}
+impl StmtNode for BreakStmtNode {
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn generate(&self, _scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        let label = ir.break_label().map_err(|e| e.with_span(self.span()))?;
+        ir.emit(Box::new(Jump { label }));
+
+        Ok(())
+    }
+}
 
 #[derive(Debug)]
 pub struct ContinueStmtNode {
     /*
         CONTINUE ;
     */
+    pub span: Span,
 }
 
-impl StmtNode for ContinueStmtNode {}
+impl StmtNode for ContinueStmtNode {
+    fn span(&self) -> Span {
+        return self.span.clone()
+    }
+
+    fn generate(&self, _scope: &mut TopLevelScope, ir: &mut IR) -> Result<(), SemanticError> {
+        let label = ir.continue_label().map_err(|e| e.with_span(self.span()))?;
+        ir.emit(Box::new(Jump { label }));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,412 @@
+use lex::error:: {
+    LexicalError,
+    PreprocessError,
+};
+use lex::preprocessor:: {
+    SourceLine,
+    SourceMap,
+};
+use lex::location:: {
+    Location,
+    Span,
+    DEFAULT_TAB_WIDTH,
+};
+use crate::error:: {
+    ParseError,
+    SemanticError,
+    SemanticErrorType,
+};
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    // 主标签用^标出，次要标签用-标出
+    pub primary: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /*
+        error[code]: message
+          --> file:line:column
+           |
+        line | source
+           | ^^^^ label
+    */
+    pub code: String,
+    pub message: String,
+    pub labels: Vec<Label>,
+    // 标签所在的文件和那一行的代码，预处理的错误可能在头文件里，不在传给render的文本里
+    pub origin: Option<SourceLine>,
+    pub origin_text: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &str, message: String) -> Self {
+        Diagnostic {
+            code: code.to_string(),
+            message,
+            labels: Vec::new(),
+            origin: None,
+            origin_text: None,
+        }
+    }
+
+    pub fn with_label(mut self, span: Span, message: &str) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.to_string(),
+            primary: true,
+        });
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, message: &str) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.to_string(),
+            primary: false,
+        });
+        self
+    }
+
+    /**
+     *  从出错的位置标到行尾，没有列号时从这一行第一个不是空白的字符开始
+     *  tab_width要和预处理时用的一致
+     */
+    pub fn from_preprocess(err: &PreprocessError, tab_width: usize) -> Self {
+        let column = err.column.unwrap_or_else(|| {
+            err.text.chars().take_while(|c| c.is_whitespace())
+                .fold(Location::new(err.line, 1), |location, c| location.advance(c, tab_width))
+                .column
+        });
+        let span = Span::new(Location::new(err.line, column), Location::new(err.line + 1, 1));
+        let mut diagnostic = Diagnostic::error(err.error.code(), err.error.to_string()).with_label(span, "");
+        diagnostic.origin = Some(SourceLine {
+            file: err.file.clone(),
+            line: err.line,
+        });
+        diagnostic.origin_text = Some(err.text.clone());
+        diagnostic
+    }
+
+    /**
+     *  按rustc的格式输出，color为true时加上ANSI颜色
+     */
+    pub fn render(&self, filename: &str, source: &str, color: bool) -> String {
+        return self.render_with_tab_width(filename, source, color, DEFAULT_TAB_WIDTH)
+    }
+
+    /**
+     *  tab_width要和词法分析时用的一致，这样列号和下划线才能对上
+     */
+    pub fn render_with_tab_width(&self, filename: &str, source: &str, color: bool, tab_width: usize) -> String {
+        let lines: Vec<&str> = source.lines().collect();
+        let locate = |span: &Span| {
+            let line = SourceLine {
+                file: filename.to_string(),
+                line: span.start.row,
+            };
+            let text = lines.get(span.start.row - 1).copied().unwrap_or("");
+            (line, span.clone(), text.to_string())
+        };
+        return self.render_lines(filename, color, tab_width, &locate)
+    }
+
+    /**
+     *  source是预处理之后的文本，位置通过source map换回原始文件，显示的也是展开之前的代码
+     *  tab宽度用预处理时记在source map里的
+     */
+    pub fn render_mapped(&self, filename: &str, source: &str, map: &SourceMap, color: bool) -> String {
+        let lines: Vec<&str> = source.lines().collect();
+        let locate = |span: &Span| {
+            let expanded = lines.get(span.start.row - 1).copied().unwrap_or("");
+            match map.map_span(span) {
+                Some((line, mapped)) => {
+                    let text = map.source_line(&line).unwrap_or(expanded).to_string();
+                    (line, mapped, text)
+                },
+                None => {
+                    let line = SourceLine {
+                        file: filename.to_string(),
+                        line: span.start.row,
+                    };
+                    (line, span.clone(), expanded.to_string())
+                },
+            }
+        };
+        return self.render_lines(filename, color, map.tab_width(), &locate)
+    }
+
+    /**
+     *  locate返回标签在原始文件里的行、位置和那一行的代码
+     */
+    fn render_lines(
+        &self,
+        filename: &str,
+        color: bool,
+        tab_width: usize,
+        locate: &dyn Fn(&Span) -> (SourceLine, Span, String),
+    ) -> String {
+        let paint = |style: &str, text: &str| -> String {
+            if color {
+                format!("{}{}{}", style, text, RESET)
+            } else {
+                text.to_string()
+            }
+        };
+
+        let mut out = format!("{}{}\n",
+            paint(RED, &format!("error[{}]", self.code)),
+            paint(BOLD, &format!(": {}", self.message)));
+        let locate = |span: &Span| match (&self.origin, &self.origin_text) {
+            (Some(origin), Some(text)) => (origin.clone(), span.clone(), text.clone()),
+            _ => locate(span),
+        };
+
+        let mut labels: Vec<&Label> = self.labels.iter().collect();
+        labels.sort_by_key(|label| (label.span.start.row, label.span.start.column));
+        let width = labels.iter().map(|label| locate(&label.span).0.line.to_string().len()).max().unwrap_or(0);
+        let gutter = " ".repeat(width);
+
+        // 有主标签时指向主标签，否则指向第一个标签
+        let head = self.labels.iter().find(|label| label.primary).or_else(|| labels.first().copied());
+        let head = match head {
+            Some(label) => label,
+            None => {
+                // 不知道位置的错误只指出文件
+                out.push_str(&format!("{} {}\n", paint(BLUE, "-->"), filename));
+                return out
+            },
+        };
+        let (origin, span, _) = locate(&head.span);
+        out.push_str(&format!("{}{} {}:{}:{}\n", gutter, paint(BLUE, "-->"), origin.file, origin.line, span.start.column));
+        out.push_str(&format!("{} {}\n", gutter, paint(BLUE, "|")));
+
+        let mut last_line: Option<SourceLine> = None;
+        for label in labels {
+            let (mapped, span, text) = locate(&label.span);
+            let line = expand_tabs(&text, tab_width);
+            if last_line.as_ref() != Some(&mapped) {
+                match &last_line {
+                    // 标签来自别的文件时先标出文件名
+                    Some(last) if last.file != mapped.file => {
+                        out.push_str(&format!("{}{} {}:{}:{}\n", gutter, paint(BLUE, ":::"), mapped.file, mapped.line, span.start.column));
+                    },
+                    Some(last) if mapped.line > last.line + 1 => {
+                        out.push_str(&format!("{}\n", paint(BLUE, "...")));
+                    },
+                    _ => {},
+                }
+                out.push_str(&format!("{} {} {}\n", paint(BLUE, &format!("{:>width$}", mapped.line, width = width)), paint(BLUE, "|"), line));
+                last_line = Some(mapped);
+            }
+
+            let (mark, style) = if label.primary { ("^", RED) } else { ("-", BLUE) };
+            let underline = format!("{} {}", mark.repeat(underline_width(&span, &line)), label.message);
+            out.push_str(&format!("{} {} {}{}\n",
+                gutter,
+                paint(BLUE, "|"),
+                " ".repeat(span.start.column - 1),
+                paint(style, underline.trim_end())));
+        }
+
+        return out
+    }
+}
+
+/**
+ *  把tab展开成空格，展开后每个字符的位置和Location的列号一致
+ */
+fn expand_tabs(line: &str, tab_width: usize) -> String {
+    let mut expanded = String::new();
+    let mut location = Location::new(1, 1);
+    for ch in line.chars() {
+        let next = location.advance(ch, tab_width);
+        if ch == '\t' {
+            expanded.push_str(&" ".repeat(next.column - location.column));
+        } else {
+            expanded.push(ch);
+        }
+        location = next;
+    }
+
+    expanded
+}
+
+fn underline_width(span: &Span, line: &str) -> usize {
+    let start = span.start.column;
+    let end = if span.end.row == span.start.row {
+        span.end.column
+    } else {
+        // 跨行的span只标到当前行的末尾
+        line.chars().count() + 1
+    };
+
+    if end > start { end - start } else { 1 }
+}
+
+impl From<&LexicalError> for Diagnostic {
+    fn from(err: &LexicalError) -> Self {
+        let span = Span::new(err.location.clone(), err.location.clone());
+        Diagnostic::error(err.error.code(), err.error.to_string()).with_label(span, "")
+    }
+}
+
+impl From<&PreprocessError> for Diagnostic {
+    fn from(err: &PreprocessError) -> Self {
+        return Diagnostic::from_preprocess(err, DEFAULT_TAB_WIDTH)
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(err: &ParseError) -> Self {
+        let span = Span::new(err.location.clone(), err.location.clone());
+        Diagnostic::error(err.error.code(), err.error.to_string()).with_label(span, "")
+    }
+}
+
+impl From<&SemanticError> for Diagnostic {
+    fn from(err: &SemanticError) -> Self {
+        let diagnostic = Diagnostic::error(err.error.code(), err.error.to_string());
+        let span = match &err.span {
+            Some(span) => span.clone(),
+            None => return diagnostic,
+        };
+
+        match &err.error {
+            SemanticErrorType::Redefinition(_, prev) => {
+                diagnostic.with_secondary(prev.clone(), "first defined here")
+                    .with_label(span, "redefined here")
+            },
+            SemanticErrorType::ConflictingTypes(_, prev) => {
+                diagnostic.with_secondary(prev.clone(), "previous declaration here")
+                    .with_label(span, "conflicting declaration")
+            },
+            SemanticErrorType::DuplicateCase(_, prev) | SemanticErrorType::DuplicateDefault(prev) => {
+                diagnostic.with_secondary(prev.clone(), "previous label here")
+                    .with_label(span, "duplicate label")
+            },
+            _ => diagnostic.with_label(span, ""),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use lex::lexer::Lexer;
+    use lex::preprocessor::Preprocessor;
+
+    #[test]
+    fn test_render_parse_error() {
+        let source = "int main() {\n    int a = 1\n}";
+        let err = parse(&mut Lexer::new(String::from(source))).unwrap_err();
+        let text = Diagnostic::from(&err).render("test.c", source, false);
+        assert_eq!("\
+error[E0001]: unexcept token! Token: <RBrace>, expect Token: <Semi>
+ --> test.c:3:1
+  |
+3 | }
+  | ^
+", text);
+    }
+
+    #[test]
+    fn test_render_multiple_labels() {
+        let source = "int a;\nint b;\nint main() {\n  int b;\n}\nint a;";
+        let prev = Span::new(Location::new(1, 1), Location::new(1, 7));
+        let span = Span::new(Location::new(6, 1), Location::new(6, 7));
+        let err = SemanticError::at(SemanticErrorType::Redefinition(String::from("a"), prev), span);
+        let text = Diagnostic::from(&err).render("test.c", source, false);
+        assert_eq!("\
+error[E0213]: redefinition of \"a\"
+ --> test.c:6:1
+  |
+1 | int a;
+  | ------ first defined here
+...
+6 | int a;
+  | ^^^^^^ redefined here
+", text);
+    }
+
+    #[test]
+    fn test_render_color() {
+        let err = SemanticError::new(SemanticErrorType::BreakOutsideLoop);
+        let text = Diagnostic::from(&err).render("test.c", "", true);
+        assert!(text.starts_with("\x1b[1;31merror[E0214]\x1b[0m"));
+        assert!(text.ends_with("test.c\n"));
+    }
+
+    #[test]
+    fn test_render_tab() {
+        let source = "int main() {\n\tb = 1;\n}";
+        let span = Span::new(Location::new(2, 9), Location::new(2, 10));
+        let err = SemanticError::at(SemanticErrorType::UndefinedSymbol(String::from("b")), span);
+        let text = Diagnostic::from(&err).render("test.c", source, false);
+        assert!(text.ends_with("2 |         b = 1;\n  |         ^\n"));
+    }
+
+    #[test]
+    fn test_render_mapped() {
+        let mut preprocessor = Preprocessor::new();
+        let source = "#define ONE 1\n\nint main() {\n    b = ONE;\n}\n";
+        let (text, map) = preprocessor.preprocess("test.c", source).unwrap();
+        let span = Span::new(Location::new(4, 5), Location::new(4, 6));
+        let err = SemanticError::at(SemanticErrorType::UndefinedSymbol(String::from("b")), span);
+        let rendered = Diagnostic::from(&err).render_mapped("test.c", &text, &map, false);
+        assert!(rendered.contains(" --> test.c:4:5\n"));
+        assert!(rendered.ends_with("4 |     b = ONE;\n  |     ^\n"));
+
+        // 块注释不会把后面的行拼上来，宏展开之后的列号换回原来的位置
+        let source = "#define TWICE(x) ((x) + (x))\nint main() {\n    int x = 1; /* a\n b */ x = TWICE(x) + qq;\n}\n";
+        let (text, map) = preprocessor.preprocess("test.c", source).unwrap();
+        let column = text.lines().nth(3).unwrap().find("qq").unwrap() + 1;
+        assert_eq!(25, column);
+        let span = Span::new(Location::new(4, column), Location::new(4, column + 2));
+        let err = SemanticError::at(SemanticErrorType::UndefinedSymbol(String::from("qq")), span);
+        assert_eq!("\
+error[E0201]: Can't find the symbol \"qq\"
+ --> test.c:4:22
+  |
+4 |  b */ x = TWICE(x) + qq;
+  |                      ^^
+", Diagnostic::from(&err).render_mapped("test.c", &text, &map, false));
+
+        let span = Span::new(Location::new(4, 11), Location::new(4, 22));
+        let err = SemanticError::at(SemanticErrorType::NotConstant, span);
+        assert!(Diagnostic::from(&err).render_mapped("test.c", &text, &map, false).ends_with("\
+4 |  b */ x = TWICE(x) + qq;
+  |           ^^^^^^^^
+"));
+
+        // 预处理的错误也显示出错的代码，头文件里的错误显示头文件里的那一行
+        let err = preprocessor.preprocess("test.c", "\n  #if 1\n").unwrap_err();
+        let rendered = Diagnostic::from(&err).render("test.c", "", false);
+        assert_eq!("\
+error[E0303]: Unterminated conditional directive
+ --> test.c:2:3
+  |
+2 |   #if 1
+  |   ^^^^^
+", rendered);
+
+        let err = preprocessor.preprocess("test.c", "int a;\nint b; /* b").unwrap_err();
+        let rendered = Diagnostic::from(&err).render("main.c", "int main;", false);
+        assert_eq!("\
+error[E0304]: The block comment is not closed
+ --> test.c:2:8
+  |
+2 | int b; /* b
+  |        ^^^^
+", rendered);
+    }
+}
@@ -0,0 +1,234 @@
+use lex::error:: {
+    LexicalError,
+    LexicalErrorType,
+};
+use lex::lexer::Lexer;
+use lex::location:: {
+    Location,
+    Span,
+};
+use lex::token::Token;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub location: Location,
+    pub error: ParseErrorType,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseErrorType {
+    Lexical(LexicalErrorType),
+    UnexpectToken(Token),
+}
+
+impl ParseError {
+    pub fn unexpect(lexer: &Lexer, token: Token) -> Self {
+        ParseError {
+            location: lexer.location(),
+            error: ParseErrorType::UnexpectToken(token),
+        }
+    }
+}
+
+impl ParseErrorType {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseErrorType::Lexical(error) => error.code(),
+            ParseErrorType::UnexpectToken(_) => "E0101",
+        }
+    }
+}
+
+impl From<LexicalError> for ParseError {
+    fn from(err: LexicalError) -> Self {
+        ParseError {
+            location: err.location,
+            error: ParseErrorType::Lexical(err.error),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} in {}", self.error, self.location)
+    }
+}
+
+impl fmt::Display for ParseErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorType::Lexical(error) => write!(f, "{}", error),
+            ParseErrorType::UnexpectToken(token) => write!(f, "unexcept token! {}", token),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SemanticError {
+    pub error: SemanticErrorType,
+    pub span: Option<Span>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SemanticErrorType {
+    UndefinedSymbol(String),
+    UndefinedStruct(String),
+    UndefinedFunction(String),
+    IncompatibleTypes(String, String),
+    UnknownType(String),
+    NotLeftValue(String),
+    NotArray(String),
+    NotIndexable(String),
+    NotStruct(String),
+    NoMember(String, String),
+    WrongAccessOp(String, Token),
+    RecursiveDefinition(String),
+    // 第二个span是第一次定义的位置
+    Redefinition(String, Span),
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+    // 第二个span是前一个相同标签的位置
+    DuplicateCase(i64, Span),
+    DuplicateDefault(Span),
+    NotConstant,
+    UndefinedLabel(String),
+    NotFunction(String),
+    // 函数名，参数个数，实际传的参数个数
+    ArgumentCount(String, usize, usize),
+    UndefinedEnum(String),
+    InvalidArraySize(i64),
+    AssignToConst(String),
+    ConflictingTypes(String, Span),
+    TooFewArguments(String, usize, usize),
+    ExcessElements,
+    InvalidDesignator(String),
+    UnexpectedInitList,
+    // 第二个span是标签定义的位置
+    TagMismatch(String, Span),
+    EnumOverflow(String),
+    TypeTooLarge,
+}
+
+impl SemanticError {
+    pub fn new(error: SemanticErrorType) -> Self {
+        SemanticError {
+            error,
+            span: None,
+        }
+    }
+
+    pub fn at(error: SemanticErrorType, span: Span) -> Self {
+        SemanticError {
+            error,
+            span: Some(span),
+        }
+    }
+
+    /**
+     *  内层已经定位到更精确的位置时保留原来的span
+     */
+    pub fn with_span(mut self, span: Span) -> Self {
+        if self.span.is_none() {
+            self.span = Some(span);
+        }
+        self
+    }
+}
+
+impl SemanticErrorType {
+    pub fn code(&self) -> &'static str {
+        match self {
+            SemanticErrorType::UndefinedSymbol(_) => "E0201",
+            SemanticErrorType::UndefinedStruct(_) => "E0202",
+            SemanticErrorType::UndefinedFunction(_) => "E0203",
+            SemanticErrorType::IncompatibleTypes(_, _) => "E0204",
+            SemanticErrorType::UnknownType(_) => "E0205",
+            SemanticErrorType::NotLeftValue(_) => "E0206",
+            SemanticErrorType::NotArray(_) => "E0207",
+            SemanticErrorType::NotIndexable(_) => "E0208",
+            SemanticErrorType::NotStruct(_) => "E0209",
+            SemanticErrorType::NoMember(_, _) => "E0210",
+            SemanticErrorType::WrongAccessOp(_, _) => "E0211",
+            SemanticErrorType::RecursiveDefinition(_) => "E0212",
+            SemanticErrorType::Redefinition(_, _) => "E0213",
+            SemanticErrorType::BreakOutsideLoop => "E0214",
+            SemanticErrorType::ContinueOutsideLoop => "E0215",
+            SemanticErrorType::DuplicateCase(_, _) => "E0216",
+            SemanticErrorType::DuplicateDefault(_) => "E0217",
+            SemanticErrorType::NotConstant => "E0218",
+            SemanticErrorType::UndefinedLabel(_) => "E0219",
+            SemanticErrorType::NotFunction(_) => "E0220",
+            SemanticErrorType::ArgumentCount(_, _, _) => "E0221",
+            SemanticErrorType::UndefinedEnum(_) => "E0222",
+            SemanticErrorType::InvalidArraySize(_) => "E0223",
+            SemanticErrorType::AssignToConst(_) => "E0224",
+            SemanticErrorType::ConflictingTypes(_, _) => "E0225",
+            SemanticErrorType::TooFewArguments(_, _, _) => "E0226",
+            SemanticErrorType::ExcessElements => "E0227",
+            SemanticErrorType::InvalidDesignator(_) => "E0228",
+            SemanticErrorType::UnexpectedInitList => "E0229",
+            SemanticErrorType::TagMismatch(_, _) => "E0230",
+            SemanticErrorType::EnumOverflow(_) => "E0231",
+            SemanticErrorType::TypeTooLarge => "E0232",
+        }
+    }
+}
+
+impl fmt::Display for SemanticError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        if let Some(span) = &self.span {
+            write!(f, " in {}", span)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SemanticErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SemanticErrorType::UndefinedSymbol(name) => write!(f, "Can't find the symbol \"{}\"", name),
+            SemanticErrorType::UndefinedStruct(name) => write!(f, "Undefined struct \"{}\"", name),
+            SemanticErrorType::UndefinedFunction(name) => write!(f, "Undefined function \"{}\"", name),
+            SemanticErrorType::IncompatibleTypes(l, r) => write!(f, "{} and {} types are incompatible", l, r),
+            SemanticErrorType::UnknownType(expr) => write!(f, "Can't infer the type of {}", expr),
+            SemanticErrorType::NotLeftValue(expr) => write!(f, "Type error! {}, Expect an left value", expr),
+            SemanticErrorType::NotArray(name) => write!(f, "The identifier \"{}\" is not an array or a pointer", name),
+            SemanticErrorType::NotIndexable(name) => write!(f, "\"{}\" Type! Cannot be referenced as an array", name),
+            SemanticErrorType::NotStruct(name) => write!(f, "Type error! The identifier \"{}\" is not a struct", name),
+            SemanticErrorType::NoMember(name, member) => write!(f, "{} has no members of \"{}\"", name, member),
+            SemanticErrorType::WrongAccessOp(member, Token::Dot) => {
+                write!(f, "Members of the \"{}\" should probably access through .", member)
+            },
+            SemanticErrorType::WrongAccessOp(member, _) => {
+                write!(f, "Members of the \"{}\" should probably access through ->", member)
+            },
+            SemanticErrorType::RecursiveDefinition(name) => write!(f, "\"{}\" recursive definition", name),
+            SemanticErrorType::Redefinition(name, _) => write!(f, "redefinition of \"{}\"", name),
+            SemanticErrorType::BreakOutsideLoop => write!(f, "break statement not within loop or switch"),
+            SemanticErrorType::ContinueOutsideLoop => write!(f, "continue statement not within loop"),
+            SemanticErrorType::DuplicateCase(value, _) => write!(f, "duplicate case value {}", value),
+            SemanticErrorType::DuplicateDefault(_) => write!(f, "multiple default labels in one switch"),
+            SemanticErrorType::NotConstant => write!(f, "expression is not an integer constant"),
+            SemanticErrorType::UndefinedLabel(name) => write!(f, "use of undeclared label \"{}\"", name),
+            SemanticErrorType::NotFunction(name) => write!(f, "called object \"{}\" is not a function or function pointer", name),
+            SemanticErrorType::ArgumentCount(name, expect, found) => {
+                write!(f, "function \"{}\" takes {} arguments but {} were given", name, expect, found)
+            },
+            SemanticErrorType::UndefinedEnum(name) => write!(f, "Undefined enum \"{}\"", name),
+            SemanticErrorType::InvalidArraySize(size) => write!(f, "array size {} is not positive", size),
+            SemanticErrorType::AssignToConst(name) => write!(f, "cannot assign to \"{}\" with const-qualified type", name),
+            SemanticErrorType::ConflictingTypes(name, _) => write!(f, "conflicting types for \"{}\"", name),
+            SemanticErrorType::TooFewArguments(name, expect, found) => {
+                write!(f, "function \"{}\" takes at least {} arguments but {} were given", name, expect, found)
+            },
+            SemanticErrorType::ExcessElements => write!(f, "excess elements in initializer"),
+            SemanticErrorType::InvalidDesignator(designator) => write!(f, "invalid designator \"{}\" in initializer", designator),
+            SemanticErrorType::UnexpectedInitList => write!(f, "initializer list can only be used to initialize a variable"),
+            SemanticErrorType::TagMismatch(name, _) => write!(f, "use of \"{}\" with tag type that does not match its definition", name),
+            SemanticErrorType::EnumOverflow(name) => write!(f, "overflow in enumeration value \"{}\"", name),
+            SemanticErrorType::TypeTooLarge => write!(f, "size of type is too large"),
+        }
+    }
+}
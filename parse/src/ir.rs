@@ -0,0 +1,381 @@
+use crate::ast:: {
+    DefVarNode,
+    DefFuncNode,
+    TypeNode,
+};
+use crate::error:: {
+    SemanticError,
+    SemanticErrorType,
+};
+use lex::token::Token;
+use lex::location::Span;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::fmt;
+
+pub trait IRNode:fmt::Debug {
+
+}
+
+#[derive(Clone, Debug)]
+pub struct IR {
+    pub variables: Vec<DefVarNode>,
+    // 全局变量名到初始值的映射
+    pub data: HashMap<String, Vec<Data>>,
+    pub functions: Vec<Func>,
+    label_seq: usize,
+    temp_seq: usize,
+    static_seq: usize,
+    in_func: bool,
+    break_labels: Vec<LabelStmt>,
+    continue_labels: Vec<LabelStmt>,
+}
+
+impl Default for IR {
+    fn default() -> Self {
+        return IR::new()
+    }
+}
+
+impl IR {
+    pub fn new() -> Self {
+        IR {
+            variables: Vec::new(),
+            data: HashMap::new(),
+            functions: Vec::new(),
+            label_seq: 0,
+            temp_seq: 0,
+            static_seq: 0,
+            in_func: false,
+            break_labels: Vec::new(),
+            continue_labels: Vec::new(),
+        }
+    }
+
+    pub fn begin_func(&mut self, func: Func) {
+        self.functions.push(func);
+        self.in_func = true;
+    }
+
+    pub fn end_func(&mut self) {
+        self.in_func = false;
+    }
+
+    pub fn in_func(&self) -> bool {
+        return self.in_func
+    }
+
+    /**
+     *  把语句追加到当前正在生成的函数体里
+     */
+    pub fn emit(&mut self, stmt: Box<dyn IRNode>) {
+        let len = self.functions.len();
+        self.functions[len - 1].body.push(Rc::new(stmt));
+    }
+
+    pub fn new_label(&mut self) -> LabelStmt {
+        self.label_seq += 1;
+        LabelStmt {
+            label: format!(".L{}", self.label_seq),
+        }
+    }
+
+    /**
+     *  表达式求值时用到的临时变量，名字以.开头不会和源码里的变量冲突
+     */
+    pub fn new_temp(&mut self, typeref: TypeNode, span: Span) -> Var {
+        self.temp_seq += 1;
+        let name = format!(".T{}", self.temp_seq);
+        Var {
+            name: name.clone(),
+            expr: DefVarNode {
+                typeref,
+                declarators: vec![(name, None)],
+                span,
+            },
+        }
+    }
+
+    /**
+     *  源码里的标签只在函数内有效，加上函数名避免和别的函数重名
+     */
+    pub fn user_label(&self, name: &str) -> LabelStmt {
+        let func = &self.functions[self.functions.len() - 1];
+        LabelStmt {
+            label: format!(".L{}.{}", func.name, name),
+        }
+    }
+
+    /**
+     *  函数里的static变量放到全局变量里时的名字
+     *  不同块里可以声明同名的static变量，每个声明加上序号区分
+     */
+    pub fn static_name(&mut self, name: &str) -> String {
+        self.static_seq += 1;
+        let func = &self.functions[self.functions.len() - 1];
+        return format!("{}.{}.{}", func.name, name, self.static_seq)
+    }
+
+    pub fn push_loop(&mut self, break_label: LabelStmt, continue_label: LabelStmt) {
+        self.break_labels.push(break_label);
+        self.continue_labels.push(continue_label);
+    }
+
+    pub fn pop_loop(&mut self) {
+        self.break_labels.pop();
+        self.continue_labels.pop();
+    }
+
+    /**
+     *  switch里只能break，continue还是跳到外层的循环
+     */
+    pub fn push_switch(&mut self, break_label: LabelStmt) {
+        self.break_labels.push(break_label);
+    }
+
+    pub fn pop_switch(&mut self) {
+        self.break_labels.pop();
+    }
+
+    pub fn break_label(&self) -> Result<LabelStmt, SemanticError> {
+        match self.break_labels.last() {
+            Some(label) => Ok(label.clone()),
+            None => Err(SemanticError::new(SemanticErrorType::BreakOutsideLoop)),
+        }
+    }
+
+    pub fn continue_label(&self) -> Result<LabelStmt, SemanticError> {
+        match self.continue_labels.last() {
+            Some(label) => Ok(label.clone()),
+            None => Err(SemanticError::new(SemanticErrorType::ContinueOutsideLoop)),
+        }
+    }
+}
+
+impl IRNode for IR {}
+
+#[derive(Clone, Debug)]
+pub struct Func {
+    pub origin: DefFuncNode,
+    pub name: String,
+    pub return_type: TypeNode,
+    pub body: Vec<Rc<Box<dyn IRNode>>>,
+}
+
+impl IRNode for Func {}
+
+/**
+ *  全局变量里从offset开始的size个字节的初始值，没有记录的地方是0
+ */
+#[derive(Clone, Debug)]
+pub struct Data {
+    pub offset: usize,
+    pub size: usize,
+    pub value: ExprStmt,
+}
+
+#[derive(Clone, Debug)]
+pub struct Assign {
+    pub lhs: ExprStmt,
+    pub rhs: ExprStmt,
+}
+
+impl IRNode for Assign {}
+
+#[derive(Clone, Debug)]
+pub struct CJump {
+    pub cond: ExprStmt,
+    pub then_label: LabelStmt,
+    pub else_label: LabelStmt,
+}
+
+impl IRNode for CJump {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Jump {
+    pub label: LabelStmt,
+}
+
+impl IRNode for Jump {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LabelStmt {
+    pub label: String,
+}
+
+impl IRNode for LabelStmt {}
+
+#[derive(Clone, Debug)]
+pub struct ExprStmt {
+    pub expr: Rc<Box<dyn IRNode>>,
+}
+
+impl ExprStmt {
+    pub fn new(expr: Box<dyn IRNode>) -> Self {
+        ExprStmt {
+            expr: Rc::new(expr),
+        }
+    }
+}
+
+impl IRNode for ExprStmt {}
+
+#[derive(Clone, Debug)]
+pub struct Return {
+    pub expr: Rc<Box<dyn IRNode>>,
+}
+
+impl IRNode for Return {}
+
+#[derive(Clone, Debug)]
+pub struct Uni {
+    pub op: Op,
+    pub expr: ExprStmt,
+}
+
+impl IRNode for Uni {}
+
+#[derive(Clone, Debug)]
+pub struct Bin {
+    pub op: Op,
+    pub left: ExprStmt,
+    pub right: ExprStmt,
+}
+
+impl IRNode for Bin {}
+
+#[derive(Clone, Debug)]
+pub struct Call {
+    pub expr: ExprStmt,
+    pub args: Vec<ExprStmt>,
+    // 调用可变参数函数时用到的向量寄存器个数，按System V的约定后端要把它放到%al里
+    pub varargs: Option<usize>,
+}
+
+impl IRNode for Call {}
+
+#[derive(Clone, Debug)]
+pub struct Addr {
+    pub expr: ExprStmt,
+}
+
+impl IRNode for Addr {}
+
+#[derive(Clone, Debug)]
+pub struct Mem {
+    pub expr: ExprStmt,
+}
+
+impl IRNode for Mem {}
+
+#[derive(Clone, Debug)]
+pub struct Var {
+    pub name: String,
+    pub expr: DefVarNode,
+}
+
+impl IRNode for Var {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FuncAddr {
+    pub name: String,
+}
+
+impl IRNode for FuncAddr {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Int {
+    pub value: i64,
+}
+
+impl IRNode for Int {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Float {
+    pub value: f64,
+}
+
+impl IRNode for Float {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Str {
+    pub value: String,
+}
+
+impl IRNode for Str {}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Op {
+    ADD,
+    SUB,
+    MUL,
+    DIV,
+    MOD,
+    BIT_AND,
+    BIT_OR,
+    BIT_XOR,
+    BIT_LSHIFT,
+    BIT_RSHIFT,
+    EQ,
+    NEQ,
+    GT,
+    GTEQ,
+    LT,
+    LTEQ,
+    LOGIC_AND,
+    LOGIC_OR,
+    NOT,
+    CAST,
+    // 无符号数的除法、取模、右移和比较
+    U_DIV,
+    U_MOD,
+    U_BIT_RSHIFT,
+    U_GT,
+    U_GTEQ,
+    U_LT,
+    U_LTEQ,
+}
+
+impl Op {
+    pub fn from_token(token: &Token) -> Op {
+        match token {
+            Token::Add => Op::ADD,
+            Token::Sub => Op::SUB,
+            Token::Mul => Op::MUL,
+            Token::Div => Op::DIV,
+            Token::Mod => Op::MOD,
+            Token::Bitand => Op::BIT_AND,
+            Token::Bitor => Op::BIT_OR,
+            Token::Bitxor => Op::BIT_XOR,
+            Token::Leftshift => Op::BIT_LSHIFT,
+            Token::Rightshift => Op::BIT_RSHIFT,
+            Token::Equal => Op::EQ,
+            Token::Notequal => Op::NEQ,
+            Token::Greater => Op::GT,
+            Token::Greaterequal => Op::GTEQ,
+            Token::Less => Op::LT,
+            Token::Lessequal => Op::LTEQ,
+            Token::And => Op::LOGIC_AND,
+            Token::Or => Op::LOGIC_OR,
+            Token::Not => Op::NOT,
+            _ => panic!("{} is not an operator", token),
+        }
+    }
+
+    /**
+     *  其他运算有没有符号结果都一样
+     */
+    pub fn to_unsigned(self) -> Op {
+        match self {
+            Op::DIV => Op::U_DIV,
+            Op::MOD => Op::U_MOD,
+            Op::BIT_RSHIFT => Op::U_BIT_RSHIFT,
+            Op::GT => Op::U_GT,
+            Op::GTEQ => Op::U_GTEQ,
+            Op::LT => Op::U_LT,
+            Op::LTEQ => Op::U_LTEQ,
+            op => op,
+        }
+    }
+}
@@ -1,6 +1,12 @@
+// 项目里的函数统一用显式的return返回值
+#![allow(clippy::needless_return)]
+
 pub mod parser;
 pub mod ast;
 pub mod symbol_table;
+pub mod ir;
+pub mod error;
+pub mod diagnostic;
 mod ast_expr;
 mod ast_stmt;
 mod parse_def;
@@ -1,7 +1,11 @@
 use lex::lexer::Lexer;
+use lex::location::Location;
+use crate::error::ParseError;
 use lex::token:: {
     Token,
     is_base_type,
+    is_qualifier,
+    is_aggregate,
 };
 use crate::ast:: {
     ImportStmtNode,
@@ -11,306 +15,638 @@ use crate::ast:: {
     TypeNode,
     TypeDef,
     TypeBase,
+    FuncType,
+    Qualifier,
     DefFuncNode,
     ParamsNode,
     DefVarNode,
+    Declarator,
+    DefTypedefNode,
+    DefEnumNode,
+    EnumMemberNode,
+    ConstExpr,
     DefNode,
 };
-use crate::parse_expr::expr0;
-use std::collections::HashMap;
+use crate::parse_expr:: {
+    expr0,
+    init_list,
+};
 use std::rc::Rc;
-use crate::ast_expr::ExprNode;
 use crate::parse_stmt::block;
 
-pub fn import_statements(mut lexer: &mut Lexer) -> Vec<ImportStmtNode> {
+pub fn import_statements(lexer: &mut Lexer) -> Result<Vec<ImportStmtNode>, ParseError> {
     // import_stmt *
     let mut nodes = Vec::new();
-    while lexer.lookahead(1) == Token::Import {
-        lexer.advance();
-        nodes.push(import_statement(&mut lexer));
+    while lexer.lookahead(1)?.token == Token::Import {
+        lexer.advance()?;
+        nodes.push(import_statement(lexer)?);
     }
 
-    return nodes
+    return Ok(nodes)
 }
 
-fn import_statement(mut lexer: &mut Lexer) -> ImportStmtNode {
+fn import_statement(lexer: &mut Lexer) -> Result<ImportStmtNode, ParseError> {
     // IMPORT NAME (. NAME)* 
     let mut paths = Vec::new();
     loop {
-        let t = lexer.lookahead(1);
+        let t = lexer.lookahead(1)?.token;
         match t {
             Token::Name(s) => {
-                lexer.advance();
+                lexer.advance()?;
                 paths.push(s);
             },
             Token::Dot => { 
-                lexer.advance();
+                lexer.advance()?;
             },
             _ => break,
         }
     }
 
-    lexer.matcher(Token::Semi);
+    lexer.matcher(Token::Semi)?;
 
-    return ImportStmtNode {
+    return Ok(ImportStmtNode {
         paths,
-    }
+    })
 }
 
-pub fn top_defs(mut lexer: &mut Lexer) -> TopDefNode {
+pub fn top_defs(lexer: &mut Lexer) -> Result<TopDefNode, ParseError> {
     let mut var_defs: Vec<Rc<Box<dyn DefNode>>> = Vec::new();
     let mut func_defs: Vec<Rc<Box<dyn DefNode>>> = Vec::new();
 
     loop {
-        let t = lexer.lookahead(1);
-        println!(" ========== loop times {}", t);
-        if t == Token::Struct {
-            var_defs.push(Rc::new(Box::new(defstruct(&mut lexer))));
-        } else if is_base_type(&t) {
-            let typeref = typeref(&mut lexer);
-            if lexer.lookahead(2) == Token::LParentheses {
-                func_defs.push(Rc::new(Box::new(deffunc(&mut lexer, typeref))));
+        let t = lexer.lookahead(1)?.token;
+        if (t == Token::Struct || t == Token::Union) && lexer.lookahead(3)?.token == Token::LBrace {
+            var_defs.push(Rc::new(Box::new(defstruct(lexer)?)));
+        } else if t == Token::Enum && is_enum_def(lexer)? {
+            var_defs.push(Rc::new(Box::new(defenum(lexer)?)));
+        } else if t == Token::Typedef {
+            var_defs.push(Rc::new(Box::new(deftypedef(lexer)?)));
+        } else if is_base_type(&t) || is_qualifier(&t) {
+            let start = lexer.next_location()?;
+            let typeref = typeref(lexer)?;
+            if lexer.lookahead(2)?.token == Token::LParentheses {
+                func_defs.push(Rc::new(Box::new(deffunc(lexer, typeref, start)?)));
             } else {
-                var_defs.push(Rc::new(Box::new(defvar(&mut lexer, typeref))));
+                var_defs.push(Rc::new(Box::new(defvar(lexer, typeref, start)?)));
             }
         } else {
             break;
         }
     }
 
-    TopDefNode {
+    Ok(TopDefNode {
         var_defs,
         func_defs
-    }
+    })
 }
 
-pub fn defstruct(mut lexer: &mut Lexer) -> DefStructNode {
-    // TYPE_BASE ( [] | [ INTEGER ] | * | ( param_typeref ) ) *
-    lexer.advance();
-    let mut name = String::from("");
+pub fn defstruct(lexer: &mut Lexer) -> Result<DefStructNode, ParseError> {
+    // ( STRUCT | UNION ) name { ( slot ; ) * } ;
+    let start = lexer.next_location()?;
+    let kind = lexer.advance()?.token;
+    
     let mut member_list = Vec::new();
     
-    let t = lexer.lookahead(1);
-    match t {
-        Token::Name(s) => name = s,
-        _ => panic!("unexcept token! {}", t)
-    }
-    lexer.advance();
+    let t = lexer.lookahead(1)?.token;
+    let name = match t {
+        Token::Name(s) => s,
+        _ => return Err(ParseError::unexpect(lexer, t))
+    };
+    lexer.advance()?;
 
-    lexer.matcher(Token::LBrace);
+    lexer.matcher(Token::LBrace)?;
 
-    while is_base_type(&lexer.lookahead(1)) {
-        member_list.push(slot(&mut lexer));
-        lexer.matcher(Token::Semi);
+    while is_base_type(&lexer.lookahead(1)?.token) || is_qualifier(&lexer.lookahead(1)?.token) {
+        member_list.push(slot(lexer)?);
+        lexer.matcher(Token::Semi)?;
     }
 
-    lexer.matcher(Token::RBrace);
-    lexer.matcher(Token::Semi);
+    lexer.matcher(Token::RBrace)?;
+    lexer.matcher(Token::Semi)?;
 
-    DefStructNode {
+    Ok(DefStructNode {
+        kind,
         name,
         member_list,
-    }
+        span: lexer.span_from(start),
+    })
+}
+
+/**
+ *  struct point {...}是定义，struct point p;是声明全局变量，走下面基本类型的分支
+ *  enum color {...}是定义，enum color c;是用枚举类型声明变量
+ */
+fn is_enum_def(lexer: &mut Lexer) -> Result<bool, ParseError> {
+    return Ok(lexer.lookahead(2)?.token == Token::LBrace || lexer.lookahead(3)?.token == Token::LBrace)
 }
 
-fn slot(mut lexer: &mut Lexer) -> SlotNode {
+pub fn defenum(lexer: &mut Lexer) -> Result<DefEnumNode, ParseError> {
     /*
-        type name
+        ENUM [ name ] { name [ = expr ] ( , name [ = expr ] ) * [ , ] } ;
     */
-    let typeref = typeref(&mut lexer);
-    let name;
+    let start = lexer.next_location()?;
+    lexer.advance()?;
+    let mut name = None;
+    let mut members = Vec::new();
 
-    let t = lexer.lookahead(1);
-    match t {
-        Token::Name(s) => name = s,
-        _ => panic!("unexcept token! {}", t)
+    if let Token::Name(s) = lexer.lookahead(1)?.token {
+        lexer.advance()?;
+        name = Some(s);
     }
 
-    lexer.advance();
+    lexer.matcher(Token::LBrace)?;
+    while lexer.lookahead(1)?.token != Token::RBrace {
+        members.push(enum_member(lexer)?);
 
-    SlotNode {
-        typeref,
+        let t = lexer.lookahead(1)?.token;
+        match t {
+            Token::RBrace => break,
+            Token::Comma => lexer.advance()?.token,
+            _ => return Err(ParseError::unexpect(lexer, t)),
+        };
+    }
+    lexer.matcher(Token::RBrace)?;
+    lexer.matcher(Token::Semi)?;
+
+    Ok(DefEnumNode {
         name,
+        members,
+        span: lexer.span_from(start),
+    })
+}
+
+fn enum_member(lexer: &mut Lexer) -> Result<EnumMemberNode, ParseError> {
+    let start = lexer.next_location()?;
+    // 枚举成员是普通的标识符，和typedef同名时留给语义分析报重定义
+    let name = declarator_name(lexer)?;
+    lexer.add_var_name(&name);
+
+    let mut value = None;
+    if lexer.lookahead(1)?.token == Token::Assgin {
+        lexer.advance()?;
+        value = Some(Rc::new(expr0(lexer)?));
     }
+
+    Ok(EnumMemberNode {
+        name,
+        value,
+        span: lexer.span_from(start),
+    })
 }
 
-pub fn typeref(mut lexer: &mut Lexer) -> TypeNode {
-    // TYPE_BASE ( [] | [ INTEGER ] | * | ( param_typeref ) ) *
-    let type_base = typebase(&mut lexer);
+pub fn deftypedef(lexer: &mut Lexer) -> Result<DefTypedefNode, ParseError> {
+    /*
+        TYPEDEF typeref declarator ;
+    */
+    let start = lexer.next_location()?;
+    lexer.advance()?;
+    let typeref = typeref(lexer)?;
+    let (name, typeref) = declarator(lexer, typeref)?;
+    lexer.matcher(Token::Semi)?;
+    // 后面再遇到这个名字就当成类型
+    lexer.add_type_name(&name);
+
+    Ok(DefTypedefNode {
+        name,
+        typeref,
+        span: lexer.span_from(start),
+    })
+}
+
+fn slot(lexer: &mut Lexer) -> Result<SlotNode, ParseError> {
+    /*
+        type name
+    */
+    let start = lexer.next_location()?;
+    let typeref = typeref(lexer)?;
+    let (name, typeref) = declarator(lexer, typeref)?;
+
+    Ok(SlotNode {
+        typeref,
+        name,
+        span: lexer.span_from(start),
+    })
+}
+
+pub fn typeref(lexer: &mut Lexer) -> Result<TypeNode, ParseError> {
+    // qualifier TYPE_BASE qualifier ( [] | [ INTEGER ] | [ expr ] | * ( const | volatile ) * | ( param_typeref ) ) *
+    let mut qualifier = Qualifier::default();
+    let mut sign = None;
+    qualifiers(lexer, &mut qualifier, &mut sign)?;
+    // 只写unsigned或signed时是int
+    let type_base = if sign.is_some() && !is_base_type(&lexer.lookahead(1)?.token) {
+        TypeBase {
+            base: Token::Int,
+            name: None,
+        }
+    } else {
+        typebase(lexer)?
+    };
+    qualifiers(lexer, &mut qualifier, &mut sign)?;
     let mut nested_def = Vec::new();
     loop {
-        let t = lexer.lookahead(1);
+        let t = lexer.lookahead(1)?.token;
         match t {
             Token::LBrackets => {
-                lexer.advance();
-                let t = lexer.lookahead(1);
+                lexer.advance()?;
+                let t = lexer.lookahead(1)?.token;
                 match t {
                     Token::RBrackets => {
                         nested_def.push(TypeDef::Array);
                     },
-                    Token::Number(n) => {
-                        lexer.advance();
+                    Token::Number(n, _) if lexer.lookahead(2)?.token == Token::RBrackets => {
+                        lexer.advance()?;
                         nested_def.push(TypeDef::FixedArray(n as usize));
                     },
-                    _ => panic!("unexcept token! {}", t)
+                    // 长度里用到枚举成员时要到语义分析才能算出来
+                    _ => {
+                        nested_def.push(TypeDef::ConstArray(ConstExpr(Rc::new(expr0(lexer)?))));
+                        let t = lexer.lookahead(1)?.token;
+                        if t != Token::RBrackets {
+                            return Err(ParseError::unexpect(lexer, t));
+                        }
+                    },
                 }
             },
+            // *后面的const只修饰这一层指针
             Token::Mul => {
                 nested_def.push(TypeDef::Pointer);
+                lexer.advance()?;
+                loop {
+                    match lexer.lookahead(1)?.token {
+                        Token::Const => qualifier.const_pointers.push(nested_def.len() - 1),
+                        Token::Volatile => {},
+                        _ => break,
+                    }
+                    lexer.advance()?;
+                }
+                continue;
+            },
+            // (后面跟着类型才是参数列表，int (*f)(int)里的(*f)是声明符
+            Token::LParentheses if is_param_list(lexer)? => {
+                let return_type = TypeNode {
+                    type_base: type_base.clone(),
+                    nested_def: nested_def.clone(),
+                    qualifier: Qualifier {
+                        storage: None,
+                        ..qualifier.clone()
+                    },
+                };
+                nested_def.push(func_type(lexer, return_type)?);
+                continue;
             },
-            // TODO func
             _ => break,
         }
-        lexer.advance();
+        lexer.advance()?;
     }
 
-    TypeNode {
+    Ok(TypeNode {
         type_base,
         nested_def,
+        qualifier,
+    })
+}
+
+/**
+ *  sign记录写了的signed或unsigned，和static、extern一样不能同时写两种
+ */
+fn qualifiers(lexer: &mut Lexer, qualifier: &mut Qualifier, sign: &mut Option<Token>) -> Result<(), ParseError> {
+    loop {
+        let t = lexer.lookahead(1)?.token;
+        match t {
+            Token::Const => qualifier.is_const = true,
+            Token::Volatile => qualifier.is_volatile = true,
+            Token::Unsigned | Token::Signed => {
+                if sign.as_ref().is_some_and(|prev| *prev != t) {
+                    return Err(ParseError::unexpect(lexer, t))
+                }
+                qualifier.is_unsigned = t == Token::Unsigned;
+                *sign = Some(t);
+            },
+            Token::Static | Token::Extern => {
+                if qualifier.storage.as_ref().is_some_and(|prev| *prev != t) {
+                    return Err(ParseError::unexpect(lexer, t))
+                }
+                qualifier.storage = Some(t);
+            },
+            _ => break,
+        }
+        lexer.advance()?;
     }
+
+    Ok(())
 }
 
-fn typebase(mut lexer: &mut Lexer) -> TypeBase {
+fn is_param_list(lexer: &mut Lexer) -> Result<bool, ParseError> {
+    let t = lexer.lookahead(2)?.token;
+    return Ok(t == Token::RParentheses || is_base_type(&t) || is_qualifier(&t))
+}
+
+fn func_type(lexer: &mut Lexer, return_type: TypeNode) -> Result<TypeDef, ParseError> {
     /*
-        int | float | double | struct xxx | char | void 
+        ( [ typeref [ name ] ( , typeref [ name ] ) * [ , ... ] ] )
+        参数名可以省略，(void)和()一样表示没有参数
+    */
+    lexer.matcher(Token::LParentheses)?;
+    let mut params = Vec::new();
+    let mut variadic = false;
+    while lexer.lookahead(1)?.token != Token::RParentheses {
+        if lexer.lookahead(1)?.token == Token::Ellipsis && !params.is_empty() {
+            lexer.advance()?;
+            variadic = true;
+            break;
+        }
+        params.push(typeref(lexer)?);
+        if let Token::Name(_) = lexer.lookahead(1)?.token {
+            lexer.advance()?;
+        }
+
+        let t = lexer.lookahead(1)?.token;
+        match t {
+            Token::RParentheses => break,
+            Token::Comma => lexer.advance()?.token,
+            _ => return Err(ParseError::unexpect(lexer, t)),
+        };
+    }
+    lexer.matcher(Token::RParentheses)?;
+
+    let is_void = |param: &TypeNode| param.type_base.base == Token::Void && param.nested_def.is_empty();
+    if params.len() == 1 && is_void(&params[0]) {
+        params.clear();
+    }
+
+    Ok(TypeDef::Func(Box::new(FuncType {
+        params,
+        return_type,
+        variadic,
+    })))
+}
+
+fn declarator(lexer: &mut Lexer, typeref: TypeNode) -> Result<(String, TypeNode), ParseError> {
+    /*
+        name | ( * name ) ( [ param_typeref ] )
+        第二种是函数指针，比如int (*cb)(int, char*)
+    */
+    if lexer.lookahead(1)?.token != Token::LParentheses {
+        return Ok((declarator_name(lexer)?, typeref))
+    }
+
+    lexer.advance()?;
+    lexer.matcher(Token::Mul)?;
+    let name = declarator_name(lexer)?;
+    lexer.matcher(Token::RParentheses)?;
+
+    let mut nested_def = typeref.nested_def.clone();
+    nested_def.push(func_type(lexer, typeref.clone())?);
+    nested_def.push(TypeDef::Pointer);
+
+    Ok((name, TypeNode {
+        type_base: typeref.type_base,
+        nested_def,
+        qualifier: typeref.qualifier,
+    }))
+}
+
+fn declarator_name(lexer: &mut Lexer) -> Result<String, ParseError> {
+    let t = lexer.lookahead(1)?.token;
+    match t {
+        // 重复typedef同一个名字或者声明和typedef同名的变量时这里读到的是TypeName，留给语义分析报重定义
+        Token::Name(s) | Token::TypeName(s) => {
+            lexer.advance()?;
+            return Ok(s)
+        },
+        _ => return Err(ParseError::unexpect(lexer, t)),
+    }
+}
+
+fn typebase(lexer: &mut Lexer) -> Result<TypeBase, ParseError> {
+    /*
+        int | float | double | struct xxx | union xxx | enum xxx | char | void 
     */
     let base;
     let mut name = None;
-    let t = lexer.lookahead(1);
-    println!("base {}", t);
+    let t = lexer.lookahead(1)?.token;
     if is_base_type(&t) {
         base = t;
-        lexer.advance();
+        lexer.advance()?;
     } else {
-        panic!("unexcept token! {} ", t);
+        return Err(ParseError::unexpect(lexer, t));
+    }
+
+    // long long和long int都按long处理，short int按short处理
+    if base == Token::Long && lexer.lookahead(1)?.token == Token::Long {
+        lexer.advance()?;
+    }
+    if (base == Token::Long || base == Token::Short) && lexer.lookahead(1)?.token == Token::Int {
+        lexer.advance()?;
     }
 
-    if base == Token::Struct {
-        let t = lexer.lookahead(1); 
+    if is_aggregate(&base) || base == Token::Enum {
+        let t = lexer.lookahead(1)?.token; 
         match t {
             Token::Name(s) => name = Some(s.clone()),
-            _ => panic!("unexcept token! {}", t),
+            _ => return Err(ParseError::unexpect(lexer, t)),
         }
-        lexer.advance();
+        lexer.advance()?;
     }
 
-    TypeBase {
+    Ok(TypeBase {
         base,
         name,
-    }
+    })
 }
 
 
-fn deffunc(mut lexer: &mut Lexer, typeref: TypeNode) -> DefFuncNode {
+fn deffunc(lexer: &mut Lexer, typeref: TypeNode, start: Location) -> Result<DefFuncNode, ParseError> {
     /*
-        typeref name ( [ param ] ) block
+        typeref name ( [ param ] ) ( block | ; )
     */
     // let typeref = typeref(&mut lexer);
-    let name;
+    
 
-    let t = lexer.lookahead(1);
-    match t {
-        Token::Name(s) => name = s,
-        _ => panic!("unexcept token! {}", t),
+    let t = lexer.lookahead(1)?.token;
+    let name = match t {
+        Token::Name(s) => s,
+        _ => return Err(ParseError::unexpect(lexer, t)),
     };
 
-    lexer.advance();
+    lexer.advance()?;
 
-    let params = params(&mut lexer);
-    let block = Rc::new(block(&mut lexer));
+    // 参数名在函数体里可以遮住typedef，声明的参数只在括号里有效
+    lexer.enter_scope();
+    let (params, unnamed) = params(lexer)?;
+    let block = if lexer.lookahead(1)?.token == Token::Semi {
+        lexer.leave_scope();
+        lexer.advance()?;
+        None
+    } else {
+        // 只有声明可以省略参数名，函数定义的参数必须有名字
+        if let Some(err) = unnamed {
+            return Err(err)
+        }
+        let block = block(lexer)?;
+        lexer.leave_scope();
+        Some(Rc::new(block))
+    };
 
-    DefFuncNode {
+    Ok(DefFuncNode {
         typeref,
         name,
         params,
         block,
+        span: lexer.span_from(start),
+    })
+}
+
+fn param(lexer: &mut Lexer, unnamed: &mut Option<ParseError>) -> Result<SlotNode, ParseError> {
+    /*
+        type [ name ]
+        省略参数名时名字是空的，记下第一个没有名字的位置，是函数定义时再报错
+    */
+    let start = lexer.next_location()?;
+    let typeref = typeref(lexer)?;
+    let t = lexer.lookahead(1)?.token;
+    if t == Token::Comma || t == Token::RParentheses {
+        if unnamed.is_none() {
+            *unnamed = Some(ParseError::unexpect(lexer, t));
+        }
+        return Ok(SlotNode {
+            typeref,
+            name: String::new(),
+            span: lexer.span_from(start),
+        })
     }
+    let (name, typeref) = declarator(lexer, typeref)?;
+    lexer.add_var_name(&name);
+
+    Ok(SlotNode {
+        typeref,
+        name,
+        span: lexer.span_from(start),
+    })
 }
 
-fn params(mut lexer: &mut Lexer) -> ParamsNode {
+fn params(lexer: &mut Lexer) -> Result<(ParamsNode, Option<ParseError>), ParseError> {
     /*
-        ( [ slot ( , slot) * ])
+        ( [ param ( , param) * [ , ... ] ])
+        ...前面至少要有一个参数，(void)和()一样表示没有参数
     */
-    lexer.matcher(Token::LParentheses);
+    lexer.matcher(Token::LParentheses)?;
     let mut params: Vec<SlotNode> = Vec::new();
-    
+    let mut variadic = false;
+    let mut unnamed = None;
+
     loop {
-        if lexer.lookahead(1) != Token::RParentheses {
-            params.push(slot(&mut lexer));
+        if lexer.lookahead(1)?.token == Token::Ellipsis && !params.is_empty() {
+            lexer.advance()?;
+            variadic = true;
+            break;
+        }
+        if lexer.lookahead(1)?.token != Token::RParentheses {
+            params.push(param(lexer, &mut unnamed)?);
         }
 
-        let t = lexer.lookahead(1);
+        let t = lexer.lookahead(1)?.token;
         match t {
             Token::RParentheses => break,
-            Token::Comma => lexer.advance(),
-            _ => panic!("unexcept token! {}", t),
+            Token::Comma => lexer.advance()?.token,
+            _ => return Err(ParseError::unexpect(lexer, t)),
         };
     }
 
-    lexer.matcher(Token::RParentheses);
+    lexer.matcher(Token::RParentheses)?;
 
-    ParamsNode {
-        params,
+    let is_void = |param: &SlotNode| param.name.is_empty() && param.typeref.type_base.base == Token::Void && param.typeref.nested_def.is_empty();
+    if params.len() == 1 && is_void(&params[0]) {
+        params.clear();
+        unnamed = None;
     }
+
+    Ok((ParamsNode {
+        params,
+        variadic,
+    }, unnamed))
 }
 
-pub fn defvar(mut lexer: &mut Lexer, typeref: TypeNode) -> DefVarNode {
+pub fn defvar(lexer: &mut Lexer, typeref: TypeNode, start: Location) -> Result<DefVarNode, ParseError> {
     /*
         typeref name [ = expr] [, name = [expr] ] *
     */
     // let typeref = typeref(&mut lexer);
-    let mut name_map = HashMap::new();
-    var_stmt(&mut lexer, &mut name_map);
+    let mut declarators = Vec::new();
+    let mut typeref = typeref;
+    var_stmt(lexer, &mut typeref, &mut declarators)?;
 
-    DefVarNode {
+    Ok(DefVarNode {
         typeref,
-        name_map: name_map.clone(),
-    }
+        declarators,
+        span: lexer.span_from(start),
+    })
 }
 
-fn var_stmt(mut lexer: &mut Lexer, name_map: &mut HashMap<String, Option<Rc<Box<dyn ExprNode>>>>) {
-    let mut name = String::from("");
-    if let Token::Name(s) = lexer.lookahead(1) {
-        lexer.advance();
-        name = s;
-    } else {
-        panic!("unexcept token! {}", lexer.lookahead(1));
+fn var_stmt(
+    lexer: &mut Lexer,
+    typeref: &mut TypeNode,
+    declarators: &mut Vec<Declarator>,
+) -> Result<(), ParseError> {
+    /*
+        所有变量共用一个typeref，所以函数指针只能单独声明
+        按源码里的顺序保存，初始化也按这个顺序进行
+    */
+    let t = lexer.lookahead(1)?.token;
+    if t == Token::LParentheses && !declarators.is_empty() {
+        return Err(ParseError::unexpect(lexer, t));
     }
-    let t = lexer.lookahead(1);
+    let (name, declared) = declarator(lexer, typeref.clone())?;
+    // 声明之后这个名字是普通的标识符，同名的typedef被遮住
+    lexer.add_var_name(&name);
+    let is_func_pointer = declared != *typeref;
+    *typeref = declared;
+
+    let t = lexer.lookahead(1)?.token;
     match t {
         Token::Assgin => {
-            lexer.advance();
-            name_map.insert(name, Some(Rc::new(expr0(&mut lexer))));
+            lexer.advance()?;
+            let value = if lexer.lookahead(1)?.token == Token::LBrace {
+                init_list(lexer)?
+            } else {
+                expr0(lexer)?
+            };
+            declarators.push((name, Some(Rc::new(value))));
 
-            if lexer.lookahead(1) == Token::Comma {
-                lexer.advance();
-                var_stmt(&mut lexer, name_map);
+            if lexer.lookahead(1)?.token == Token::Comma && !is_func_pointer {
+                lexer.advance()?;
+                return var_stmt(lexer, typeref, declarators);
             }
         },
-        Token::Comma => {
-            name_map.insert(name, None);
-            lexer.advance();
-            var_stmt(&mut lexer, name_map);
+        Token::Comma if !is_func_pointer => {
+            declarators.push((name, None));
+            lexer.advance()?;
+            return var_stmt(lexer, typeref, declarators);
         }
         Token::Semi => {
-            name_map.insert(name, None);
+            declarators.push((name, None));
         },
         _ => {
-            panic!("unexcpet token! {}", t);
+            return Err(ParseError::unexpect(lexer, t));
         }
     }
-    lexer.matcher(Token::Semi);
+    lexer.matcher(Token::Semi)?;
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use lex::location::Span;
+    use crate::error::ParseErrorType;
 
     #[test]
     fn test_import_stmt() {
         let mut lxr = Lexer::new(String::from("import a.b.c;
         import z.x.c;"));
-        println!("{:?}", import_statements(&mut lxr));
+        println!("{:?}", import_statements(&mut lxr).unwrap());
     }
 
     #[test]
@@ -321,19 +657,231 @@ mod tests {
             float* c;
             int[2] d;
         };"));
-        println!("{:?}", defstruct(&mut lxr));
+        println!("{:?}", defstruct(&mut lxr).unwrap());
     }
 
     #[test]
     fn test_deffunc() {
         let mut lxr = Lexer::new(String::from("float test(int[] *a, struct na b) { if(1 == 2) { for(a = 1; a < 3; a++) { b = 10 + 20; } } else { a = 6; return a; } }"));
-        // println!("{:?}", deffunc(&mut lxr));
+        let start = lxr.next_location().unwrap();
+        let typeref = typeref(&mut lxr).unwrap();
+        println!("{:?}", deffunc(&mut lxr, typeref, start).unwrap());
+    }
+
+    #[test]
+    fn test_unnamed_params() {
+        let parse_func = |source: &str| {
+            let mut lxr = Lexer::new(String::from(source));
+            let start = lxr.next_location().unwrap();
+            let typeref = typeref(&mut lxr).unwrap();
+            deffunc(&mut lxr, typeref, start)
+        };
+
+        // 声明里可以省略参数名
+        let node = parse_func("int puts(const char *);").unwrap();
+        assert_eq!(1, node.params.params.len());
+        assert_eq!("", node.params.params[0].name);
+        assert_eq!(vec![TypeDef::Pointer], node.params.params[0].typeref.nested_def);
+        let node = parse_func("int f(int, char *s, ...);").unwrap();
+        assert_eq!(vec!["", "s"], node.params.params.iter().map(|param| param.name.as_str()).collect::<Vec<_>>());
+        assert!(node.params.variadic);
+        assert!(parse_func("int main(void) { return 0; }").unwrap().params.params.is_empty());
+
+        let err = parse_func("int f(int a, int) { return a; }").unwrap_err();
+        assert_eq!(ParseErrorType::UnexpectToken(Token::RParentheses), err.error);
+        assert_eq!(Location::new(1, 17), err.location);
     }
 
     #[test]
     fn test_defvars() {
         let mut lxr = Lexer::new(String::from("struct stu *[] a = a + 32, b = 234, c;"));
-        // println!("{:?}", defvar(&mut lxr));
+        let start = lxr.next_location().unwrap();
+        let typeref = typeref(&mut lxr).unwrap();
+        let node = defvar(&mut lxr, typeref, start).unwrap();
+        assert_eq!(Span::new(Location::new(1, 1), Location::new(1, 39)), node.span);
+        println!("{:?}", node);
+    }
+
+    #[test]
+    fn test_func_pointer() {
+        let mut lxr = Lexer::new(String::from("int (*cb)(int, char *name) = calc;"));
+        let start = lxr.next_location().unwrap();
+        let ty = typeref(&mut lxr).unwrap();
+        let node = defvar(&mut lxr, ty, start).unwrap();
+        assert_eq!("cb", node.declarators[0].0);
+        match node.typeref.nested_def.as_slice() {
+            [TypeDef::Func(func), TypeDef::Pointer] => {
+                assert_eq!(2, func.params.len());
+                assert_eq!(vec![TypeDef::Pointer], func.params[1].nested_def);
+                assert_eq!(Token::Int, func.return_type.type_base.base);
+            },
+            nested => panic!("expect function pointer, found {:?}", nested),
+        }
+
+        // 类型写在前面的形式和声明符的形式是同一个类型
+        let mut lxr = Lexer::new(String::from("int(int, char*)* cb; char *(*f)(void);"));
+        let start = lxr.next_location().unwrap();
+        let ty = typeref(&mut lxr).unwrap();
+        assert_eq!(node.typeref, defvar(&mut lxr, ty, start).unwrap().typeref);
+        let start = lxr.next_location().unwrap();
+        let ty = typeref(&mut lxr).unwrap();
+        let node = defvar(&mut lxr, ty, start).unwrap();
+        match node.typeref.nested_def.as_slice() {
+            [TypeDef::Pointer, TypeDef::Func(func), TypeDef::Pointer] => {
+                assert!(func.params.is_empty());
+                assert_eq!(vec![TypeDef::Pointer], func.return_type.nested_def);
+            },
+            nested => panic!("expect function pointer, found {:?}", nested),
+        }
+
+        for source in ["int (*a)(int), b;", "int a, (*b)(int);", "int (*a)(int) = f, b;"] {
+            let mut lxr = Lexer::new(String::from(source));
+            let start = lxr.next_location().unwrap();
+            let ty = typeref(&mut lxr).unwrap();
+            assert!(defvar(&mut lxr, ty, start).is_err());
+        }
+    }
+
+    #[test]
+    fn test_typedef() {
+        let mut lxr = Lexer::new(String::from("
+            typedef struct node Node;
+            typedef int (*Callback)(Node *);
+            struct node {
+                Node *next;
+                Callback cb;
+            };
+            Node *head;
+            int main() {
+                Node n;
+                Callback f;
+                (Node) n;
+                return 0;
+            }
+        "));
+        let defs = top_defs(&mut lxr).unwrap();
+        assert_eq!(4, defs.var_defs.len());
+        assert_eq!(1, defs.func_defs.len());
+        let defs = format!("{:?}", defs);
+        assert!(defs.contains("DefTypedefNode { name: \"Node\", typeref: TypeNode { type_base: TypeBase { base: Struct, name: Some(\"node\") }"));
+        assert!(defs.contains("base: TypeName(\"Callback\")"));
+
+        let mut lxr = Lexer::new(String::from("typedef int;"));
+        assert!(top_defs(&mut lxr).is_err());
+    }
+
+    #[test]
+    fn test_enum() {
+        let mut lxr = Lexer::new(String::from("
+            enum color { RED, GREEN = 5, BLUE, };
+            enum { A = 1 << 2 };
+            enum color c;
+            int[BLUE + 1] a;
+            int main(enum color c) {
+                return 0;
+            }
+        "));
+        let defs = top_defs(&mut lxr).unwrap();
+        assert_eq!(4, defs.var_defs.len());
+        assert_eq!(1, defs.func_defs.len());
+        let defs = format!("{:?}", defs);
+        assert!(defs.contains("DefEnumNode { name: Some(\"color\"), members: [EnumMemberNode { name: \"RED\", value: None"));
+        assert!(defs.contains("TypeBase { base: Enum, name: Some(\"color\") }"));
+        assert!(defs.contains("ConstArray"));
+
+        let mut lxr = Lexer::new(String::from("enum color { RED GREEN };"));
+        assert!(top_defs(&mut lxr).is_err());
+    }
+
+    #[test]
+    fn test_prototype() {
+        let mut lxr = Lexer::new(String::from("
+            int calc(int a);
+            extern char *strcpy(char *dst, const char *src);
+            int main() {
+                return calc(1);
+            }
+        "));
+        let defs = top_defs(&mut lxr).unwrap();
+        assert_eq!(3, defs.func_defs.len());
+        let defs = format!("{:?}", defs);
+        assert!(defs.contains("name: \"calc\", params: ParamsNode { params: [SlotNode { typeref: TypeNode { type_base: TypeBase { base: Int, name: None }"));
+        assert_eq!(2, defs.matches("block: None").count());
+
+        let mut lxr = Lexer::new(String::from("int calc(int a)"));
+        assert!(top_defs(&mut lxr).is_err());
+
+        let mut lxr = Lexer::new(String::from("
+            int printf(char *fmt, ...);
+            int apply(int (*f)(int, ...), int a, ...) {
+                return 0;
+            }
+        "));
+        let defs = format!("{:?}", top_defs(&mut lxr).unwrap());
+        assert_eq!(3, defs.matches("variadic: true").count());
+
+        for source in ["int f(...);", "int f(int a, ..., int b);"].iter() {
+            let mut lxr = Lexer::new(String::from(*source));
+            assert!(top_defs(&mut lxr).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_init_list() {
+        let mut lxr = Lexer::new(String::from("
+            int[3] a = {1, 2, 3};
+            struct point p = {.x = 1, .y = 2,};
+            struct line l = {{1, 2}, [1] = {.y = 4}};
+            int[2] e = {};
+        "));
+        let defs = format!("{:?}", top_defs(&mut lxr).unwrap());
+        assert_eq!(6, defs.matches("InitListNode").count());
+        assert!(defs.contains("designator: Some(Member(\"x\"))"));
+        assert!(defs.contains("designator: Some(Index("));
+
+        for source in ["int[2] a = {1 2};", "int[2] a = {1,,};", "struct point p = {.x 1};"].iter() {
+            let mut lxr = Lexer::new(String::from(*source));
+            assert!(top_defs(&mut lxr).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_qualifier() {
+        let mut lxr = Lexer::new(String::from("
+            static const unsigned int a;
+            extern long long b;
+            unsigned c;
+            signed char d;
+            long int e;
+            int main(const char *s) {
+                static int count;
+                return 0;
+            }
+        "));
+        let defs = top_defs(&mut lxr).unwrap();
+        assert_eq!(5, defs.var_defs.len());
+        let defs = format!("{:?}", defs);
+        assert!(defs.contains("base: Int, name: None }, nested_def: [], qualifier: Qualifier { is_const: true, is_volatile: false, is_unsigned: true, storage: Some(Static), const_pointers: [] }"));
+        assert!(defs.contains("base: Long, name: None }, nested_def: [], qualifier: Qualifier { is_const: false, is_volatile: false, is_unsigned: false, storage: Some(Extern), const_pointers: [] }"));
+        assert!(defs.contains("base: Char, name: None }, nested_def: [Pointer], qualifier: Qualifier { is_const: true"));
+        assert!(!defs.contains("base: Unsigned"));
+
+        // *后面的const修饰的是那一层指针
+        let mut lxr = Lexer::new(String::from("
+            int * const p;
+            const char * volatile * const * s;
+        "));
+        let defs = top_defs(&mut lxr).unwrap();
+        let defs = format!("{:?}", defs);
+        assert!(defs.contains("nested_def: [Pointer], qualifier: Qualifier { is_const: false, is_volatile: false, is_unsigned: false, storage: None, const_pointers: [0] }"));
+        assert!(defs.contains("nested_def: [Pointer, Pointer, Pointer], qualifier: Qualifier { is_const: true, is_volatile: false, is_unsigned: false, storage: None, const_pointers: [1] }"));
+
+        for source in ["unsigned signed int a;", "int signed unsigned a;", "static extern int a;", "extern int static a;", "int * unsigned a;"].iter() {
+            let mut lxr = Lexer::new(String::from(*source));
+            assert!(top_defs(&mut lxr).is_err(), "{}", source);
+        }
+        let mut lxr = Lexer::new(String::from("signed signed int a; static int static b;"));
+        assert_eq!(2, top_defs(&mut lxr).unwrap().var_defs.len());
     }
 
     #[test]
@@ -344,11 +892,11 @@ mod tests {
                 char[] name;
                 int age;
                 int sex;
-            }
+            };
 
             struct class {
                 struct student[] *ss;
-            }
+            };
 
             int main(int argc,char **argv) {
                 int a = 1;
@@ -369,6 +917,6 @@ mod tests {
                 return a;
             }
         "));
-        println!("{:?}", top_defs(&mut lxr));
+        println!("{:?}", top_defs(&mut lxr).unwrap());
     }
 }
\ No newline at end of file
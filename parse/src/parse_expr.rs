@@ -1,9 +1,16 @@
 use lex::lexer::Lexer;
+use lex::location::Location;
+use crate::error:: {
+    ParseError,
+    ParseErrorType,
+};
 use lex::token:: {
     Token,
     is_base_type,
+    is_qualifier,
     is_prefix_op,
     is_postfix_op,
+    is_assgin_op,
 };
 use crate::parse_def::typeref;
 use crate::ast_expr:: {
@@ -12,513 +19,754 @@ use crate::ast_expr:: {
     PrimaryNode,
     Const,
     RefUnaryNode,
-    PointerRefUnaryNode,
     ExprNode,
     AssginmentNode,
+    ConditionalNode,
+    CommaNode,
     SingeUnaryNode,
     SelfOpUnaryNode,
     FuncCallNode,
     ArithmeticOpNode,
     ArrayUnaryNode,
+    SizeofNode,
+    SizeofTarget,
+    InitListNode,
+    InitItemNode,
+    Designator,
 };
 use std::rc::Rc;
 
-pub fn expr0(mut lexer: &mut Lexer) -> Box<dyn ExprNode> {
-    let mut left_value = expr1(&mut lexer);
-    loop {
-        let t = lexer.lookahead(1);
-        match t {
-            Token::Assgin => {
-                left_value = assignment_expr(&mut lexer, left_value);
-            },
-            _ => return left_value
-        }
+type CallParams = Option<Vec<Rc<Box<dyn ExprNode>>>>;
+
+pub fn comma_expr(lexer: &mut Lexer) -> Result<Box<dyn ExprNode>, ParseError> {
+    /*
+        expr0 ( , expr0 ) *
+        优先级最低，函数参数和初始化列表里的逗号是分隔符，那里直接用expr0
+    */
+    let mut node = expr0(lexer)?;
+    while lexer.lookahead(1)?.token == Token::Comma {
+        lexer.advance()?;
+        let right = expr0(lexer)?;
+        let span = node.span().merge(&right.span());
+        node = Box::new(CommaNode {
+            left_value: Rc::new(node),
+            right_value: Rc::new(right),
+            span,
+        });
+    }
+
+    return Ok(node)
+}
+
+pub fn expr0(lexer: &mut Lexer) -> Result<Box<dyn ExprNode>, ParseError> {
+    /*
+        conditional_expr [ (= | += | -= | *= | /= | %= | <<= | >>= | &= | |= | ^=) expr0 ]
+    */
+    let left_value = conditional_expr(lexer)?;
+    let t = lexer.lookahead(1)?.token;
+    if is_assgin_op(&t) {
+        return assignment_expr(lexer, left_value)
     }
+
+    return Ok(left_value)
 }
 
-fn assignment_expr(mut lexer: &mut Lexer, node: Box<dyn ExprNode>) -> Box<dyn ExprNode> {
-    lexer.advance();
-    let t = expr1(&mut lexer);
-    Box::new(AssginmentNode {
+fn assignment_expr(lexer: &mut Lexer, node: Box<dyn ExprNode>) -> Result<Box<dyn ExprNode>, ParseError> {
+    let operator = lexer.advance()?.token;
+    // 赋值是右结合的，a = b += c 是 a = (b += c)
+    let t = expr0(lexer)?;
+    let span = node.span().merge(&t.span());
+    Ok(Box::new(AssginmentNode {
+        operator,
         left_value: Rc::new(node),
         right_value: Rc::new(t),
-    })
+        span,
+    }))
 }
 
-fn expr1(mut lexer: &mut Lexer) -> Box<dyn ExprNode> {
-    let mut left_value = expr2(&mut lexer);
+fn conditional_expr(lexer: &mut Lexer) -> Result<Box<dyn ExprNode>, ParseError> {
+    /*
+        expr1 [ ? comma_expr : conditional_expr ]
+    */
+    let condition = expr1(lexer)?;
+    if lexer.lookahead(1)?.token != Token::Question {
+        return Ok(condition)
+    }
+
+    lexer.advance()?;
+    let then_value = comma_expr(lexer)?;
+    lexer.matcher(Token::Colon)?;
+    let else_value = conditional_expr(lexer)?;
+    let span = condition.span().merge(&else_value.span());
+    Ok(Box::new(ConditionalNode {
+        condition: Rc::new(condition),
+        then_value: Rc::new(then_value),
+        else_value: Rc::new(else_value),
+        span,
+    }))
+}
+
+fn expr1(lexer: &mut Lexer) -> Result<Box<dyn ExprNode>, ParseError> {
+    let mut left_value = expr2(lexer)?;
     loop {
-        let t = lexer.lookahead(1);
+        let t = lexer.lookahead(1)?.token;
         match t {
             Token::Or => {
-                left_value = or_expr(&mut lexer, left_value);
+                left_value = or_expr(lexer, left_value)?;
             },
-            _ => return left_value
+            _ => return Ok(left_value)
         }
     }
 }
 
-fn or_expr(mut lexer: &mut Lexer, node: Box<dyn ExprNode>) -> Box<dyn ExprNode> {
-    let operator = lexer.advance();
-    let t = expr2(&mut lexer);
-    Box::new(ArithmeticOpNode {
+fn or_expr(lexer: &mut Lexer, node: Box<dyn ExprNode>) -> Result<Box<dyn ExprNode>, ParseError> {
+    let operator = lexer.advance()?.token;
+    let t = expr2(lexer)?;
+    let span = node.span().merge(&t.span());
+    Ok(Box::new(ArithmeticOpNode {
         operator,
         left_value: Rc::new(node),
         right_value: Rc::new(t),
-    })
+        span,
+    }))
 }
 
-fn expr2(mut lexer: &mut Lexer) -> Box<dyn ExprNode> {
-    let mut left_value = expr3(&mut lexer);
+fn expr2(lexer: &mut Lexer) -> Result<Box<dyn ExprNode>, ParseError> {
+    let mut left_value = expr3(lexer)?;
     loop {
-        let t = lexer.lookahead(1);
+        let t = lexer.lookahead(1)?.token;
         match t {
             Token::And => {
-                left_value = and_expr(&mut lexer, left_value);
+                left_value = and_expr(lexer, left_value)?;
             },
-            _ => return left_value
+            _ => return Ok(left_value)
         }
     }
 }
 
-fn and_expr(mut lexer: &mut Lexer, node: Box<dyn ExprNode>) -> Box<dyn ExprNode> {
-    let operator = lexer.advance();
-    let t = expr3(&mut lexer);
-    Box::new(ArithmeticOpNode {
+fn and_expr(lexer: &mut Lexer, node: Box<dyn ExprNode>) -> Result<Box<dyn ExprNode>, ParseError> {
+    let operator = lexer.advance()?.token;
+    let t = expr3(lexer)?;
+    let span = node.span().merge(&t.span());
+    Ok(Box::new(ArithmeticOpNode {
         operator,
         left_value: Rc::new(node),
         right_value: Rc::new(t),
-    })
+        span,
+    }))
 }
 
-fn expr3(mut lexer: &mut Lexer) -> Box<dyn ExprNode> {
-    let mut left_value = expr4(&mut lexer);
+fn expr3(lexer: &mut Lexer) -> Result<Box<dyn ExprNode>, ParseError> {
+    let mut left_value = expr4(lexer)?;
     loop {
-        let t = lexer.lookahead(1);
+        let t = lexer.lookahead(1)?.token;
         match t {
             Token::Greater | Token::Greaterequal | 
             Token::Less    | Token::Lessequal    |
             Token::Equal   | Token::Notequal => {
-                left_value = arithmetic3_expr(&mut lexer, left_value);
+                left_value = arithmetic3_expr(lexer, left_value)?;
             },
             _ => {
-                return left_value
+                return Ok(left_value)
             }
         }
     }
 }
 
-fn arithmetic3_expr(mut lexer: &mut Lexer, node: Box<dyn ExprNode>) -> Box<dyn ExprNode> {
+fn arithmetic3_expr(lexer: &mut Lexer, node: Box<dyn ExprNode>) -> Result<Box<dyn ExprNode>, ParseError> {
     /*
         > >= < <= != == 
     */
-    let operator = lexer.advance();
-    let t = expr4(&mut lexer);
-    Box::new(ArithmeticOpNode {
+    let operator = lexer.advance()?.token;
+    let t = expr4(lexer)?;
+    let span = node.span().merge(&t.span());
+    Ok(Box::new(ArithmeticOpNode {
         operator,
         left_value: Rc::new(node),
         right_value: Rc::new(t),
-    })
+        span,
+    }))
 }
 
-fn expr4(mut lexer: &mut Lexer) -> Box<dyn ExprNode> {
-    let mut left_value = expr5(&mut lexer);
+fn expr4(lexer: &mut Lexer) -> Result<Box<dyn ExprNode>, ParseError> {
+    let mut left_value = expr5(lexer)?;
     loop {
-        let t = lexer.lookahead(1);
+        let t = lexer.lookahead(1)?.token;
         match t {
             Token::Bitor => {
-                left_value = bit_or_expr(&mut lexer, left_value);
+                left_value = bit_or_expr(lexer, left_value)?;
             },
             _ => {
-                return left_value
+                return Ok(left_value)
             }
         }
     }
 }
 
-fn bit_or_expr(mut lexer: &mut Lexer, node: Box<dyn ExprNode>) -> Box<dyn ExprNode> {
-    let operator = lexer.advance();
-    let t = expr5(&mut lexer);
-    Box::new(ArithmeticOpNode {
+fn bit_or_expr(lexer: &mut Lexer, node: Box<dyn ExprNode>) -> Result<Box<dyn ExprNode>, ParseError> {
+    let operator = lexer.advance()?.token;
+    let t = expr5(lexer)?;
+    let span = node.span().merge(&t.span());
+    Ok(Box::new(ArithmeticOpNode {
         operator,
         left_value: Rc::new(node),
         right_value: Rc::new(t),
-    })
+        span,
+    }))
 }
 
-fn expr5(mut lexer: &mut Lexer) -> Box<dyn ExprNode> {
-    let mut left_value = expr6(&mut lexer);
+fn expr5(lexer: &mut Lexer) -> Result<Box<dyn ExprNode>, ParseError> {
+    let mut left_value = expr6(lexer)?;
     loop {
-        let t = lexer.lookahead(1);
+        let t = lexer.lookahead(1)?.token;
         match t {
             Token::Bitxor => {
-                left_value = bit_xor_expr(&mut lexer, left_value);
+                left_value = bit_xor_expr(lexer, left_value)?;
             },
             _ => {
-                return left_value
+                return Ok(left_value)
             }
         }
     }
 }
 
-fn bit_xor_expr(mut lexer: &mut Lexer, node: Box<dyn ExprNode>) -> Box<dyn ExprNode> {
-    let operator = lexer.advance();
-    let t = expr6(&mut lexer);
-    Box::new(ArithmeticOpNode {
+fn bit_xor_expr(lexer: &mut Lexer, node: Box<dyn ExprNode>) -> Result<Box<dyn ExprNode>, ParseError> {
+    let operator = lexer.advance()?.token;
+    let t = expr6(lexer)?;
+    let span = node.span().merge(&t.span());
+    Ok(Box::new(ArithmeticOpNode {
         operator,
         left_value: Rc::new(node),
         right_value: Rc::new(t),
-    })
+        span,
+    }))
 }
 
-fn expr6(mut lexer: &mut Lexer) -> Box<dyn ExprNode> {
-    let mut left_value = expr7(&mut lexer);
+fn expr6(lexer: &mut Lexer) -> Result<Box<dyn ExprNode>, ParseError> {
+    let mut left_value = expr7(lexer)?;
     loop {
-        let t = lexer.lookahead(1);
+        let t = lexer.lookahead(1)?.token;
         match t {
             Token::Bitand => {
-                left_value = bit_and_expr(&mut lexer, left_value);
+                left_value = bit_and_expr(lexer, left_value)?;
             },
             _ => {
-                return left_value
+                return Ok(left_value)
             }
         }
     }
 }
 
-fn bit_and_expr(mut lexer: &mut Lexer, node: Box<dyn ExprNode>) -> Box<dyn ExprNode> {
-    let operator = lexer.advance();
-    let t = expr7(&mut lexer);
-    Box::new(ArithmeticOpNode {
+fn bit_and_expr(lexer: &mut Lexer, node: Box<dyn ExprNode>) -> Result<Box<dyn ExprNode>, ParseError> {
+    let operator = lexer.advance()?.token;
+    let t = expr7(lexer)?;
+    let span = node.span().merge(&t.span());
+    Ok(Box::new(ArithmeticOpNode {
         operator,
         left_value: Rc::new(node),
         right_value: Rc::new(t),
-    })
+        span,
+    }))
 }
     
-fn expr7(mut lexer: &mut Lexer) -> Box<dyn ExprNode> {
-    let mut left_value = expr8(&mut lexer);
+fn expr7(lexer: &mut Lexer) -> Result<Box<dyn ExprNode>, ParseError> {
+    let mut left_value = expr8(lexer)?;
     loop {
-        let t = lexer.lookahead(1);
+        let t = lexer.lookahead(1)?.token;
         match t {
             Token::Rightshift | Token::Leftshift => {
-                left_value = arithmetic2_expr(&mut lexer, left_value);
+                left_value = arithmetic2_expr(lexer, left_value)?;
             },
             _ => {
-                return left_value
+                return Ok(left_value)
             }
         }
     }
 }
 
-fn arithmetic2_expr(mut lexer: &mut Lexer, node: Box<dyn ExprNode>) -> Box<dyn ExprNode> {
+fn arithmetic2_expr(lexer: &mut Lexer, node: Box<dyn ExprNode>) -> Result<Box<dyn ExprNode>, ParseError> {
     /*
         << >>
     */
-    let operator = lexer.advance();
-    let t = expr8(&mut lexer);
-    Box::new(ArithmeticOpNode {
+    let operator = lexer.advance()?.token;
+    let t = expr8(lexer)?;
+    let span = node.span().merge(&t.span());
+    Ok(Box::new(ArithmeticOpNode {
         operator,
         left_value: Rc::new(node),
         right_value: Rc::new(t),
-    })
+        span,
+    }))
 }
 
-fn expr8(mut lexer: &mut Lexer) -> Box<dyn ExprNode> {
-    let mut left_value = expr9(&mut lexer);
+fn expr8(lexer: &mut Lexer) -> Result<Box<dyn ExprNode>, ParseError> {
+    let mut left_value = expr9(lexer)?;
     loop {
-        let t = lexer.lookahead(1);
+        let t = lexer.lookahead(1)?.token;
         match t {
             Token::Add| Token::Sub => {
-                left_value = arithmetic1_expr(&mut lexer, left_value);
+                left_value = arithmetic1_expr(lexer, left_value)?;
             },
             _ => {
-                return left_value
+                return Ok(left_value)
             }
         }
     }
 }
 
-fn arithmetic1_expr(mut lexer: &mut Lexer, node: Box<dyn ExprNode>) -> Box<dyn ExprNode> {
+fn arithmetic1_expr(lexer: &mut Lexer, node: Box<dyn ExprNode>) -> Result<Box<dyn ExprNode>, ParseError> {
     /*
         add and sub
     */
-    let operator = lexer.advance();
-    let t = expr9(&mut lexer);
-    Box::new(ArithmeticOpNode {
+    let operator = lexer.advance()?.token;
+    let t = expr9(lexer)?;
+    let span = node.span().merge(&t.span());
+    Ok(Box::new(ArithmeticOpNode {
         operator,
         left_value: Rc::new(node),
         right_value: Rc::new(t),
-    })
+        span,
+    }))
 }
 
-fn expr9(mut lexer: &mut Lexer) -> Box<dyn ExprNode> {
-    let mut left_value = term(&mut lexer);
+fn expr9(lexer: &mut Lexer) -> Result<Box<dyn ExprNode>, ParseError> {
+    let mut left_value = term(lexer)?;
     loop {
-        let t = lexer.lookahead(1);
+        let t = lexer.lookahead(1)?.token;
         match t {
             Token::Mul | Token::Div | Token::Mod=> {
-                left_value = arithmetic0_expr(&mut lexer, left_value);
+                left_value = arithmetic0_expr(lexer, left_value)?;
             },
             _ => {
-                return left_value
+                return Ok(left_value)
             },
         } 
     }
 }
 
-fn arithmetic0_expr(mut lexer: &mut Lexer, node: Box<dyn ExprNode>) -> Box<dyn ExprNode> {
+fn arithmetic0_expr(lexer: &mut Lexer, node: Box<dyn ExprNode>) -> Result<Box<dyn ExprNode>, ParseError> {
     /*
         Three arithmetic operators of the same priority, multiply, divide and mod
     */ 
-    let operator = lexer.advance();
-    let t = term(&mut lexer);
-    Box::new(ArithmeticOpNode {
+    let operator = lexer.advance()?.token;
+    let t = term(lexer)?;
+    let span = node.span().merge(&t.span());
+    Ok(Box::new(ArithmeticOpNode {
         operator,
         left_value: Rc::new(node),
         right_value: Rc::new(t),
-    })
+        span,
+    }))
 }
 
-fn term(mut lexer: &mut Lexer) -> Box<dyn ExprNode> {
+fn term(lexer: &mut Lexer) -> Result<Box<dyn ExprNode>, ParseError> {
+    let start = lexer.next_location()?;
     let mut case_type = None;
-    let node;
-    if lexer.lookahead(1) == Token::LParentheses {
-        if is_base_type(&lexer.lookahead(2)) {
-            lexer.advance();
-            case_type = Some(typeref(&mut lexer));
-            lexer.matcher(Token::RParentheses);
+    
+    if lexer.lookahead(1)?.token == Token::LParentheses
+        && (is_base_type(&lexer.lookahead(2)?.token) || is_qualifier(&lexer.lookahead(2)?.token)) {
+            lexer.advance()?;
+            case_type = Some(typeref(lexer)?);
+            lexer.matcher(Token::RParentheses)?;
         }
-    }
     
-    node = Rc::new(unary(&mut lexer));
+    let node = Rc::new(unary(lexer)?);
 
-    Box::new(TermNode {
+    Ok(Box::new(TermNode {
         case_type,
         unary: node,
-    })
+        span: lexer.span_from(start),
+    }))
 }
 
-fn unary(mut lexer: &mut Lexer) -> Box<dyn UnaryNode> {
+fn unary(lexer: &mut Lexer) -> Result<Box<dyn UnaryNode>, ParseError> {
+    let start = lexer.next_location()?;
     let mut t = None;
-    let la = lexer.lookahead(1);
+    let la = lexer.lookahead(1)?.token;
     if is_prefix_op(&la) {
-        lexer.advance();
+        lexer.advance()?;
         t = Some(la);
     }
 
-    let pn = primary(&mut lexer);
+    let la = lexer.lookahead(1)?.token;
+    if la == Token::Sizeof || la == Token::Alignof {
+        return sizeof_expr(lexer, t, start)
+    }
+
+    let pn = primary(lexer)?;
 
-    if is_postfix_op(&lexer.lookahead(1)) {
-        match lexer.lookahead(1) {
+    if is_postfix_op(&lexer.lookahead(1)?.token) {
+        match lexer.lookahead(1)?.token {
             Token::Dot | Token::PointerRef => {
-                let op = lexer.advance();
-                return Box::new(RefUnaryNode {
+                let op = lexer.advance()?.token;
+                return Ok(Box::new(RefUnaryNode {
                     prefix: t,
                     operator: op,
                     primary: pn,
-                    postfix: Some(Rc::new(unary(&mut lexer))),
-                })
+                    postfix: Some(Rc::new(unary(lexer)?)),
+                    span: lexer.span_from(start),
+                }))
             },
-            // Token::PointerRef => {
-            //     lexer.advance();
-            //     return Box::new(PointerRefUnaryNode {
-            //         prefix: t,
-            //         primary: pn,
-            //         postfix: Some(Rc::new(unary(&mut lexer))),
-            //     })
-            // },
             Token::Inc => {
-                return Box::new(SelfOpUnaryNode {
+                return Ok(Box::new(SelfOpUnaryNode {
                     prefix: t,
                     primary: pn,
-                    postfix: lexer.advance(),
-                })
+                    postfix: lexer.advance()?.token,
+                    span: lexer.span_from(start),
+                }))
             },
             Token::Dec => {
-                return Box::new(SelfOpUnaryNode {
+                return Ok(Box::new(SelfOpUnaryNode {
                     prefix: t,
                     primary: pn,
-                    postfix: lexer.advance(),
-                })
+                    postfix: lexer.advance()?.token,
+                    span: lexer.span_from(start),
+                }))
             },
             Token::LParentheses => {
-                return Box::new(FuncCallNode {
+                return Ok(Box::new(FuncCallNode {
                     prefix: t,
                     primary: pn,
-                    params: func_call_params_expr(&mut lexer),
-                })
+                    params: func_call_params_expr(lexer)?,
+                    span: lexer.span_from(start),
+                }))
             },
             Token::LBrackets => {
-                return Box::new(ArrayUnaryNode {
+                return Ok(Box::new(ArrayUnaryNode {
                     prefix: t,
                     primary: pn,
-                    postfix: array_expr(&mut lexer),
-                })
+                    postfix: array_expr(lexer)?,
+                    span: lexer.span_from(start),
+                }))
             }
-            _ => panic!("unexcept token!")
+            t => return Err(ParseError::unexpect(lexer, t)),
             // TODO other type
         }
     }
 
-    Box::new(SingeUnaryNode {
+    Ok(Box::new(SingeUnaryNode {
         prefix: t,
         primary: pn,
-    })
+        span: lexer.span_from(start),
+    }))
+}
+
+fn sizeof_expr(lexer: &mut Lexer, prefix: Option<Token>, start: Location) -> Result<Box<dyn UnaryNode>, ParseError> {
+    /*
+        ( sizeof | alignof ) ( ( typeref ) | ( expr ) | unary )
+    */
+    let operator = lexer.advance()?.token;
+    let target = if lexer.lookahead(1)?.token == Token::LParentheses {
+        lexer.advance()?;
+        let t = lexer.lookahead(1)?.token;
+        // 括号里的表达式直接取出来，不然括号表达式推导不出类型
+        let target = if is_base_type(&t) || is_qualifier(&t) {
+            SizeofTarget::Type(typeref(lexer)?)
+        } else {
+            SizeofTarget::Expr(Rc::new(comma_expr(lexer)?))
+        };
+        lexer.matcher(Token::RParentheses)?;
+        target
+    } else {
+        let node = unary(lexer)?;
+        let span = node.span();
+        SizeofTarget::Expr(Rc::new(Box::new(TermNode {
+            case_type: None,
+            unary: Rc::new(node),
+            span,
+        })))
+    };
+
+    Ok(Box::new(SizeofNode {
+        prefix,
+        operator,
+        target,
+        span: lexer.span_from(start),
+    }))
 }
 
-fn array_expr(mut lexer: &mut Lexer) -> Vec<Box<dyn ExprNode>> {
+fn array_expr(lexer: &mut Lexer) -> Result<Vec<Box<dyn ExprNode>>, ParseError> {
     let mut v = Vec::new();
-    lexer.matcher(Token::LBrackets);
-    let mut expr = expr0(&mut lexer);
+    lexer.matcher(Token::LBrackets)?;
+    let mut expr = comma_expr(lexer)?;
     v.push(expr);
-    lexer.matcher(Token::RBrackets);
+    lexer.matcher(Token::RBrackets)?;
 
     loop {
-        if lexer.lookahead(1) == Token::LBrackets {
-            lexer.matcher(Token::LBrackets);
-            expr = expr0(&mut lexer);
+        if lexer.lookahead(1)?.token == Token::LBrackets {
+            lexer.matcher(Token::LBrackets)?;
+            expr = comma_expr(lexer)?;
             v.push(expr);
-            lexer.matcher(Token::RBrackets);
+            lexer.matcher(Token::RBrackets)?;
         } else {
             break;
         }
     }
 
-    return v
+    return Ok(v)
 }
 
+pub fn init_list(lexer: &mut Lexer) -> Result<Box<dyn ExprNode>, ParseError> {
+    /*
+        { [ init_item ( , init_item ) * [ , ] ] }
+    */
+    let start = lexer.next_location()?;
+    lexer.matcher(Token::LBrace)?;
+    let mut items = Vec::new();
+    while lexer.lookahead(1)?.token != Token::RBrace {
+        items.push(init_item(lexer)?);
+        if lexer.lookahead(1)?.token != Token::Comma {
+            break;
+        }
+        lexer.advance()?;
+    }
+    lexer.matcher(Token::RBrace)?;
+
+    Ok(Box::new(InitListNode {
+        items,
+        span: lexer.span_from(start),
+    }))
+}
 
-fn func_call_params_expr(mut lexer: &mut Lexer) -> Option<Vec<Rc<Box<dyn ExprNode>>>> {
-    if lexer.lookahead(2) == Token::RParentheses {
-        lexer.advance();
-        lexer.advance();
-        return None
+fn init_item(lexer: &mut Lexer) -> Result<InitItemNode, ParseError> {
+    /*
+        [ . name = | [ expr ] = ] ( expr | init_list )
+    */
+    let start = lexer.next_location()?;
+    let t = lexer.lookahead(1)?.token;
+    let designator = match t {
+        Token::Dot => {
+            lexer.advance()?;
+            let t = lexer.advance()?.token;
+            let name = match t {
+                Token::Name(name) => name,
+                _ => return Err(ParseError::unexpect(lexer, t)),
+            };
+            lexer.matcher(Token::Assgin)?;
+            Some(Designator::Member(name))
+        },
+        Token::LBrackets => {
+            lexer.advance()?;
+            let index = expr0(lexer)?;
+            lexer.matcher(Token::RBrackets)?;
+            lexer.matcher(Token::Assgin)?;
+            Some(Designator::Index(Rc::new(index)))
+        },
+        _ => None,
+    };
+    let value = if lexer.lookahead(1)?.token == Token::LBrace {
+        init_list(lexer)?
     } else {
-        lexer.advance();
+        expr0(lexer)?
+    };
+
+    Ok(InitItemNode {
+        designator,
+        value: Rc::new(value),
+        span: lexer.span_from(start),
+    })
+}
+
+fn func_call_params_expr(lexer: &mut Lexer) -> Result<CallParams, ParseError> {
+    if lexer.lookahead(2)?.token == Token::RParentheses {
+        lexer.advance()?;
+        lexer.advance()?;
+        return Ok(None)
+    } else {
+        lexer.advance()?;
         let mut params = Vec::new();
-        params.push(Rc::new(expr0(&mut lexer)));
+        params.push(Rc::new(expr0(lexer)?));
 
         loop {
-            if lexer.lookahead(1) != Token::Comma {
+            if lexer.lookahead(1)?.token != Token::Comma {
                 break;
             }
-            lexer.advance();
-            params.push(Rc::new(expr0(&mut lexer)));
+            lexer.advance()?;
+            params.push(Rc::new(expr0(lexer)?));
         }
-        lexer.matcher(Token::RParentheses);
+        lexer.matcher(Token::RParentheses)?;
 
-        return Some(params)
+        return Ok(Some(params))
     }
 
 
 }
 
-fn primary(mut lexer: &mut Lexer) -> PrimaryNode {
-    let t = lexer.advance();
+fn primary(lexer: &mut Lexer) -> Result<PrimaryNode, ParseError> {
+    let start = lexer.next_location()?;
+    let t = lexer.advance()?.token;
     let name = None;
-    match t {
-        Token::Number(i) => PrimaryNode {
+    let node = match t {
+        Token::Number(i, literal_type) => PrimaryNode {
             name,
-            value: Const::Integer(i),
+            value: Const::Integer(i, literal_type),
+            span: lexer.span_from(start.clone()),
+        },
+        Token::FloatNumber(n, literal_type) => PrimaryNode {
+            name,
+            value: Const::Float(n, literal_type),
+            span: lexer.span_from(start.clone()),
         },
         Token::Character(c) => PrimaryNode {
             name,
             value: Const::Char(c),
+            span: lexer.span_from(start.clone()),
         },
         Token::String(s) => PrimaryNode {
             name,
             value: Const::String(s),
+            span: lexer.span_from(start.clone()),
         },
         Token::Name(s) => PrimaryNode {
             name: Some(s),
             value: Const::Identifier,
+            span: lexer.span_from(start.clone()),
         },
         Token::LParentheses => {
-            let value = expr0(&mut lexer);
-            lexer.matcher(Token::RParentheses);
+            let value = comma_expr(lexer)?;
+            lexer.matcher(Token::RParentheses)?;
             PrimaryNode {
                 name,
                 value: Const::ParenthesesExpr(Rc::new(value)),
+                span: lexer.span_from(start.clone()),
             }
         },
-        _ => panic!("unexcept token! {}", t)
-    }
+        _ => return Err(ParseError {
+            location: start,
+            error: ParseErrorType::UnexpectToken(t),
+        }),
+    };
+
+    Ok(node)
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use lex::token::LiteralType;
 
     #[test]
     fn test_expr9() {
         // let mut lxr = Lexer::new(String::from("+a->b.c->d = 1"));
         let mut lxr = Lexer::new(String::from("6 * 5 % 4 * 3 * 2 / 1"));
-        let node = expr9(&mut lxr);
+        let node = expr9(&mut lxr).unwrap();
         println!("{:?}", node);
     }
 
     #[test]
     fn test_expr8() {
         let mut lxr = Lexer::new(String::from("7 / 6 % 5 + 4 * 3 + 2 / 1"));
-        let node = expr8(&mut lxr);
+        let node = expr8(&mut lxr).unwrap();
         println!("{:?}", node);
     }
 
     #[test]
     fn test_expr7() {
         let mut lxr = Lexer::new(String::from("8 / 7 >> 6 % 5 + 4 * 3 + 2 / 1"));
-        let node = expr7(&mut lxr);
+        let node = expr7(&mut lxr).unwrap();
         println!("{:?}", node);
     }
 
     #[test]
     fn test_expr6() {
         let mut lxr = Lexer::new(String::from("8 / 7 >> 6 & 4 * 3 + 2 / 1"));
-        let node = expr6(&mut lxr);
+        let node = expr6(&mut lxr).unwrap();
         println!("{:?}", node);
     }
 
     #[test]
     fn test_expr5() {
         let mut lxr = Lexer::new(String::from("8 / 7 >> 6 & 4 * 3 ^ 2 / 1"));
-        let node = expr5(&mut lxr);
+        let node = expr5(&mut lxr).unwrap();
         println!("{:?}", node);
     }
 
     #[test]
     fn test_expr4() {
         let mut lxr = Lexer::new(String::from("8 / 7 >> 6 & 4 | 3 ^ 2 * 1"));
-        let node = expr4(&mut lxr);
+        let node = expr4(&mut lxr).unwrap();
         println!("{:?}", node);
     }
 
     #[test]
     fn test_expr3() {
         let mut lxr = Lexer::new(String::from("8 / 7 >> 6 & 4 >= 3 ^ 2 * 1"));
-        let node = expr3(&mut lxr);
+        let node = expr3(&mut lxr).unwrap();
         println!("{:?}", node);
     }
 
     #[test]
     fn test_expr2() {
         let mut lxr = Lexer::new(String::from("8 / 7 >> 6 & 4 >= 3 ^ 2 && 1"));
-        let node = expr2(&mut lxr);
+        let node = expr2(&mut lxr).unwrap();
         println!("{:?}", node);
     }
 
     #[test]
     fn test_expr1() {
         let mut lxr = Lexer::new(String::from("8 / 7 >> 6 & 4 || 3 ^ 2 && 1"));
-        let node = expr1(&mut lxr);
+        let node = expr1(&mut lxr).unwrap();
         println!("{:?}", node);
     }
 
     #[test]
     fn test_expr0() {
         let mut lxr = Lexer::new(String::from("(int *[]) a->b.c = 7++ >> 6 & (4 || 3) ^ 2 && 1 + func(2, 3) * 9 / b[1][2]"));
-        let node = expr0(&mut lxr);
+        let node = expr0(&mut lxr).unwrap();
         println!("{:?}", node);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_assgin_and_conditional() {
+        let mut lxr = Lexer::new(String::from("a = b <<= c ? d || e : f ? g : h"));
+        let node = format!("{:?}", expr0(&mut lxr).unwrap());
+        // 赋值是右结合的，条件运算符的优先级在||和赋值之间
+        assert!(node.starts_with("AssginmentNode { operator: Assgin, left_value: TermNode"));
+        assert!(node.contains("right_value: AssginmentNode { operator: LeftshiftAssgin"));
+        assert!(node.contains("then_value: ArithmeticOpNode { operator: Or"));
+        assert!(node.contains("else_value: ConditionalNode"));
+
+        let mut lxr = Lexer::new(String::from("a ? b c"));
+        assert!(expr0(&mut lxr).is_err());
+    }
+
+    #[test]
+    fn test_primary_literal() {
+        let mut lxr = Lexer::new(String::from("0x1F 2.5e1 0xFFFFFFFFFFFFFFFFULL 2.0f"));
+        let node = primary(&mut lxr).unwrap();
+        assert!(matches!(node.value, Const::Integer(31, LiteralType::Int)));
+        let node = primary(&mut lxr).unwrap();
+        assert!(matches!(node.value, Const::Float(n, LiteralType::Double) if n == 25.0));
+        let node = primary(&mut lxr).unwrap();
+        assert!(matches!(node.value, Const::Integer(u64::MAX, LiteralType::UnsignedLong)));
+        let node = primary(&mut lxr).unwrap();
+        assert!(matches!(node.value, Const::Float(n, LiteralType::Float) if n == 2.0));
+    }
+
+    #[test]
+    fn test_comma() {
+        let mut lxr = Lexer::new(String::from("a = 1, f(b, (c, d)), e"));
+        let node = format!("{:?}", comma_expr(&mut lxr).unwrap());
+        // 逗号是左结合的，函数参数里的逗号还是分隔符
+        assert!(node.starts_with("CommaNode { left_value: CommaNode { left_value: AssginmentNode"));
+        assert_eq!(2, node.matches("CommaNode").count() - 1);
+        assert!(node.contains("params: Some([TermNode"));
+
+        let mut lxr = Lexer::new(String::from("a, b"));
+        assert!(!format!("{:?}", expr0(&mut lxr).unwrap()).contains("CommaNode"));
+    }
+
+    #[test]
+    fn test_sizeof() {
+        let mut lxr = Lexer::new(String::from("sizeof(struct student) * sizeof x + -alignof(int[10]) - sizeof(a)"));
+        let node = format!("{:?}", expr0(&mut lxr).unwrap());
+        assert!(node.contains("operator: Sizeof, target: Type(TypeNode { type_base: TypeBase { base: Struct, name: Some(\"student\") }"));
+        assert!(node.contains("operator: Sizeof, target: Expr(TermNode { case_type: None, unary: SingeUnaryNode"));
+        assert!(node.contains("prefix: Some(Sub), operator: Alignof, target: Type(TypeNode { type_base: TypeBase { base: Int, name: None }, nested_def: [FixedArray(10)]"));
+        assert!(node.contains("operator: Sizeof, target: Expr(TermNode"));
+
+        let mut lxr = Lexer::new(String::from("sizeof(int"));
+        assert!(expr0(&mut lxr).is_err());
+    }
+}
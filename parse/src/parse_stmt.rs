@@ -1,198 +1,327 @@
 use lex::lexer::Lexer;
+use crate::error::ParseError;
 use lex::token:: {
     Token,
     is_base_type,
+    is_qualifier,
 };
 use crate::ast_stmt:: {
     StmtNode,
     IfStmtNode,
     ExprStmtNode,
     BlockNode,
+    BlockItem,
     WhileStmtNode,
     DoWhileStmtNode,
     ForStmtNode,
+    SwitchStmtNode,
+    CaseNode,
     ReturnStmtNode,
+    GotoStmtNode,
+    LabeledStmtNode,
     BreakStmtNode,
     ContinueStmtNode,
 };
-use crate::parse_expr::expr0;
+use crate::parse_expr:: {
+    expr0,
+    comma_expr,
+};
 use crate::parse_def:: {
     defvar,
     typeref,
 };
 use std::rc::Rc;
+use crate::ast_expr::ExprNode;
 use crate::ast::DefNode;
 
-fn statement(mut lexer: &mut Lexer) -> Box<dyn StmtNode> {
-    let t = lexer.lookahead(1);
-    let stmt: Box<dyn StmtNode>;
-    match t {
-        Token::LBrace => {
-            stmt = block(&mut lexer);
-        }
-        Token::If => {
-            stmt = if_stmt(&mut lexer);
-        },
-        Token::While => {
-            stmt = while_stmt(&mut lexer);
-        },
-        Token::Do => {
-            stmt = do_while_stmt(&mut lexer);
-        },
-        Token::For => {
-            stmt = for_stmt(&mut lexer);
-        },
-        Token::Break => {
-            stmt = break_stmt(&mut lexer);
-        },
-        Token::Continue => {
-            stmt = continue_stmt(&mut lexer);
-        }
-        Token::Return => {
-            stmt = return_stmt(&mut lexer);
-        },
-        _ => {
-            stmt = expr(&mut lexer);
-        }
-    }
-
-    return stmt
+fn statement(lexer: &mut Lexer) -> Result<Box<dyn StmtNode>, ParseError> {
+    let t = lexer.lookahead(1)?.token;
+    let stmt: Box<dyn StmtNode> = match t {
+        Token::LBrace => block(lexer)?,
+        Token::If => if_stmt(lexer)?,
+        Token::While => while_stmt(lexer)?,
+        Token::Do => do_while_stmt(lexer)?,
+        Token::For => for_stmt(lexer)?,
+        Token::Switch => switch_stmt(lexer)?,
+        Token::Break => break_stmt(lexer)?,
+        Token::Continue => continue_stmt(lexer)?,
+        Token::Return => return_stmt(lexer)?,
+        Token::Goto => goto_stmt(lexer)?,
+        Token::Name(_) if lexer.lookahead(2)?.token == Token::Colon => labeled_stmt(lexer)?,
+        _ => expr(lexer)?,
+    };
+
+    return Ok(stmt)
 }
 
-pub fn block(mut lexer: &mut Lexer) -> Box<dyn StmtNode> {
-    lexer.advance();
+pub fn block(lexer: &mut Lexer) -> Result<Box<dyn StmtNode>, ParseError> {
+    let start = lexer.next_location()?;
+    lexer.advance()?;
+    // block里的声明可以遮住外面的typedef
+    lexer.enter_scope();
 
-    let mut defvars: Vec<Box<dyn DefNode>> = Vec::new();
-    let mut stmts = Vec::new();
+    let mut items = Vec::new();
     loop {
-        let t = lexer.lookahead(1);
-        println!(" block token : {}", t);
+        let t = lexer.lookahead(1)?.token;
         if t == Token::RBrace {
-            lexer.advance();
+            lexer.leave_scope();
+            lexer.advance()?;
             break;
         } else if t == Token::Semi {
-            lexer.advance();
-        } else if is_base_type(&t) {
-            let typeref = typeref(&mut lexer);
-            defvars.push(Box::new(defvar(&mut lexer, typeref)));
+            lexer.advance()?;
+        } else if is_base_type(&t) || is_qualifier(&t) {
+            let start = lexer.next_location()?;
+            let typeref = typeref(lexer)?;
+            items.push(BlockItem::Def(Box::new(defvar(lexer, typeref, start)?)));
         } else {
-            stmts.push(Rc::new(statement(&mut lexer)));
+            items.push(BlockItem::Stmt(Rc::new(statement(lexer)?)));
         }
     }
 
-    
-    Box::new(BlockNode {
-        defvars,
-        stmts,
-    })
+    Ok(Box::new(BlockNode {
+        items,
+        span: lexer.span_from(start),
+    }))
 }
 
-fn if_stmt(mut lexer: &mut Lexer) -> Box<dyn StmtNode> {
-    lexer.advance();
-    lexer.matcher(Token::LParentheses);
-    let condition = expr0(&mut lexer);
-    lexer.matcher(Token::RParentheses);
-    let if_stmt = statement(&mut lexer);
+fn if_stmt(lexer: &mut Lexer) -> Result<Box<dyn StmtNode>, ParseError> {
+    let start = lexer.next_location()?;
+    lexer.advance()?;
+    lexer.matcher(Token::LParentheses)?;
+    let condition = comma_expr(lexer)?;
+    lexer.matcher(Token::RParentheses)?;
+    let if_stmt = statement(lexer)?;
     let mut else_stmt = None;
 
-    if lexer.lookahead(1) == Token::Else {
-        lexer.advance();
-        else_stmt = Some(statement(&mut lexer));
+    if lexer.lookahead(1)?.token == Token::Else {
+        lexer.advance()?;
+        else_stmt = Some(statement(lexer)?);
     }
 
-    Box::new(IfStmtNode {
+    Ok(Box::new(IfStmtNode {
         condition,
         if_stmt,
         else_stmt,
-    })
+        span: lexer.span_from(start),
+    }))
 }
 
-fn expr(mut lexer: &mut Lexer) -> Box<dyn StmtNode> {
-    let expr = expr0(&mut lexer);
-    lexer.matcher(Token::Semi);
+fn expr(lexer: &mut Lexer) -> Result<Box<dyn StmtNode>, ParseError> {
+    let start = lexer.next_location()?;
+    let expr = comma_expr(lexer)?;
+    lexer.matcher(Token::Semi)?;
 
-    Box::new({
+    Ok(Box::new({
         ExprStmtNode {
             expr,
+            span: lexer.span_from(start),
         }
-    })
+    }))
 }
 
-fn while_stmt(mut lexer: &mut Lexer) -> Box<dyn StmtNode> {
-    lexer.advance();
+fn while_stmt(lexer: &mut Lexer) -> Result<Box<dyn StmtNode>, ParseError> {
+    let start = lexer.next_location()?;
+    lexer.advance()?;
 
-    lexer.matcher(Token::LParentheses);
-    let condition = expr0(&mut lexer);
-    lexer.matcher(Token::RParentheses);
-    println!("while next token {}", lexer.lookahead(1));
-    let stmts = statement(&mut lexer);
+    lexer.matcher(Token::LParentheses)?;
+    let condition = comma_expr(lexer)?;
+    lexer.matcher(Token::RParentheses)?;
+    let stmts = statement(lexer)?;
 
-    Box::new(WhileStmtNode {
+    Ok(Box::new(WhileStmtNode {
         condition,
         stmts,
-    })
+        span: lexer.span_from(start),
+    }))
 }
 
-fn do_while_stmt(mut lexer: &mut Lexer) -> Box<dyn StmtNode> {
-    lexer.advance();
-    let stmts = block(&mut lexer);
-    lexer.matcher(Token::While);
-    lexer.matcher(Token::LParentheses);
-    let condition = expr0(&mut lexer);
-    lexer.matcher(Token::RParentheses);
+fn do_while_stmt(lexer: &mut Lexer) -> Result<Box<dyn StmtNode>, ParseError> {
+    let start = lexer.next_location()?;
+    lexer.advance()?;
+    let stmts = block(lexer)?;
+    lexer.matcher(Token::While)?;
+    lexer.matcher(Token::LParentheses)?;
+    let condition = comma_expr(lexer)?;
+    lexer.matcher(Token::RParentheses)?;
 
-    Box::new(DoWhileStmtNode {
+    Ok(Box::new(DoWhileStmtNode {
         condition,
         stmts,
-    })
+        span: lexer.span_from(start),
+    }))
 }
 
-fn for_stmt(mut lexer: &mut Lexer) -> Box<dyn StmtNode> {
-    lexer.advance();
-
-    lexer.matcher(Token::LParentheses);
-    let initial_expr = expr0(&mut lexer);
-    lexer.matcher(Token::Semi);
-    let condition = expr0(&mut lexer);
-    lexer.matcher(Token::Semi);
-    let end_expr = expr0(&mut lexer);
-    lexer.matcher(Token::RParentheses);
-    let stmts = statement(&mut lexer);
-
-    Box::new(ForStmtNode {
+fn for_stmt(lexer: &mut Lexer) -> Result<Box<dyn StmtNode>, ParseError> {
+    let start = lexer.next_location()?;
+    lexer.advance()?;
+
+    lexer.matcher(Token::LParentheses)?;
+    // 初始化部分声明的变量只在循环里有效
+    lexer.enter_scope();
+    let mut initial_def: Option<Box<dyn DefNode>> = None;
+    let mut initial_expr = None;
+    let t = lexer.lookahead(1)?.token;
+    if is_base_type(&t) || is_qualifier(&t) {
+        // defvar会读掉后面的分号
+        let start = lexer.next_location()?;
+        let typeref = typeref(lexer)?;
+        initial_def = Some(Box::new(defvar(lexer, typeref, start)?));
+    } else {
+        initial_expr = optional_expr(lexer, Token::Semi)?;
+        lexer.matcher(Token::Semi)?;
+    }
+    let condition = optional_expr(lexer, Token::Semi)?;
+    lexer.matcher(Token::Semi)?;
+    let end_expr = optional_expr(lexer, Token::RParentheses)?;
+    lexer.matcher(Token::RParentheses)?;
+    let stmts = statement(lexer)?;
+    lexer.leave_scope();
+
+    Ok(Box::new(ForStmtNode {
+        initial_def,
         initial_expr,
         condition,
         end_expr,
         stmts,
-    })
+        span: lexer.span_from(start),
+    }))
+}
+
+/**
+ *  for的三个部分都可以省略，下一个token是end时为空
+ */
+fn optional_expr(lexer: &mut Lexer, end: Token) -> Result<Option<Box<dyn ExprNode>>, ParseError> {
+    if lexer.lookahead(1)?.token == end {
+        return Ok(None)
+    }
+
+    return Ok(Some(comma_expr(lexer)?))
 }
 
-fn break_stmt(mut lexer: &mut Lexer) -> Box<dyn StmtNode> {
-    lexer.advance();
-    lexer.matcher(Token::Semi);
+fn switch_stmt(lexer: &mut Lexer) -> Result<Box<dyn StmtNode>, ParseError> {
+    let start = lexer.next_location()?;
+    lexer.advance()?;
 
-    Box::new(BreakStmtNode {
+    lexer.matcher(Token::LParentheses)?;
+    let condition = comma_expr(lexer)?;
+    lexer.matcher(Token::RParentheses)?;
+    lexer.matcher(Token::LBrace)?;
 
+    let mut cases: Vec<CaseNode> = Vec::new();
+    loop {
+        let t = lexer.lookahead(1)?.token;
+        match t {
+            Token::RBrace => {
+                lexer.advance()?;
+                break;
+            },
+            Token::Semi => {
+                lexer.advance()?;
+            },
+            Token::Case | Token::Default => {
+                cases.push(case_label(lexer)?);
+            },
+            _ => {
+                // 第一个case之前的语句永远执行不到，直接当成语法错误
+                let case = match cases.last_mut() {
+                    Some(case) => case,
+                    None => return Err(ParseError::unexpect(lexer, t)),
+                };
+                case.stmts.push(Rc::new(statement(lexer)?));
+            },
+        }
+    }
+
+    Ok(Box::new(SwitchStmtNode {
+        condition,
+        cases,
+        span: lexer.span_from(start),
+    }))
+}
+
+fn case_label(lexer: &mut Lexer) -> Result<CaseNode, ParseError> {
+    let start = lexer.next_location()?;
+    let value = match lexer.advance()?.token {
+        Token::Case => Some(expr0(lexer)?),
+        _ => None,
+    };
+    lexer.matcher(Token::Colon)?;
+
+    Ok(CaseNode {
+        value,
+        stmts: Vec::new(),
+        span: lexer.span_from(start),
     })
 }
 
-fn continue_stmt(mut lexer: &mut Lexer) -> Box<dyn StmtNode> {
-    lexer.advance();
-    lexer.matcher(Token::Semi);
+fn goto_stmt(lexer: &mut Lexer) -> Result<Box<dyn StmtNode>, ParseError> {
+    let start = lexer.next_location()?;
+    lexer.advance()?;
+    let label = match lexer.advance()?.token {
+        Token::Name(name) => name,
+        t => return Err(ParseError::unexpect(lexer, t)),
+    };
+    lexer.matcher(Token::Semi)?;
+
+    Ok(Box::new(GotoStmtNode {
+        label,
+        span: lexer.span_from(start),
+    }))
+}
 
-    Box::new(ContinueStmtNode {
+fn labeled_stmt(lexer: &mut Lexer) -> Result<Box<dyn StmtNode>, ParseError> {
+    let start = lexer.next_location()?;
+    let label = match lexer.advance()?.token {
+        Token::Name(name) => name,
+        t => return Err(ParseError::unexpect(lexer, t)),
+    };
+    lexer.matcher(Token::Colon)?;
+    // 标签的位置只标到冒号，后面的语句有自己的span
+    let span = lexer.span_from(start);
+    let stmt = if lexer.lookahead(1)?.token == Token::Semi {
+        lexer.advance()?;
+        None
+    } else {
+        Some(statement(lexer)?)
+    };
+
+    Ok(Box::new(LabeledStmtNode {
+        label,
+        stmt,
+        span,
+    }))
+}
 
-    })
+fn break_stmt(lexer: &mut Lexer) -> Result<Box<dyn StmtNode>, ParseError> {
+    let start = lexer.next_location()?;
+    lexer.advance()?;
+    lexer.matcher(Token::Semi)?;
+
+    Ok(Box::new(BreakStmtNode {
+        span: lexer.span_from(start),
+    }))
 }
 
-fn return_stmt(mut lexer: &mut Lexer) -> Box<dyn StmtNode> {
-    lexer.advance();
-    let value = expr0(&mut lexer);
-    lexer.matcher(Token::Semi);
+fn continue_stmt(lexer: &mut Lexer) -> Result<Box<dyn StmtNode>, ParseError> {
+    let start = lexer.next_location()?;
+    lexer.advance()?;
+    lexer.matcher(Token::Semi)?;
 
-    Box::new(ReturnStmtNode {
+    Ok(Box::new(ContinueStmtNode {
+        span: lexer.span_from(start),
+    }))
+}
+
+fn return_stmt(lexer: &mut Lexer) -> Result<Box<dyn StmtNode>, ParseError> {
+    let start = lexer.next_location()?;
+    lexer.advance()?;
+    let value = comma_expr(lexer)?;
+    lexer.matcher(Token::Semi)?;
+
+    Ok(Box::new(ReturnStmtNode {
         value,
-    })
+        span: lexer.span_from(start),
+    }))
 }
 
 
@@ -203,42 +332,95 @@ mod tests {
     #[test]
     fn test_if_stmt() {
         let mut lxr = Lexer::new(String::from("if(1 == 2) { a = 3 + 5; } else { a = 6; }"));
-        let node = statement(&mut lxr);
+        let node = statement(&mut lxr).unwrap();
         println!("{:?}", node);
     }
 
     #[test]
     fn test_block() {
         let mut lxr = Lexer::new(String::from("if(1 == 2) { int *[] a = 1; if (3 == 4) { a = 1; } } else { a = 6; }"));
-        let node = statement(&mut lxr);
+        let node = statement(&mut lxr).unwrap();
         println!("{:?}", node);
     }
 
+    #[test]
+    fn test_block_order() {
+        let mut lxr = Lexer::new(String::from("{ int x = y; y = 2; int z = x; }"));
+        let node = format!("{:?}", block(&mut lxr).unwrap());
+        let def_x = node.find("Def(DefVarNode").unwrap();
+        let stmt = node.find("Stmt(ExprStmtNode").unwrap();
+        let def_z = node.rfind("Def(DefVarNode").unwrap();
+        assert!(def_x < stmt && stmt < def_z);
+    }
+
     #[test]
     fn test_while_stmt() {
         let mut lxr = Lexer::new(String::from("if(1 == 2) { while(3 == 4) { a++; } } else { a = 6; }"));
-        let node = statement(&mut lxr);
+        let node = statement(&mut lxr).unwrap();
         println!("{:?}", node);
     }
 
     #[test]
     fn test_do_while_stmt() {
         let mut lxr = Lexer::new(String::from("if(1 == 2) { do { a = 1 + 2; } while(a < 3) } else { a = 6; }"));
-        let node = statement(&mut lxr);
+        let node = statement(&mut lxr).unwrap();
         println!("{:?}", node);
     }
 
     #[test]
     fn test_for_stmt() {
         let mut lxr = Lexer::new(String::from("if(1 == 2) { for(a = 1; a < 3; a++) { b = 10 + 20; } } else { a = 6; return a; }"));
-        let node = statement(&mut lxr);
+        let node = statement(&mut lxr).unwrap();
         println!("{:?}", node);
     }
 
+    #[test]
+    fn test_for_optional() {
+        let mut lxr = Lexer::new(String::from("for (;;) { break; }"));
+        let node = format!("{:?}", statement(&mut lxr).unwrap());
+        assert!(node.starts_with("ForStmtNode { initial_def: None, initial_expr: None, condition: None, end_expr: None"));
+
+        let mut lxr = Lexer::new(String::from("for (int i = 0, j = 1; i < j;) a++;"));
+        let node = format!("{:?}", statement(&mut lxr).unwrap());
+        assert!(node.starts_with("ForStmtNode { initial_def: Some(DefVarNode"));
+        assert!(node.contains("initial_expr: None, condition: Some(ArithmeticOpNode"));
+        assert!(node.contains("end_expr: None"));
+
+        for source in ["for (;) a++;", "for (int i = 0) a++;", "for (a = 1; a < 2; a++;) a++;"].iter() {
+            let mut lxr = Lexer::new(String::from(*source));
+            assert!(statement(&mut lxr).is_err(), "{}", source);
+        }
+    }
+
     #[test]
     fn test_break_continue_stmt() {
         let mut lxr = Lexer::new(String::from("if(1 == 2) { for(a = 1; a < 3; a++) { b = 10 + 20; } } else { a = 6; break; continue; return a; }"));
-        let node = statement(&mut lxr);
+        let node = statement(&mut lxr).unwrap();
         println!("{:?}", node);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_switch_stmt() {
+        let mut lxr = Lexer::new(String::from("switch (a) { case 1: case 2 + 1: a = 1; break; default: { a = 2; } case 'c': ; }"));
+        let node = format!("{:?}", statement(&mut lxr).unwrap());
+        assert_eq!(4, node.matches("CaseNode").count());
+        assert!(node.contains("CaseNode { value: None"));
+
+        let mut lxr = Lexer::new(String::from("switch (a) { a = 1; case 1: break; }"));
+        assert!(statement(&mut lxr).is_err());
+        let mut lxr = Lexer::new(String::from("switch (a) { case 1 break; }"));
+        assert!(statement(&mut lxr).is_err());
+    }
+
+    #[test]
+    fn test_goto_stmt() {
+        let mut lxr = Lexer::new(String::from("{ again: a = a + 1; if (a < 10) goto again; end: ; }"));
+        let node = format!("{:?}", statement(&mut lxr).unwrap());
+        assert!(node.contains("LabeledStmtNode { label: \"again\", stmt: Some(ExprStmtNode"));
+        assert!(node.contains("GotoStmtNode { label: \"again\""));
+        assert!(node.contains("LabeledStmtNode { label: \"end\", stmt: None"));
+
+        let mut lxr = Lexer::new(String::from("goto 1;"));
+        assert!(statement(&mut lxr).is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use lex::lexer::Lexer;
+use crate::error::ParseError;
 use lex::token::Token;
 use crate::ast:: {
     ProgramNode,
@@ -9,29 +10,37 @@ use crate::parse_def:: {
     top_defs,
 };
 
-pub fn parse(mut lexer: &mut Lexer) -> ProgramNode {
-    return compilation_unit(&mut lexer)
+pub fn parse(lexer: &mut Lexer) -> Result<ProgramNode, ParseError> {
+    return compilation_unit(lexer)
 }
 
-fn compilation_unit(mut lexer: &mut Lexer) -> ProgramNode {
+fn compilation_unit(lexer: &mut Lexer) -> Result<ProgramNode, ParseError> {
     // import_stmts + defs + EOF
     let mut import_stmts: Vec<ImportStmtNode> = Vec::new();
-    let token = lexer.lookahead(1);
-    println!("=================== {}", token);
+    let token = lexer.lookahead(1)?.token;
     if token == Token::Import {
-        import_stmts = import_statements(&mut lexer);
+        import_stmts = import_statements(lexer)?;
+    }
+    let defs = top_defs(lexer)?;
+    // top_defs遇到不认识的token就停下，后面只能是文件结尾
+    let token = lexer.lookahead(1)?.token;
+    if token != Token::Eof {
+        return Err(ParseError::unexpect(lexer, token))
     }
-    let defs = top_defs(&mut lexer);
 
-    ProgramNode {
+    Ok(ProgramNode {
         import_stmts,
         defs,
-    }
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::ParseErrorType;
+    use lex::error::LexicalErrorType;
+    use lex::location::Location;
+    use lex::token::LiteralType;
 
     #[test]
     fn test_parse() {
@@ -73,6 +82,38 @@ mod tests {
                 return a;
             }
         "));
-        println!("{:?}", compilation_unit(&mut lxr));
+        println!("{:?}", compilation_unit(&mut lxr).unwrap());
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let mut lxr = Lexer::new(String::from("int main() { return 0 }"));
+        let err = parse(&mut lxr).unwrap_err();
+        assert_eq!(ParseErrorType::Lexical(LexicalErrorType::UnexpectToken {
+            expect: Token::Semi,
+            found: Token::RBrace,
+        }), err.error);
+
+        let mut lxr = Lexer::new(String::from("struct 1 {};"));
+        let err = parse(&mut lxr).unwrap_err();
+        assert_eq!(ParseErrorType::UnexpectToken(Token::Number(1, LiteralType::Int)), err.error);
+
+        let mut lxr = Lexer::new(String::from("+ 2\nint main() { return 0; }"));
+        let err = parse(&mut lxr).unwrap_err();
+        assert_eq!(ParseErrorType::UnexpectToken(Token::Add), err.error);
+        assert_eq!(Location::new(1, 1), err.location);
+
+        let mut lxr = Lexer::new(String::from("int main() { return 0; }\n}"));
+        let err = parse(&mut lxr).unwrap_err();
+        assert_eq!(ParseErrorType::UnexpectToken(Token::RBrace), err.error);
+        assert_eq!(Location::new(2, 1), err.location);
+    }
+
+    #[test]
+    fn test_parse_error_location() {
+        let mut lxr = Lexer::new(String::from("int main() {\n    int a = 1;\n    a = ;\n}"));
+        let err = parse(&mut lxr).unwrap_err();
+        assert_eq!(ParseErrorType::UnexpectToken(Token::Semi), err.error);
+        assert_eq!(Location::new(3, 9), err.location);
     }
-}
\ No newline at end of file
+}
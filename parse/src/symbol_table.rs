@@ -1,29 +1,60 @@
 use std::fmt;
-use std::collections::HashMap;
+use std::collections:: {
+    HashMap,
+    HashSet,
+};
+use std::cmp::max;
 use crate::ast:: {
     DefStructNode,
     DefVarNode,
     DefFuncNode,
+    DefTypedefNode,
+    DefEnumNode,
+    FuncType,
+    Qualifier,
     TypeDef,
+    TypeNode,
+    TypeBase,
 };
 use std::rc::Rc;
 use std::cell::RefCell;
-use lex::token::Token;
+use lex::token:: {
+    Token,
+    is_aggregate,
+};
+use lex::location::Span;
+use crate::error:: {
+    SemanticError,
+    SemanticErrorType,
+};
 
 pub struct TopLevelScope {
     pub global_define_map: HashMap<String, DefStructNode>,
     pub func_map: HashMap<String, DefFuncNode>,
+    pub typedef_map: HashMap<String, DefTypedefNode>,
+    pub enum_map: HashMap<String, DefEnumNode>,
+    // 枚举成员的值和定义的位置
+    pub enum_constants: HashMap<String, (i64, Span)>,
     pub scopes: HashMap<String, Rc<RefCell<LocalScope>>>,
     pub scope_stack: Vec<Rc<RefCell<LocalScope>>>,
     pub order_block: Vec<usize>,
     pub current_scope: Option<Rc<RefCell<LocalScope>>>,
 }
 
+impl Default for TopLevelScope {
+    fn default() -> Self {
+        return TopLevelScope::new()
+    }
+}
+
 impl TopLevelScope {
     pub fn new() -> Self {
         let mut scope = TopLevelScope {
             global_define_map: HashMap::new(),
             func_map: HashMap::new(),
+            typedef_map: HashMap::new(),
+            enum_map: HashMap::new(),
+            enum_constants: HashMap::new(),
             scopes: HashMap::new(),
             scope_stack: Vec::new(),
             order_block: Vec::new(),
@@ -37,6 +68,8 @@ impl TopLevelScope {
     }
 
     pub fn push_func(&mut self, name: String) {
+        // 只保留全局作用域，再压入函数自己的作用域
+        self.scope_stack.truncate(1);
         self.order_block = Vec::new();
         let local = &self.scopes.get(&name);
         self.scope_stack.push(Rc::clone(local.unwrap()));
@@ -44,76 +77,344 @@ impl TopLevelScope {
         self.order_block.push(0);
     }
 
-    pub fn push_block(&mut self, local_scope: Rc<RefCell<LocalScope>>) {
-        let l = local_scope.borrow_mut().scopes.len();
-        // println!("name {:?} len {:?}", local_scope.borrow_mut().var_map, l);
-        let mut len = local_scope.borrow_mut().scopes.len();
-        loop {
-            let local_children_size = self.order_block[self.order_block.len() - 1];
-            // println!("order block {:?} {:?} {:?}", self.order_block, len, local_children_size);
-            if len <= local_children_size {
-                self.scope_stack.pop();
-                // println!("order block in pop {:?}", pop);
-                self.order_block.pop();
-                let l = self.order_block.len() - 1;
-                let origin = self.order_block[l];
-                self.order_block[l] = origin + 1;
-                let last_local = &self.scope_stack[self.scope_stack.len() - 1];
-                self.current_scope = Some(Rc::clone(last_local));
-                len = last_local.borrow_mut().scopes.len();
-            } else {
-                self.current_scope = Some(Rc::clone(&local_scope));
-                break;
-            }
-        }
-        // println!("last {:?}", self.order_block);
+    /**
+     *  fill_symbol时新建一个子作用域并进入，离开时直接从scope_stack弹出
+     */
+    pub fn new_block(&mut self) {
+        let local = Rc::new(RefCell::new(LocalScope::new()));
+        let parent = &self.scope_stack[self.scope_stack.len() - 1];
+        local.borrow_mut().parent = Some(Rc::clone(parent));
+        parent.borrow_mut().scopes.push(Rc::clone(&local));
+        self.scope_stack.push(local);
+    }
+
+    pub fn push_block(&mut self) {
+        /*
+            按照fill_symbol时创建子作用域的顺序依次进入，
+            order_block记录每一层已经访问过的子作用域个数
+        */
         let last = self.order_block[self.order_block.len() - 1];
-        // println!("====== push {:?}", local_scope);
-        // let local = &self.scope_stack[self.scope_stack.len() - 1];
-        let local = &self.current_scope.as_ref().unwrap();
-        let scope = &local.borrow_mut().scopes[last];
-        self.scope_stack.push(Rc::clone(scope));
+        let parent = Rc::clone(&self.scope_stack[self.scope_stack.len() - 1]);
+        let scope = Rc::clone(&parent.borrow().scopes[last]);
+        let names = scope.borrow().var_map.keys().cloned().collect();
+        scope.borrow_mut().pending = names;
+        self.current_scope = Some(Rc::clone(&scope));
+        self.scope_stack.push(scope);
         self.order_block.push(0);
     }
 
+    /**
+     *  检查和生成时走到了变量的声明，之后才能找到这个变量
+     */
+    pub fn declare(&mut self, name: &String) {
+        let last = &self.scope_stack[self.scope_stack.len() - 1];
+        last.borrow_mut().pending.remove(name);
+    }
+
+    /**
+     *  当前作用域里声明的变量，不管有没有走到声明的地方
+     */
+    pub fn local_var(&self, name: &String) -> Option<DefVarNode> {
+        let last = &self.scope_stack[self.scope_stack.len() - 1];
+        return last.borrow().var_map.get(name).cloned()
+    }
+
     pub fn pop_block(&mut self) {
         self.scope_stack.pop();
+        self.order_block.pop();
+        let l = self.order_block.len() - 1;
+        self.order_block[l] += 1;
+        let last_local = &self.scope_stack[self.scope_stack.len() - 1];
+        self.current_scope = Some(Rc::clone(last_local));
+    }
+
+    /**
+     *  scope_stack[0]是全局作用域，[1]是当前函数的作用域
+     */
+    pub fn add_label(&mut self, name: &String, span: &Span) -> Result<(), SemanticError> {
+        let mut func = self.scope_stack[1].borrow_mut();
+        if let Some(prev) = func.labels.get(name) {
+            return Err(SemanticError::at(SemanticErrorType::Redefinition(name.clone(), prev.clone()), span.clone()))
+        }
+        func.labels.insert(name.clone(), span.clone());
+
+        Ok(())
+    }
+
+    pub fn has_label(&self, name: &String) -> bool {
+        return self.scope_stack[1].borrow().labels.contains_key(name)
+    }
+
+    /**
+     *  函数里的static变量，生成代码时换成全局变量的名字
+     */
+    pub fn static_local(&self, name: &String) -> Option<String> {
+        for local in self.scope_stack.iter().skip(1).rev() {
+            let local = local.borrow();
+            if local.get_var(name).is_some() {
+                return local.statics.get(name).cloned()
+            }
+        }
+
+        return None
+    }
+
+    /**
+     *  记录当前作用域里的static变量生成的全局变量名
+     */
+    pub fn bind_static(&mut self, name: &str, global: String) {
+        let last = &self.scope_stack[self.scope_stack.len() - 1];
+        last.borrow_mut().statics.insert(name.to_string(), global);
+    }
+
+    /**
+     *  没有被局部变量遮住的全局变量，地址在编译期就能确定
+     */
+    pub fn is_global_var(&self, name: &String) -> bool {
+        for local in self.scope_stack.iter().skip(1) {
+            if local.borrow().get_var(name).is_some() {
+                return false
+            }
+        }
+
+        return self.scope_stack[0].borrow().get_var(name).is_some()
+    }
+
+    /**
+     *  局部变量会遮住同名的枚举成员
+     */
+    pub fn enum_constant(&self, name: &String) -> Option<i64> {
+        if self.get_var(name).is_some() {
+            return None
+        }
+
+        return self.enum_constants.get(name).map(|(value, _)| *value)
+    }
+
+    pub fn get_var(&self, name: &String) -> Option<DefVarNode> {
+        for local in self.scope_stack.iter().rev() {
+            if let Some(node) = local.borrow().get_var(name) {
+                return Some(node.clone())
+            }
+        }
+
+        return None
+    }
+
+    /**
+     *  把typedef的别名展开成原来的类型，比如Node *n里的Node是struct node
+     *  函数指针的参数和返回值里也可能用到别名
+     */
+    pub fn resolve_type(&mut self, typeref: &TypeNode) -> Result<TypeNode, SemanticError> {
+        // struct和union共用一个标签空间，同名时种类也要对上
+        if is_aggregate(&typeref.type_base.base) {
+            let name = struct_name(&typeref.type_base);
+            if let Some(def) = self.global_define_map.get(&name) {
+                if def.kind != typeref.type_base.base {
+                    return Err(SemanticError::new(SemanticErrorType::TagMismatch(name, def.span.clone())))
+                }
+            }
+        }
+        let mut resolved = match &typeref.type_base.base {
+            Token::TypeName(name) => match self.typedef_map.get(name) {
+                Some(typedef) => typedef.typeref.clone(),
+                None => return Err(SemanticError::new(SemanticErrorType::UndefinedSymbol(name.clone()))),
+            },
+            Token::Enum if !self.enum_map.contains_key(&struct_name(&typeref.type_base)) => {
+                return Err(SemanticError::new(SemanticErrorType::UndefinedEnum(struct_name(&typeref.type_base))))
+            },
+            _ => TypeNode {
+                type_base: typeref.type_base.clone(),
+                nested_def: Vec::new(),
+                qualifier: Qualifier::default(),
+            },
+        };
+        resolved.qualifier = resolved.qualifier.merge(&typeref.qualifier, resolved.nested_def.len());
+
+        for def in &typeref.nested_def {
+            let def = match def {
+                TypeDef::Func(func) => {
+                    let mut params = Vec::new();
+                    for param in &func.params {
+                        params.push(self.resolve_type(param)?);
+                    }
+                    TypeDef::Func(Box::new(FuncType {
+                        params,
+                        return_type: self.resolve_type(&func.return_type)?,
+                        variadic: func.variadic,
+                    }))
+                },
+                TypeDef::ConstArray(size) => match size.0.const_value(self) {
+                    Some(n) if n > 0 => TypeDef::FixedArray(n as usize),
+                    Some(n) => return Err(SemanticError::at(SemanticErrorType::InvalidArraySize(n), size.0.span())),
+                    None => return Err(SemanticError::at(SemanticErrorType::NotConstant, size.0.span())),
+                },
+                def => def.clone(),
+            };
+            resolved.nested_def.push(def);
+        }
+
+        Ok(resolved)
     }
 
-    pub fn get_type(&self, name: &String) -> TypeInfo {
-        let mut index = self.scope_stack.len();
-        // println!("get type {:?} {:?}", index, self.scope_stack);
-        loop {
-            if index == 0 {
-                panic!("Can't find the symbol \"{}\"", name);
+    /**
+     *  类型的大小不能超过isize的范围，否则地址的偏移会溢出
+     */
+    pub fn type_size(&self, typeref: &TypeNode) -> Result<usize, SemanticError> {
+        let size = self.nested_size(&typeref.type_base, &typeref.nested_def)?;
+        if size > isize::MAX as usize {
+            return Err(SemanticError::new(SemanticErrorType::TypeTooLarge))
+        }
+
+        Ok(size)
+    }
+
+    fn nested_size(&self, type_base: &TypeBase, nested_def: &[TypeDef]) -> Result<usize, SemanticError> {
+        // 最后一个修饰是最外层的类型
+        match nested_def.last() {
+            Some(TypeDef::FixedArray(n)) => {
+                let elem = self.nested_size(type_base, &nested_def[..nested_def.len() - 1])?;
+                return n.checked_mul(elem).ok_or(SemanticError::new(SemanticErrorType::TypeTooLarge))
+            },
+            Some(_) => return Ok(8),
+            None => {},
+        }
+
+        let size = match type_base.base {
+            Token::Char => 1,
+            Token::Short => 2,
+            Token::Int | Token::Float | Token::Enum => 4,
+            Token::Long | Token::Double => 8,
+            Token::Struct | Token::Union => {
+                let (_, size) = self.struct_layout(&struct_name(type_base))?;
+                size
+            },
+            _ => 0,
+        };
+
+        Ok(size)
+    }
+
+    pub fn type_align(&self, typeref: &TypeNode) -> Result<usize, SemanticError> {
+        match typeref.nested_def.last() {
+            Some(TypeDef::FixedArray(_)) => {
+                let mut elem = typeref.clone();
+                elem.nested_def.pop();
+                return self.type_align(&elem)
+            },
+            Some(_) => return Ok(8),
+            None => {},
+        }
+
+        if is_aggregate(&typeref.type_base.base) {
+            let def = self.get_struct(&struct_name(&typeref.type_base))?;
+            let mut align = 1;
+            for member in &def.member_list {
+                align = max(align, self.type_align(&member.typeref)?);
+            }
+            return Ok(align)
+        }
+
+        return Ok(max(self.type_size(typeref)?, 1))
+    }
+
+    /**
+     *  计算结构体每个成员的偏移和结构体的总大小
+     *  联合体的成员都从0开始，大小是最大的成员按对齐补齐
+     */
+    pub fn struct_layout(&self, name: &String) -> Result<(HashMap<String, usize>, usize), SemanticError> {
+        let def = self.get_struct(name)?;
+        let mut offsets = HashMap::new();
+        let mut offset = 0;
+        let mut max_align = 1;
+        for member in &def.member_list {
+            let align = self.type_align(&member.typeref)?;
+            max_align = max(max_align, align);
+            if def.kind == Token::Union {
+                offsets.insert(member.name.clone(), 0);
+                offset = max(offset, self.type_size(&member.typeref)?);
+                continue;
             }
-            let local = &self.scope_stack[index - 1];
-            if let Some(node) = local.borrow_mut().var_map.get(&name.clone()) {
-                if node.typeref.type_base.base == Token::Struct {
-                    let struct_name = node.typeref.type_base.name.as_ref().unwrap().clone();
-                    let struct_type = self.global_define_map.get(&struct_name);
-                    return TypeInfo {
+            offset = align_to(offset, align)?;
+            offsets.insert(member.name.clone(), offset);
+            offset = offset.checked_add(self.type_size(&member.typeref)?)
+                .ok_or(SemanticError::new(SemanticErrorType::TypeTooLarge))?;
+        }
+
+        return Ok((offsets, align_to(offset, max_align)?))
+    }
+
+    pub fn get_struct(&self, name: &String) -> Result<&DefStructNode, SemanticError> {
+        match self.global_define_map.get(name) {
+            Some(def) => Ok(def),
+            None => Err(SemanticError::new(SemanticErrorType::UndefinedStruct(name.clone()))),
+        }
+    }
+
+    pub fn get_type(&self, name: &String) -> Result<TypeInfo, SemanticError> {
+        for local in self.scope_stack.iter().rev() {
+            if let Some(node) = local.borrow().get_var(name) {
+                if is_aggregate(&node.typeref.type_base.base) {
+                    let struct_type = self.get_struct(&struct_name(&node.typeref.type_base))?;
+                    return Ok(TypeInfo {
                         name: name.clone(),
-                        origin_struct: Some(struct_type.unwrap().clone()),
+                        origin_struct: Some(struct_type.clone()),
                         origin_base: None,
-                        base_type: Token::Struct,
+                        base_type: struct_type.kind.clone(),
                         nested_def: node.typeref.nested_def.clone(),
-                    }
+                        qualifier: node.typeref.qualifier.clone(),
+                    })
                 } else {
-                    return TypeInfo {
+                    return Ok(TypeInfo {
                         name: name.clone(),
                         origin_struct: None,
                         origin_base: Some(node.clone()),
                         base_type: node.typeref.type_base.base.clone(),
                         nested_def: node.typeref.nested_def.clone(),
-                    }
+                        qualifier: node.typeref.qualifier.clone(),
+                    })
                 }
             }
-            index = index - 1;
         }
+
+        // 枚举成员和int一样
+        if self.enum_constants.contains_key(name) {
+            return Ok(TypeInfo {
+                name: name.clone(),
+                origin_struct: None,
+                origin_base: None,
+                base_type: Token::Int,
+                nested_def: Vec::new(),
+                qualifier: Qualifier::default(),
+            })
+        }
+
+        // 函数名当成值使用，比如赋给函数指针
+        if let Some(func) = self.func_map.get(name) {
+            let typeref = func.func_type();
+            return Ok(TypeInfo {
+                name: name.clone(),
+                origin_struct: None,
+                origin_base: None,
+                base_type: typeref.type_base.base,
+                nested_def: typeref.nested_def,
+                qualifier: typeref.qualifier,
+            })
+        }
+
+        return Err(SemanticError::new(SemanticErrorType::UndefinedSymbol(name.clone())))
     }
 }
 
+// 解析器保证struct后面一定跟着名字
+fn struct_name(type_base: &TypeBase) -> String {
+    return type_base.name.clone().unwrap_or_default()
+}
+
+fn align_to(n: usize, align: usize) -> Result<usize, SemanticError> {
+    n.div_ceil(align).checked_mul(align).ok_or(SemanticError::new(SemanticErrorType::TypeTooLarge))
+}
+
 impl fmt::Debug for TopLevelScope {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "GLOBAL DEFINE: {:?} \nGLOBAL FUNC: {:?} \nSCOPES: {:?}", self.global_define_map.keys(), self.func_map.keys(), self.scopes)
@@ -124,6 +425,18 @@ pub struct LocalScope {
     pub parent: Option<Rc<RefCell<LocalScope>>>,
     pub var_map: HashMap<String, DefVarNode>,
     pub scopes: Vec<Rc<RefCell<LocalScope>>>,
+    // 标签的作用域是整个函数，只记录在函数最外层的作用域里
+    pub labels: HashMap<String, Span>,
+    // 检查和生成时还没走到声明的变量，查找时跳过，这样会找到外层的同名变量
+    pub pending: HashSet<String>,
+    // 函数里的static变量对应的全局变量名，生成代码时才分配
+    pub statics: HashMap<String, String>,
+}
+
+impl Default for LocalScope {
+    fn default() -> Self {
+        return LocalScope::new()
+    }
 }
 
 impl LocalScope {
@@ -132,7 +445,18 @@ impl LocalScope {
             parent: None,
             var_map: HashMap::new(),
             scopes: Vec::new(),
+            labels: HashMap::new(),
+            pending: HashSet::new(),
+            statics: HashMap::new(),
+        }
+    }
+
+    pub fn get_var(&self, name: &String) -> Option<&DefVarNode> {
+        if self.pending.contains(name) {
+            return None
         }
+
+        return self.var_map.get(name)
     }
 }
 
@@ -143,11 +467,32 @@ impl fmt::Debug for LocalScope {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct TypeInfo {
     pub name: String,
     pub origin_struct: Option<DefStructNode>,
     pub origin_base: Option<DefVarNode>,
     pub base_type: Token,
     pub nested_def: Vec<TypeDef>,
-}
\ No newline at end of file
+    pub qualifier: Qualifier,
+}
+
+impl TypeInfo {
+    /**
+     *  生成临时变量时需要把推导出来的类型转换回TypeNode
+     */
+    pub fn typeref(&self) -> TypeNode {
+        TypeNode {
+            type_base: TypeBase {
+                base: self.base_type.clone(),
+                name: self.origin_struct.as_ref().map(|def| def.name.clone()),
+            },
+            nested_def: self.nested_def.clone(),
+            // 临时变量要被赋值，只保留有没有符号
+            qualifier: Qualifier {
+                is_unsigned: self.qualifier.is_unsigned,
+                ..Qualifier::default()
+            },
+        }
+    }
+}
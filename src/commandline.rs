@@ -1,44 +1,56 @@
 use std::fs;
 use std::env;
 use std::process;
+use lex::location::DEFAULT_TAB_WIDTH;
 
-struct Config {
-    filename: String,
-    // pub query: String,
+pub struct Config {
+    pub filename: String,
+    // 计算列号时一个tab占的宽度，--tab-width=N
+    pub tab_width: usize,
 }
 
 impl Config {
     fn new(args: &[String]) -> Result<Config, &'static str> {
-        if args.len() < 2 {
-            return Err("not enough arguments")
+        let mut filename = None;
+        let mut tab_width = DEFAULT_TAB_WIDTH;
+        for arg in args.iter().skip(1) {
+            match arg.strip_prefix("--tab-width=") {
+                Some(width) => tab_width = width.parse().map_err(|_| "invalid tab width")?,
+                None if filename.is_none() => filename = Some(arg.clone()),
+                None => {},
+            }
         }
 
-        let filename = args[1].clone();
-
-        Ok(Config {
-            filename,
-        })
+        match filename {
+            Some(filename) => Ok(Config {
+                filename,
+                tab_width,
+            }),
+            None => Err("not enough arguments"),
+        }
     }
 }
 
-fn readfile(config: Config) -> String {
-    let content = fs::read_to_string(config.filename).unwrap_or_else(|err| {
-        println!("File read failed: {}", err);
+fn readfile(config: &Config) -> String {
+    fs::read_to_string(&config.filename).unwrap_or_else(|err| {
+        eprintln!("File read failed: {}", err);
         process::exit(1);
-    });
-
-    content
+    })
 }
 
-pub fn run() -> String {
+/**
+ *  返回命令行参数和文件内容，文件名用于输出诊断信息
+ */
+pub fn run() -> (Config, String) {
     let args: Vec<String> = env::args().collect();
     let config = Config::new(&args).unwrap_or_else(|err| {
-        println!("Problem parsing arguments: {}", err);
+        eprintln!("Problem parsing arguments: {}", err);
         process::exit(1);
     });
-    let content = readfile(config);
+    
 
-    content
+    let content = readfile(&config);
+    (config, content)
 }
 
 #[cfg(test)]
@@ -51,5 +63,14 @@ mod tests {
 
         let config = Config::new(&args).unwrap();
         assert_eq!("a2", config.filename);
+        assert_eq!(8, config.tab_width);
+
+        let args: Vec<String> = vec![String::from("a1"), String::from("--tab-width=4"), String::from("a2")];
+        let config = Config::new(&args).unwrap();
+        assert_eq!(("a2", 4), (config.filename.as_str(), config.tab_width));
+
+        let args: Vec<String> = vec![String::from("a1"), String::from("--tab-width=x"), String::from("a2")];
+        assert!(Config::new(&args).is_err());
+        assert!(Config::new(&[String::from("a1")]).is_err());
     }
 }
\ No newline at end of file
@@ -0,0 +1,906 @@
+use lex::lexer::Lexer;
+use lex::error::PreprocessError;
+use lex::preprocessor:: {
+    Preprocessor,
+    SourceMap,
+};
+use parse::parser::parse;
+use parse::ir::IR;
+use analysis::local_resolver::local_resolver;
+use analysis::type_def_check::check_circle_def;
+use analysis::expr_check::check_expr;
+use ir::generate::ir_generate;
+use parse::error:: {
+    ParseError,
+    SemanticError,
+};
+use parse::diagnostic::Diagnostic;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum CompileError {
+    Preprocess(PreprocessError),
+    Parse(ParseError),
+    Semantic(SemanticError),
+}
+
+impl From<PreprocessError> for CompileError {
+    fn from(err: PreprocessError) -> Self {
+        CompileError::Preprocess(err)
+    }
+}
+
+impl From<ParseError> for CompileError {
+    fn from(err: ParseError) -> Self {
+        CompileError::Parse(err)
+    }
+}
+
+impl From<SemanticError> for CompileError {
+    fn from(err: SemanticError) -> Self {
+        CompileError::Semantic(err)
+    }
+}
+
+impl CompileError {
+    /**
+     *  tab_width要和预处理、词法分析时用的一致
+     */
+    pub fn diagnostic(&self, tab_width: usize) -> Diagnostic {
+        match self {
+            CompileError::Preprocess(err) => Diagnostic::from_preprocess(err, tab_width),
+            CompileError::Parse(err) => Diagnostic::from(err),
+            CompileError::Semantic(err) => Diagnostic::from(err),
+        }
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompileError::Preprocess(err) => write!(f, "{}", err),
+            CompileError::Parse(err) => write!(f, "{}", err),
+            CompileError::Semantic(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/**
+ *  返回展开后的文本和对应回原始文件的source map，列号按tab_width算
+ */
+pub fn preprocess(filename: &str, content: &str, tab_width: usize) -> Result<(String, SourceMap), CompileError> {
+    let mut preprocessor = Preprocessor::new();
+    preprocessor.set_tab_width(tab_width);
+    return Ok(preprocessor.preprocess(filename, content)?)
+}
+
+pub fn compile(content: String, tab_width: usize) -> Result<IR, CompileError> {
+    /*
+        lex -> parse -> resolve symbols -> check expr -> generate ir
+    */
+    let mut lexer = Lexer::new(content);
+    lexer.set_tab_width(tab_width);
+    let ast = parse(&mut lexer)?;
+    let mut symboltable = local_resolver(&ast)?;
+    // 结构体直接包含自己时算不出大小，要在用到布局之前检查
+    check_circle_def(symboltable.global_define_map.clone())?;
+    check_expr(&ast, &mut symboltable)?;
+
+    return Ok(ir_generate(&ast, &mut symboltable)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lex::token::Token;
+    use lex::error::LexicalErrorType;
+    use lex::location::DEFAULT_TAB_WIDTH;
+    use parse::error:: {
+        ParseErrorType,
+        SemanticErrorType,
+    };
+
+    // 没有特别说明的测试都用默认的tab宽度
+    fn compile(content: String) -> Result<IR, CompileError> {
+        return super::compile(content, DEFAULT_TAB_WIDTH)
+    }
+
+    #[test]
+    fn test_compile() {
+        let ir = compile(String::from("
+            struct point {
+                int x;
+                int y;
+            };
+
+            int main(int argc, char **argv) {
+                struct point p;
+                int i;
+                for(i = 0; i < 10; i++) {
+                    p.x = p.x + calc(i);
+                }
+
+                return 0;
+            }
+
+            int calc(int a) {
+                a <<= 1;
+                return a > 0 ? a * 2 : 0;
+            }
+        ")).unwrap();
+
+        assert_eq!(2, ir.functions.len());
+        assert_eq!("calc", ir.functions[1].name);
+    }
+
+    #[test]
+    fn test_preprocess_compile() {
+        let (text, map) = preprocess("test.c", "
+            #define LIMIT 10
+            #define DOUBLE(x) x * 2
+            int main() {
+                int i;
+            #ifdef LIMIT
+                for(i = 0; i < LIMIT; i++) {
+                    i = DOUBLE(i);
+                }
+            #endif
+                b = 1;
+                return 0;
+            }
+        ", DEFAULT_TAB_WIDTH).unwrap();
+        let err = compile(text).unwrap_err();
+        let span = match err {
+            CompileError::Semantic(err) => err.span.unwrap(),
+            _ => panic!("expect semantic error, found {:?}", err),
+        };
+        assert_eq!(11, map.lookup(span.start.row).unwrap().line);
+
+        let err = preprocess("test.c", "#if 1", DEFAULT_TAB_WIDTH).unwrap_err();
+        assert!(matches!(err, CompileError::Preprocess(_)));
+
+        // 预处理、词法分析和报错用同一个tab宽度，列号和下划线才能对上
+        let source = "#define ONE 1\nint main() {\n\tb = ONE;\n\treturn 0;\n}\n";
+        let (text, map) = preprocess("test.c", source, 4).unwrap();
+        let err = super::compile(text.clone(), 4).unwrap_err();
+        let rendered = err.diagnostic(4).render_mapped("test.c", &text, &map, false);
+        assert!(rendered.contains("--> test.c:3:5\n"), "{}", rendered);
+        assert!(rendered.contains("3 |     b = ONE;\n  |     ^"), "{}", rendered);
+
+        let err = preprocess("test.c", "\t#if 1", 4).unwrap_err();
+        let rendered = err.diagnostic(4).render_with_tab_width("test.c", "\t#if 1", false, 4);
+        assert!(rendered.contains("--> test.c:1:5\n"), "{}", rendered);
+    }
+
+    #[test]
+    fn test_compile_switch() {
+        let ir = compile(String::from("
+            int main() {
+                int i;
+                for(i = 0; i < 10; i++) {
+                    switch (i) {
+                        case 1: i = 2;
+                        case 2: break;
+                        default: continue;
+                    }
+                }
+                return 0;
+            }
+        ")).unwrap();
+        let body = format!("{:?}", ir.functions[0].body);
+        assert_eq!(2, body.matches("op: EQ").count());
+
+        let err = compile(String::from("int main() { int i; switch (i) { case 1: continue; } return 0; }")).unwrap_err();
+        assert!(matches!(err, CompileError::Semantic(_)));
+    }
+
+    #[test]
+    fn test_compile_goto() {
+        let ir = compile(String::from("
+            int main() {
+                int i;
+                i = 0;
+            next:
+                i++;
+                if (i < 10) goto next;
+                return i;
+            }
+        ")).unwrap();
+        let body = format!("{:?}", ir.functions[0].body);
+        assert_eq!(2, body.matches("\".Lmain.next\"").count());
+    }
+
+    #[test]
+    fn test_compile_func_pointer() {
+        let ir = compile(String::from("
+            int main() {
+                int (*cb)(int) = calc;
+                return cb(1);
+            }
+
+            int calc(int a) {
+                return a;
+            }
+        ")).unwrap();
+        let body = format!("{:?}", ir.functions[0].body);
+        assert!(body.contains("rhs: ExprStmt { expr: FuncAddr { name: \"calc\" } }"));
+        assert!(body.contains("Call { expr: ExprStmt { expr: Var { name: \"cb\""));
+    }
+
+    #[test]
+    fn test_compile_typedef() {
+        let ir = compile(String::from("
+            typedef int Int;
+            typedef struct point Point;
+            struct point {
+                Int x;
+                Int y;
+            };
+            Point origin;
+
+            Int main() {
+                Point p;
+                Int i;
+                i = p.x + origin.y;
+                return i;
+            }
+        ")).unwrap();
+        assert_eq!(Token::Struct, ir.variables[0].typeref.type_base.base);
+        assert_eq!(Token::Int, ir.functions[0].return_type.type_base.base);
+    }
+
+    #[test]
+    fn test_compile_enum() {
+        let ir = compile(String::from("
+            enum color { RED, GREEN = 5, BLUE };
+            enum color current;
+
+            int main() {
+                int[BLUE] buf;
+                enum color c;
+                c = GREEN;
+                switch (c) {
+                    case RED: current = c; break;
+                    case BLUE: break;
+                }
+                return BLUE;
+            }
+        ")).unwrap();
+        assert_eq!(Token::Enum, ir.variables[0].typeref.type_base.base);
+        let body = format!("{:?}", ir.functions[0].body);
+        assert!(body.contains("Int { value: 5 }"));
+        assert!(body.contains("Int { value: 6 }"));
+
+        // 枚举成员是常量，报错里是成员的名字
+        for stmt in ["RED = 1;", "RED += 1;", "RED++;", "++RED;"].iter() {
+            let err = compile(format!("
+                enum color {{ RED }};
+                int main() {{
+                    {}
+                    return 0;
+                }}
+            ", stmt)).unwrap_err();
+            assert!(matches!(err, CompileError::Semantic(e) if e.error == SemanticErrorType::NotLeftValue(String::from("RED"))), "{}", stmt);
+        }
+    }
+
+    #[test]
+    fn test_compile_union() {
+        let ir = compile(String::from("
+            union value {
+                int i;
+                float f;
+            };
+            struct tagged {
+                int tag;
+                union value v;
+            };
+
+            int main() {
+                struct tagged t;
+                union value *p;
+                t.v.i = 1;
+                p->f = t.v.f;
+                return t.v.i;
+            }
+        ")).unwrap();
+        let body = format!("{:?}", ir.functions[0].body);
+        assert!(body.contains("Int { value: 4 }"));
+
+        let err = compile(String::from("
+            union value { int i; };
+            int main() {
+                union value v;
+                v.f = 1;
+                return 0;
+            }
+        ")).unwrap_err();
+        assert!(matches!(err, CompileError::Semantic(_)));
+    }
+
+    #[test]
+    fn test_compile_recursive_struct() {
+        let sources = [
+            ("struct a { int v; struct a x; };", "a"),
+            ("union u { union u x; };", "u"),
+        ];
+        for (source, name) in sources.iter() {
+            let err = compile(format!("
+                {}
+                int main() {{
+                    return 0;
+                }}
+            ", source)).unwrap_err();
+            match err {
+                CompileError::Semantic(err) => {
+                    assert_eq!(SemanticErrorType::RecursiveDefinition(name.to_string()), err.error);
+                    assert_eq!(2, err.span.unwrap().start.row);
+                },
+                err => panic!("expect semantic error, found {:?}", err),
+            }
+        }
+    }
+
+    #[test]
+    fn test_compile_init_list() {
+        let ir = compile(String::from("
+            struct point {
+                int x;
+                double y;
+            };
+            int[4] table = {1, [2] = 3, -4};
+            struct point origin = {.y = -1.5};
+            char *name = \"cr7c\";
+
+            int main() {
+                struct point[2] ps = {{1, 2.5}, [1] = {.x = 3}};
+                return 0;
+            }
+        ")).unwrap();
+        assert_eq!("[Data { offset: 0, size: 4, value: ExprStmt { expr: Int { value: 1 } } }, \
+                    Data { offset: 8, size: 4, value: ExprStmt { expr: Int { value: 3 } } }, \
+                    Data { offset: 12, size: 4, value: ExprStmt { expr: Int { value: -4 } } }]",
+                   format!("{:?}", ir.data["table"]));
+        assert_eq!("[Data { offset: 8, size: 8, value: ExprStmt { expr: Float { value: -1.5 } } }]",
+                   format!("{:?}", ir.data["origin"]));
+        assert!(format!("{:?}", ir.data["name"]).contains("Str { value: \"cr7c\" }"));
+
+        // 局部变量的每个元素都要赋值，没写到的补0
+        let body = format!("{:?}", ir.functions[0].body);
+        assert_eq!(4, body.matches("Assign").count());
+        for value in ["Int { value: 0 }", "Int { value: 8 }", "Int { value: 16 }", "Int { value: 24 }", "Float { value: 2.5 }", "Float { value: 0.0 }"].iter() {
+            assert!(body.contains(value), "{}", value);
+        }
+
+        let err = compile(String::from("
+            int seed;
+            int[2] table = {seed, 1};
+        ")).unwrap_err();
+        assert!(matches!(err, CompileError::Semantic(e) if e.error == SemanticErrorType::NotConstant));
+
+        let err = compile(String::from("
+            int seed;
+            int copy = seed + 1;
+        ")).unwrap_err();
+        assert!(matches!(err, CompileError::Semantic(e) if e.error == SemanticErrorType::NotConstant));
+
+        // 全局变量的地址、全局数组和函数名可以初始化全局的指针
+        let ir = compile(String::from("
+            int x;
+            int[4] arr;
+            int *p = &x;
+            int *start = arr;
+            int (*cb)(int) = calc;
+
+            int calc(int a) {
+                int y = *p;
+                int *q = &y;
+                char c = 1;
+                long l = c;
+                return y;
+            }
+        ")).unwrap();
+        assert!(format!("{:?}", ir.data["p"]).contains("Addr { expr: ExprStmt { expr: Var { name: \"x\""));
+        assert!(format!("{:?}", ir.data["start"]).contains("Addr { expr: ExprStmt { expr: Var { name: \"arr\""));
+        assert!(format!("{:?}", ir.data["cb"]).contains("FuncAddr { name: \"calc\" }"));
+
+        let err = compile(String::from("
+            int main() {
+                int x;
+                static int *p = &x;
+                return 0;
+            }
+        ")).unwrap_err();
+        assert!(matches!(err, CompileError::Semantic(e) if e.error == SemanticErrorType::NotConstant));
+
+        // 没写长度的数组按初始值确定长度，字符串按字符逐个初始化char数组
+        let ir = compile(String::from("
+            int[] primes = {2, 3, 5}, wide = {1, [5] = 6};
+            char[] word = \"ab\";
+            char[3] exact = \"abc\";
+
+            int main() {
+                char[4] s = \"abc\";
+                int[] n = {1, 2};
+                return sizeof n;
+            }
+        ")).unwrap();
+        assert_eq!("[FixedArray(3)]", format!("{:?}", ir.variables[0].typeref.nested_def));
+        assert_eq!("[FixedArray(6)]", format!("{:?}", ir.variables[1].typeref.nested_def));
+        assert_eq!("[FixedArray(3)]", format!("{:?}", ir.variables[2].typeref.nested_def));
+        assert_eq!("[Data { offset: 0, size: 1, value: ExprStmt { expr: Int { value: 97 } } }, \
+                    Data { offset: 1, size: 1, value: ExprStmt { expr: Int { value: 98 } } }, \
+                    Data { offset: 2, size: 1, value: ExprStmt { expr: Int { value: 0 } } }]",
+                   format!("{:?}", ir.data["word"]));
+        assert_eq!(3, ir.data["exact"].len());
+        let body = format!("{:?}", ir.functions[0].body);
+        assert!(!body.contains("Str { value"));
+        assert_eq!(6, body.matches("Assign").count());
+        assert!(body.contains("Int { value: 8 }"));
+
+        let err = compile(String::from("
+            int main() {
+                char[2] s = \"abc\";
+                return 0;
+            }
+        ")).unwrap_err();
+        assert!(matches!(err, CompileError::Semantic(e) if e.error == SemanticErrorType::ExcessElements));
+
+        let err = compile(String::from("
+            int[] empty = {};
+        ")).unwrap_err();
+        assert!(matches!(err, CompileError::Semantic(e) if e.error == SemanticErrorType::InvalidArraySize(0)));
+    }
+
+    #[test]
+    fn test_compile_sizeof() {
+        let ir = compile(String::from("
+            struct student {
+                char c;
+                double score;
+                int[3] ids;
+            };
+            typedef struct student Student;
+            int[sizeof(struct student) / 8] buf;
+            int[3] sizes = {sizeof(Student), alignof(struct student), sizeof(int[10])};
+
+            int main() {
+                struct student s;
+                char *p;
+                int[sizeof s.ids] n;
+                return sizeof p + sizeof(s) - sizeof(n);
+            }
+        ")).unwrap();
+        assert_eq!("[FixedArray(4)]", format!("{:?}", ir.variables[0].typeref.nested_def));
+        let sizes = format!("{:?}", ir.data["sizes"]);
+        assert!(sizes.contains("Int { value: 32 }") && sizes.contains("Int { value: 8 }") && sizes.contains("Int { value: 40 }"));
+        let body = format!("{:?}", ir.functions[0].body);
+        assert!(body.contains("Int { value: 8 }") && body.contains("Int { value: 32 }") && body.contains("Int { value: 48 }"));
+
+        // 按整个操作数的类型算，前缀和字符串字面量的类型都要算进去
+        let ir = compile(String::from("
+            struct student { char c; double score; };
+            int main() {
+                char *p;
+                struct student s;
+                long[2] sizes = {sizeof *p, sizeof \"abc\"};
+                int[3] more = {sizeof &s, sizeof(1 + 2L), sizeof(s.c == 1)};
+                return 0;
+            }
+        ")).unwrap();
+        let body = format!("{:?}", ir.functions[0].body);
+        let values: Vec<_> = body.match_indices("rhs: ExprStmt { expr: Int { value: ").map(|(i, m)| &body[i + m.len()..i + m.len() + 1]).collect();
+        assert_eq!(vec!["1", "4", "8", "8", "4"], values);
+
+        for expr in ["&sizeof(int)", "sizeof(struct none)", "sizeof missing"].iter() {
+            let err = compile(format!("int main() {{ return {}; }}", expr)).unwrap_err();
+            assert!(matches!(err, CompileError::Semantic(_)), "{}", expr);
+        }
+
+        // 大小溢出时报错而不是panic
+        for source in [
+            "int main() { return sizeof(int[1 << 62][4]); }",
+            "struct big { char[1 << 62] a; char[1 << 62] b; char[1 << 62] c; }; int main() { return sizeof(struct big); }",
+            "struct big { char c; long[1 << 60] a; }; int main() { return sizeof(struct big); }",
+        ].iter() {
+            let err = compile(String::from(*source)).unwrap_err();
+            match err {
+                CompileError::Semantic(e) => {
+                    assert_eq!(SemanticErrorType::TypeTooLarge, e.error, "{}", source);
+                    assert!(e.span.is_some(), "{}", source);
+                },
+                err => panic!("expect semantic error, found {:?}", err),
+            }
+        }
+    }
+
+    #[test]
+    fn test_compile_comma() {
+        let ir = compile(String::from("
+            int sum(int a, int b) {
+                return a + b;
+            }
+
+            int main() {
+                int i;
+                int j;
+                int n;
+                char *s;
+                n = 10;
+                for (i = 0, j = n; i < j; i++, j--) {
+                    n = sum(i, j), n++;
+                }
+                s = (n, \"done\");
+                return i, j;
+            }
+        ")).unwrap();
+        let body = format!("{:?}", ir.functions[1].body);
+        assert!(body.contains("Call { expr: ExprStmt { expr: FuncAddr { name: \"sum\" } }, args: [ExprStmt { expr: Var { name: \"i\""));
+        assert!(body.contains("Return { expr: Var { name: \"j\""));
+
+        let err = compile(String::from("
+            int main() {
+                int i;
+                (i, i) = 1;
+                return 0;
+            }
+        ")).unwrap_err();
+        assert!(matches!(err, CompileError::Semantic(_)));
+    }
+
+    #[test]
+    fn test_compile_for() {
+        let ir = compile(String::from("
+            int main() {
+                int n;
+                n = 0;
+                for (;;) {
+                    if (n > 3) {
+                        break;
+                    }
+                    n++;
+                }
+                for (int i = 0; i < n; i++) {
+                    n = n - i;
+                }
+                return n;
+            }
+        ")).unwrap();
+        let body = format!("{:?}", ir.functions[0].body);
+        // 只有第二个循环和if有条件跳转
+        assert_eq!(2, body.matches("CJump").count());
+        assert!(body.contains("Assign { lhs: ExprStmt { expr: Var { name: \"i\""));
+    }
+
+    #[test]
+    fn test_compile_block_order() {
+        let ir = compile(String::from("
+            int main(int y) {
+                int x = y;
+                y = 2;
+                int z = x;
+                return z;
+            }
+        ")).unwrap();
+        let body = format!("{:?}", ir.functions[0].body);
+        let x = body.find("Var { name: \"x\"").unwrap();
+        let y = body.find("lhs: ExprStmt { expr: Var { name: \"y\"").unwrap();
+        let z = body.find("Var { name: \"z\"").unwrap();
+        assert!(x < y && y < z);
+
+        let err = compile(String::from("
+            int main() {
+                int x = y;
+                int y = 1;
+                return x;
+            }
+        ")).unwrap_err();
+        assert!(matches!(err, CompileError::Semantic(e) if e.error == SemanticErrorType::UndefinedSymbol(String::from("y"))));
+
+        // 同一个声明里的变量按顺序初始化
+        let ir = compile(String::from("
+            int main() {
+                int a = 1, b = a, c = b;
+                return c;
+            }
+        ")).unwrap();
+        let body = format!("{:?}", ir.functions[0].body);
+        let names: Vec<usize> = ["a", "b", "c"].iter()
+            .map(|name| body.find(&format!("lhs: ExprStmt {{ expr: Var {{ name: \"{}\"", name)).unwrap())
+            .collect();
+        assert!(names[0] < names[1] && names[1] < names[2]);
+
+        let err = compile(String::from("
+            int main() {
+                int b = a, a = 1;
+                return b;
+            }
+        ")).unwrap_err();
+        assert!(matches!(err, CompileError::Semantic(e) if e.error == SemanticErrorType::UndefinedSymbol(String::from("a"))));
+
+        // 变量的作用域从声明符之后开始，初始化里的g是局部变量不是全局的g
+        let ir = compile(String::from("
+            int g = 1;
+            int main() {
+                long g = g + 1;
+                return 0;
+            }
+        ")).unwrap();
+        let body = format!("{:?}", ir.functions[0].body);
+        assert_eq!(2, body.matches("Var { name: \"g\"").count());
+        assert!(!body.contains("base: Int"));
+
+        let err = compile(String::from("
+            int main() {
+                int a, a;
+                return 0;
+            }
+        ")).unwrap_err();
+        match err {
+            CompileError::Semantic(e) => {
+                assert!(matches!(e.error, SemanticErrorType::Redefinition(ref name, _) if name == "a"));
+                assert_eq!("E0213", e.error.code());
+            },
+            _ => panic!("expect redefinition, found {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_compile_qualifier() {
+        let ir = compile(String::from("
+            extern int shared;
+            static unsigned int seed = 7;
+
+            int next() {
+                static int calls = 0;
+                unsigned int r;
+                calls++;
+                r = seed / 3 >> 1;
+                r %= 5;
+                if (r < seed) {
+                    return calls;
+                }
+                return -1 / 2;
+            }
+        ")).unwrap();
+        assert_eq!(2, ir.variables.len());
+        assert!(ir.variables[0].typeref.is_static());
+        assert_eq!("next.calls.1", ir.variables[1].declarators[0].0);
+        let body = format!("{:?}", ir.functions[0].body);
+        assert!(body.contains("name: \"next.calls.1\""));
+        for op in ["U_DIV", "U_BIT_RSHIFT", "U_MOD", "U_LT", "op: DIV"].iter() {
+            assert!(body.contains(op), "{}", op);
+        }
+
+        let err = compile(String::from("
+            const int limit = 1;
+            int main() {
+                limit++;
+                return 0;
+            }
+        ")).unwrap_err();
+        assert!(matches!(err, CompileError::Semantic(_)));
+
+        // const只修饰它所在的那一层
+        compile(String::from("
+            typedef int *IntPtr;
+            int main() {
+                int x;
+                int * const p = &x;
+                const int *q;
+                const IntPtr * const r = &p;
+                *p = 1;
+                q = &x;
+                *r = p;
+                return *q;
+            }
+        ")).unwrap();
+        for (stmt, name) in [("*q = 1;", "q"), ("p = &x;", "p"), ("r = 0;", "r"), ("*r[0] = 1;", "r"), ("p++;", "p")].iter() {
+            let err = compile(format!("
+                typedef int *IntPtr;
+                int main() {{
+                    int x;
+                    int * const p = &x;
+                    const int *q;
+                    const IntPtr * const r = &p;
+                    {}
+                    return 0;
+                }}
+            ", stmt)).unwrap_err();
+            match err {
+                CompileError::Semantic(err) => assert_eq!(SemanticErrorType::AssignToConst(String::from(*name)), err.error, "{}", stmt),
+                err => panic!("expect semantic error, found {:?}", err),
+            }
+        }
+
+        // 不同块里同名的static变量各自占一个全局变量
+        let ir = compile(String::from("
+            int main() {
+                {
+                    static int k = 1;
+                    k++;
+                }
+                {
+                    static int k = 2;
+                    k--;
+                }
+                return 0;
+            }
+        ")).unwrap();
+        assert_eq!(2, ir.data.len());
+        assert!(ir.data.contains_key("main.k.1") && ir.data.contains_key("main.k.2"));
+        let body = format!("{:?}", ir.functions[0].body);
+        assert_eq!(2, body.matches("name: \"main.k.1\"").count());
+        assert_eq!(2, body.matches("name: \"main.k.2\"").count());
+    }
+
+    #[test]
+    fn test_compile_prototype() {
+        let ir = compile(String::from("
+            extern int puts(const char *s);
+            int is_odd(int n);
+
+            int is_even(int n) {
+                if (n == 0) {
+                    return 1;
+                }
+                return is_odd(n - 1);
+            }
+
+            int is_odd(int n) {
+                if (n == 0) {
+                    return 0;
+                }
+                return is_even(n - 1);
+            }
+        ")).unwrap();
+        let names: Vec<&str> = ir.functions.iter().map(|func| func.name.as_str()).collect();
+        assert_eq!(vec!["is_even", "is_odd"], names);
+    }
+
+    #[test]
+    fn test_compile_variadic() {
+        let ir = compile(String::from("
+            int printf(const char *fmt, ...);
+
+            int log(char *fmt, ...) {
+                return 0;
+            }
+
+            int main() {
+                int (*print)(const char *, ...);
+                double d;
+                print = printf;
+                printf(\"%d %f %f\\n\", 1, d, 2.5);
+                print(\"done\");
+                log(\"plain\");
+                return 0;
+            }
+        ")).unwrap();
+        let body = format!("{:?}", ir.functions[1].body);
+        assert!(body.contains("varargs: Some(2)"));
+        assert!(body.contains("varargs: Some(0)"));
+
+        let err = compile(String::from("
+            int printf(const char *fmt, ...);
+            int main() {
+                printf();
+                return 0;
+            }
+        ")).unwrap_err();
+        match err {
+            CompileError::Semantic(err) => assert_eq!(SemanticErrorType::TooFewArguments(String::from("printf"), 1, 0), err.error),
+            err => panic!("expect semantic error, found {:?}", err),
+        }
+
+        let err = compile(String::from("
+            int printf(const char *fmt, ...);
+            int main() {
+                printf(1, 2);
+                return 0;
+            }
+        ")).unwrap_err();
+        assert!(matches!(err, CompileError::Semantic(_)));
+
+        // 普通函数的参数个数要完全一致
+        let call = |stmt: &str| compile(format!("
+            int g(int a, char *s) {{
+                return a;
+            }}
+            int main() {{
+                {}
+                return 0;
+            }}
+        ", stmt));
+        call("g(1, \"s\");").unwrap();
+        let err = call("g();").unwrap_err();
+        assert!(matches!(err, CompileError::Semantic(e) if e.error == SemanticErrorType::ArgumentCount(String::from("g"), 2, 0)));
+        let err = call("g(1, 2);").unwrap_err();
+        assert!(matches!(err, CompileError::Semantic(e) if matches!(e.error, SemanticErrorType::IncompatibleTypes(_, _))));
+    }
+
+    #[test]
+    fn test_compile_literal() {
+        let ir = compile(String::from("
+            unsigned long long max = 0xFFFFFFFFFFFFFFFFULL;
+
+            int main() {
+                float f;
+                long l;
+                f = 2.0f;
+                l = 10UL;
+                if (max > 1UL) {
+                    l = 5000000000L;
+                }
+                return 0;
+            }
+        ")).unwrap();
+        assert_eq!(8, ir.data["max"][0].size);
+        // 字面量是无符号的，比较也按无符号做
+        let body = format!("{:?}", ir.functions[0].body);
+        assert!(body.contains("U_GT"));
+
+        // 十进制放不下int的字面量是long，十六进制的按顺序再试无符号类型
+        let ir = compile(String::from("
+            long big = 5000000000;
+            long min = -2147483648;
+            long wide = 0x100000000;
+        ")).unwrap();
+        assert_eq!(8, ir.data["big"][0].size);
+        assert!(format!("{:?}", ir.data["min"]).contains("Int { value: -2147483648 }"));
+        assert!(format!("{:?}", ir.data["wide"]).contains("Int { value: 4294967296 }"));
+
+        let err = compile(String::from("long x = 9223372036854775808;")).unwrap_err();
+        assert!(matches!(err, CompileError::Parse(e) if e.error == ParseErrorType::Lexical(LexicalErrorType::NumberOverflow)));
+
+        let err = compile(String::from("
+            int main() {
+                int *x;
+                x = 2.0f;
+                return 0;
+            }
+        ")).unwrap_err();
+        assert!(matches!(err, CompileError::Semantic(e) if matches!(e.error, SemanticErrorType::IncompatibleTypes(_, _))));
+    }
+
+    #[test]
+    fn test_compile_arithmetic_conversion() {
+        // 不同的算术类型之间可以赋值、初始化和一起运算
+        let ir = compile(String::from("
+            long y = 1;
+            unsigned long long x = 1;
+            char c = 1;
+            double d = 1;
+
+            int main() {
+                unsigned int u;
+                long l;
+                short s;
+                y = 1;
+                c = y + c;
+                d = d * 2 + c;
+                l = u;
+                s = sizeof(l);
+                if (u > l) {
+                    return 1;
+                }
+                if (u > 1) {
+                    return 2;
+                }
+                return x;
+            }
+        ")).unwrap();
+        // unsigned int和long比较时转换成long，和int比较时转换成unsigned int
+        let body = format!("{:?}", ir.functions[0].body);
+        assert_eq!(1, body.matches("U_GT").count());
+    }
+
+    #[test]
+    fn test_compile_error() {
+        let err = compile(String::from("int main() { int a = 1 }")).unwrap_err();
+        assert!(matches!(err, CompileError::Parse(_)));
+
+        let err = compile(String::from("int main() { b = 1; return 0; }")).unwrap_err();
+        assert!(matches!(err, CompileError::Semantic(_)));
+    }
+}
@@ -1,5 +1,34 @@
+// 项目里的函数统一用显式的return返回值
+#![allow(clippy::needless_return)]
+
 mod commandline;
+mod driver;
+
+use std::process;
+use std::env;
+use std::io:: {
+    self,
+    IsTerminal,
+};
 
 fn main() {
-    
+    let (config, content) = commandline::run();
+    let (filename, tab_width) = (config.filename, config.tab_width);
+    // 输出到终端并且没有设置NO_COLOR时才加颜色
+    let color = io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+
+    let (text, map) = match driver::preprocess(&filename, &content, tab_width) {
+        Ok(expanded) => expanded,
+        Err(err) => fail(&err.diagnostic(tab_width).render_with_tab_width(&filename, &content, color, tab_width)),
+    };
+
+    match driver::compile(text.clone(), tab_width) {
+        Ok(ir) => println!("{:#?}", ir),
+        Err(err) => fail(&err.diagnostic(tab_width).render_mapped(&filename, &text, &map, color)),
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprint!("{}", message);
+    process::exit(1);
 }